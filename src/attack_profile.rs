@@ -1,25 +1,241 @@
-use crate::{HitResult, RollCollection};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
+use crate::damage::TypedDamage;
+use crate::dice::{Dice, DiceBuilder};
+use crate::distribution::Distribution;
+use crate::notation::NotationError;
+use crate::rider::Rider;
+use crate::saving_throw::SavingThrow;
+use crate::target::Target;
+use crate::{FollowUpTrigger, HitResult, RollCollection, Ruleset, SaveResult};
+
+/// The DC of the flat check made at the end of each round to end persistent damage
+const PERSISTENT_FLAT_CHECK_DC: i32 = 15;
+
+/// The roll which decides the outcome of an attack
+#[derive(Debug, PartialEq)]
+pub enum AttackKind {
+    AttackRoll(RollCollection),
+    SavingThrow(SavingThrow),
+}
+
+impl AttackKind {
+    /// Take an input string from the user and parse into an AttackKind.
+    ///
+    /// Notation beginning with `dc` is parsed as a SavingThrow, which may refer to the
+    /// saves of the target by name, and all other notation as an attack roll.
+    ///
+    /// # Examples
+    /// ```
+    /// let attack_roll = AttackKind::parse_user_input("1d20+7", Ruleset::DND5e, &target)?;
+    /// let saving_throw = AttackKind::parse_user_input("dc15:1d20+2", Ruleset::DND5e, &target)?;
+    /// ```
+    pub fn parse_user_input(
+        notation: &str,
+        rule_mode: Ruleset,
+        target: &Target,
+    ) -> Result<AttackKind, NotationError> {
+        match notation.starts_with("dc") {
+            true => Ok(AttackKind::SavingThrow(SavingThrow::parse_user_input(
+                notation, rule_mode, target,
+            )?)),
+            false => Ok(AttackKind::AttackRoll(RollCollection::parse_user_input(
+                notation, rule_mode,
+            )?)),
+        }
+    }
+}
+
+/// The requirement for a follow-up attack to be made, on the hit result of an earlier attack in the turn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AttackCondition {
+    attack_index: usize,
+    trigger: FollowUpTrigger,
+}
+
+impl AttackCondition {
+    /// Take an input string from the user and separate any condition from the attack notation.
+    ///
+    /// Attack notation prefixed with `hit:`, `crit:`, or `miss:` is a follow-up attack,
+    /// made only if the preceding attack hits (including critical hits), critically hits,
+    /// or misses. The prefix can name the earlier attack by its number in the sequence of
+    /// attacks, such as `crit1:` for the first attack. Returns the condition, or None if
+    /// the attack is always made, along with the remaining attack notation. Returns an
+    /// error if the prefix does not refer to an earlier attack.
+    ///
+    /// # Examples
+    /// ```
+    /// // The third attack of the turn, made if the first attack critically hits
+    /// let (condition, attack_notation) = AttackCondition::parse_user_input("crit1:1d20+7", 2)?;
+    /// assert_eq!("1d20+7", attack_notation);
+    /// ```
+    pub fn parse_user_input(
+        notation: &str,
+        attack_index: usize,
+    ) -> Result<(Option<AttackCondition>, &str), NotationError> {
+        let (prefix, remainder) = match notation.split_once(':') {
+            Some(split) => split,
+            None => return Ok((None, notation)),
+        };
+
+        let trigger_prefixes = [
+            ("hit", FollowUpTrigger::OnHit),
+            ("crit", FollowUpTrigger::OnCrit),
+            ("miss", FollowUpTrigger::OnMiss),
+        ];
+        let (trigger, reference) = match trigger_prefixes
+            .into_iter()
+            .find_map(|(word, trigger)| prefix.strip_prefix(word).map(|r| (trigger, r)))
+        {
+            Some(t) => t,
+            None => return Ok((None, notation)),
+        };
+
+        let referenced_index = match reference {
+            "" => attack_index.checked_sub(1),
+            n => n.parse::<usize>().ok().and_then(|n| n.checked_sub(1)),
+        };
+        match referenced_index {
+            Some(i) if i < attack_index => Ok((
+                Some(AttackCondition {
+                    attack_index: i,
+                    trigger,
+                }),
+                remainder,
+            )),
+            _ => Err(NotationError::new(
+                notation,
+                prefix.len() - reference.len(),
+                reference,
+                "the number of an earlier attack, such as crit1:",
+            )),
+        }
+    }
+
+    /// Test whether the condition is met by the hit results of the earlier attacks in the turn.
+    ///
+    /// An attack which was not made, as its own condition was not met, meets no conditions.
+    ///
+    /// # Examples
+    /// ```
+    /// let (condition, _) = AttackCondition::parse_user_input("crit:1d20+7", 1)?;
+    ///
+    /// assert!(condition.unwrap().is_met(&[Some(HitResult::CriticalHit)]));
+    /// ```
+    pub fn is_met(&self, hit_results: &[Option<HitResult>]) -> bool {
+        matches!(
+            (self.trigger, hit_results[self.attack_index]),
+            (
+                FollowUpTrigger::OnHit,
+                Some(HitResult::Hit | HitResult::CriticalHit)
+            ) | (FollowUpTrigger::OnCrit, Some(HitResult::CriticalHit))
+                | (FollowUpTrigger::OnMiss, Some(HitResult::Miss))
+        )
+    }
+}
+
+/// The per-turn tallies of an AttackProfile
+///
+/// The damage taken from each instance of damage is also recorded, in the order dealt.
+#[derive(Debug, PartialEq)]
+pub struct TurnResult {
+    pub crits: i32,
+    pub hits: i32,
+    pub raw_damage: i32,
+    pub damage: i32,
+    pub rider_damage: i32,
+    pub persistent_damage: i32,
+    pub bonus_roll: i32,
+    pub triggered_attacks: i32,
+    pub damage_by_type: TypedDamage,
+    pub instance_damage: Vec<i32>,
+}
+
+/// The exact distributions of the per-turn tallies of an AttackProfile
+///
+/// The damage is only split by type when the profile deals typed damage.
+#[derive(Debug, PartialEq)]
+pub struct TurnDistribution {
+    pub hits: Distribution,
+    pub crits: Distribution,
+    pub damage: Distribution,
+    pub rider_damage: Distribution,
+    pub bonus_roll: Distribution,
+    pub damage_by_type: BTreeMap<Option<String>, Distribution>,
+}
+
+/// The portion of the damage dealt in a turn which is tallied by AttackProfile::damage_distribution()
+#[derive(Clone, Copy)]
+enum DamageTally<'a> {
+    All,
+    Riders,
+    Type(&'a Option<String>),
+}
+
+/// A representation of a sequence of attacks made against a target each round
+///
+/// Follow-up attacks are only made if the condition paired with them is met, while
+/// attacks without a condition are always made.
+///
+/// Persistent damage inflicted on the target carries over between rounds, keyed by its
+/// damage type and recording the rider which inflicted it along with the hit result of
+/// the attack which triggered the rider.
 #[derive(Debug, PartialEq)]
 pub struct AttackProfile {
-    pub target_ac: i32,
-    hit_collection: Vec<RollCollection>,
+    pub target: Target,
+    attacks: Vec<AttackKind>,
     damage_collection: Vec<RollCollection>,
+    conditions: Vec<Option<AttackCondition>>,
+    riders: Vec<Rider>,
+    flat_check: Dice,
+    persistent_effects: BTreeMap<Option<String>, (usize, HitResult)>,
+    ruleset: Ruleset,
 }
 
 impl AttackProfile {
     pub fn new(
-        target_ac: i32,
-        hit_collection: Vec<RollCollection>,
+        target: Target,
+        attacks: Vec<AttackKind>,
         damage_collection: Vec<RollCollection>,
+        ruleset: Ruleset,
     ) -> AttackProfile {
+        let conditions = vec![None; attacks.len()];
         AttackProfile {
-            target_ac,
-            hit_collection,
+            target,
+            attacks,
             damage_collection,
+            conditions,
+            riders: Vec::new(),
+            flat_check: DiceBuilder::new().set_roll_max(20).build(),
+            persistent_effects: BTreeMap::new(),
+            ruleset,
         }
     }
 
+    /// Attach once-per-turn damage riders to the AttackProfile.
+    ///
+    /// # Examples
+    /// ```
+    /// let rider = Rider::parse_user_input("3d6", Ruleset::DND5e)?;
+    /// let attack_profile = AttackProfile::new(Target::from_ac(10), vec![AttackKind::AttackRoll(hit_context)], vec![dmg_context], Ruleset::DND5e).set_riders(vec![rider]);
+    /// ```
+    pub fn set_riders(mut self, riders: Vec<Rider>) -> Self {
+        self.riders = riders;
+        self
+    }
+
+    /// Attach the conditions of follow-up attacks to the AttackProfile, one for each attack.
+    ///
+    /// # Examples
+    /// ```
+    /// let (condition, _) = AttackCondition::parse_user_input("crit:1d20+7", 1)?;
+    /// let attack_profile = AttackProfile::new(Target::from_ac(10), attacks, damage_collection, Ruleset::DND5e).set_conditions(vec![None, condition]);
+    /// ```
+    pub fn set_conditions(mut self, conditions: Vec<Option<AttackCondition>>) -> Self {
+        self.conditions = conditions;
+        self
+    }
+
     /// Iterate through the hit/damage DiceContext pairs and return the damage dealt.
     ///
     /// Uses the internal AC value to test each hit against, then rolls damage according
@@ -27,6 +243,27 @@ impl AttackProfile {
     /// attacks per turn of combat. Records the number of critical/regular hits in the
     /// turn rolled for tallying purposes.
     ///
+    /// Attacks made as a saving throw ignore the AC, and are resolved by the degree of
+    /// success of the target's save using AttackProfile::resolve_save(). A failed save
+    /// counts as a hit, and a critical failure as a critical hit.
+    ///
+    /// A follow-up attack is skipped unless its condition is met by the earlier attacks in
+    /// the turn, and the number of follow-up attacks made is tallied.
+    ///
+    /// Each rider is applied to the first attack in the turn which satisfies its trigger,
+    /// and its damage is included in the total as well as reported separately. The bonus
+    /// dice added to each attack roll, such as Bless, are also totalled across the turn,
+    /// and the total damage is split by damage type.
+    ///
+    /// The damage of each attack, including any riders applied to it, is a single instance
+    /// of damage against which the defences of the target are applied. The damage before
+    /// defences is also recorded, and rider damage is the extra damage taken from the riders.
+    ///
+    /// Persistent riders are not included in the damage of the attack, and instead inflict
+    /// persistent damage using AttackProfile::inflict_persistent_damage(). The persistent
+    /// damage is then dealt at the end of the round by AttackProfile::roll_persistent_damage(),
+    /// and is included in the total.
+    ///
     /// # Examples
     /// ```
     /// // Create representation of a flat 1d20 roll to hit for a 1d8 weapon
@@ -36,43 +273,442 @@ impl AttackProfile {
     /// let dmg_die = DiceBuilder::new().roll_max(8).build();
     /// let dmg_context = RollCollection::new(vec![dmg_die], vec![]);
     ///
-    /// let attack_profile = AttackProfile::new(10, vec![hit_context], vec![dmg_context]);
-    /// let (n_crits, n_hits, damage_dealt) = attack_profile.roll_turn(&mut roll_element);
+    /// let attack_profile = AttackProfile::new(Target::from_ac(10), vec![AttackKind::AttackRoll(hit_collection)], vec![dmg_context], Ruleset::DND5e);
+    /// let turn_result = attack_profile.roll_turn();
     /// ```
-    pub fn roll_turn(&mut self) -> (i32, i32, i32) {
-        // Declare counters for the results - number of crits, number of hits, total damage
-        let mut crit_counter = 0;
-        let mut hit_counter = 0;
-        let mut total_damage = 0;
+    pub fn roll_turn(&mut self) -> TurnResult {
+        let mut turn_result = TurnResult {
+            crits: 0,
+            hits: 0,
+            raw_damage: 0,
+            damage: 0,
+            rider_damage: 0,
+            persistent_damage: 0,
+            bonus_roll: 0,
+            triggered_attacks: 0,
+            damage_by_type: TypedDamage::new(),
+            instance_damage: Vec::new(),
+        };
+        let mut rider_applied = vec![false; self.riders.len()];
+        let mut persistent_triggers: Vec<(usize, HitResult)> = Vec::new();
+        let mut hit_results: Vec<Option<HitResult>> = Vec::new();
 
         // For each hit/damage in the sequence, compute results
-        for (hit_collection, dmg_collection) in self
-            .hit_collection
+        for ((attack, dmg_collection), condition) in self
+            .attacks
             .iter_mut()
             .zip(self.damage_collection.iter_mut())
+            .zip(self.conditions.iter())
         {
-            let hit_result = hit_collection.roll_against_armour_class(self.target_ac);
-            total_damage += dmg_collection.roll_damage_result(&hit_result);
+            if let Some(condition) = condition {
+                if !condition.is_met(&hit_results) {
+                    hit_results.push(None);
+                    continue;
+                }
+                turn_result.triggered_attacks += 1;
+            }
+
+            let (hit_result, attack_damage) = match attack {
+                AttackKind::AttackRoll(hit_collection) => {
+                    let (hit_result, bonus_roll) =
+                        hit_collection.roll_against_armour_class(self.target.ac);
+                    turn_result.bonus_roll += bonus_roll;
+                    (hit_result, dmg_collection.roll_damage_result(&hit_result))
+                }
+                AttackKind::SavingThrow(saving_throw) => {
+                    let (hit_result, halved) =
+                        AttackProfile::resolve_save(saving_throw.roll_save());
+                    let attack_damage = match (hit_result, halved) {
+                        (HitResult::Miss, false) => TypedDamage::new(),
+                        (_, false) => dmg_collection.roll_damage_result(&hit_result),
+                        (_, true) => dmg_collection.roll_damage_result(&HitResult::Hit).halve(),
+                    };
+                    (hit_result, attack_damage)
+                }
+            };
+
+            let mut instance_damage = attack_damage.clone();
+            for (i, (rider, applied)) in self
+                .riders
+                .iter_mut()
+                .zip(rider_applied.iter_mut())
+                .enumerate()
+            {
+                if !*applied && rider.triggers_on(&hit_result) {
+                    match rider.is_persistent() {
+                        true => persistent_triggers.push((i, hit_result)),
+                        false => instance_damage.merge(&rider.roll_damage_result(&hit_result)),
+                    }
+                    *applied = true;
+                }
+            }
+
+            let damage_taken = self.target.apply_defences(&instance_damage, self.ruleset);
+            let attack_damage_taken = self
+                .target
+                .apply_defences(&attack_damage, self.ruleset)
+                .total();
+            turn_result.raw_damage += instance_damage.total();
+            turn_result.damage += damage_taken.total();
+            turn_result.rider_damage += damage_taken.total() - attack_damage_taken;
+            turn_result.damage_by_type.merge(&damage_taken);
+            turn_result.instance_damage.push(damage_taken.total());
+            hit_results.push(Some(hit_result));
 
             match hit_result {
                 HitResult::CriticalHit => {
-                    crit_counter += 1;
-                    hit_counter += 1;
+                    turn_result.crits += 1;
+                    turn_result.hits += 1;
                 }
                 HitResult::Hit => {
-                    hit_counter += 1;
+                    turn_result.hits += 1;
                 }
                 HitResult::Miss => (),
             }
         }
-        (crit_counter, hit_counter, total_damage)
+
+        for (rider_index, hit_result) in persistent_triggers {
+            self.inflict_persistent_damage(rider_index, hit_result);
+        }
+        self.roll_persistent_damage(&mut turn_result);
+
+        turn_result
+    }
+
+    /// Return true if any of the attacks are follow-up attacks, made only if their condition is met.
+    ///
+    /// # Examples
+    /// ```
+    /// let attack_profile = AttackProfile::new(target, attacks, damage_collection, Ruleset::DND5e).set_conditions(conditions);
+    /// let has_follow_ups = attack_profile.has_conditional_attacks();
+    /// ```
+    pub fn has_conditional_attacks(&self) -> bool {
+        self.conditions.iter().any(|c| c.is_some())
+    }
+
+    /// Return true if any of the riders deal persistent damage.
+    ///
+    /// # Examples
+    /// ```
+    /// let rider = Rider::parse_user_input("persistent:1d6 fire", Ruleset::PF2e)?;
+    /// let attack_profile = AttackProfile::new(target, attacks, damage_collection, Ruleset::PF2e).set_riders(vec![rider]);
+    ///
+    /// assert!(attack_profile.has_persistent_damage());
+    /// ```
+    pub fn has_persistent_damage(&self) -> bool {
+        self.riders.iter().any(|r| r.is_persistent())
+    }
+
+    /// End any persistent damage on the target, ready to simulate a new sequence of rounds.
+    ///
+    /// # Examples
+    /// ```
+    /// attack_profile.reset_persistent_damage();
+    /// ```
+    pub fn reset_persistent_damage(&mut self) {
+        self.persistent_effects.clear();
+    }
+
+    /// Inflict the persistent damage of a rider on the target.
+    ///
+    /// Persistent damage does not stack with itself, so if the target already has
+    /// persistent damage of the same type, only the effect with the higher average
+    /// damage is kept.
+    ///
+    /// # Examples
+    /// ```
+    /// attack_profile.inflict_persistent_damage(0, HitResult::CriticalHit);
+    /// ```
+    fn inflict_persistent_damage(&mut self, rider_index: usize, hit_result: HitResult) {
+        let average_damage = |(index, hit_result): &(usize, HitResult)| {
+            self.riders[*index].damage_distribution(hit_result).mean()
+        };
+
+        let damage_type = self.riders[rider_index].persistent_type();
+        let new_effect = (rider_index, hit_result);
+        let replace_effect = match self.persistent_effects.get(&damage_type) {
+            Some(effect) => average_damage(&new_effect) > average_damage(effect),
+            None => true,
+        };
+
+        if replace_effect {
+            self.persistent_effects.insert(damage_type, new_effect);
+        }
+    }
+
+    /// Deal the persistent damage on the target at the end of a round.
+    ///
+    /// Each persistent effect is a separate instance of damage against which the defences
+    /// of the target are applied. After taking the damage of each effect, the target
+    /// attempts a DC 15 flat check, ending the effect on a success. The damage is added
+    /// to the tallies of the turn.
+    ///
+    /// # Examples
+    /// ```
+    /// attack_profile.roll_persistent_damage(&mut turn_result);
+    /// ```
+    fn roll_persistent_damage(&mut self, turn_result: &mut TurnResult) {
+        let mut ended_effects: Vec<Option<String>> = Vec::new();
+
+        for (damage_type, (rider_index, hit_result)) in &self.persistent_effects {
+            let effect_damage = self.riders[*rider_index].roll_damage_result(hit_result);
+            let damage_taken = self.target.apply_defences(&effect_damage, self.ruleset);
+            turn_result.raw_damage += effect_damage.total();
+            turn_result.damage += damage_taken.total();
+            turn_result.persistent_damage += damage_taken.total();
+            turn_result.damage_by_type.merge(&damage_taken);
+            turn_result.instance_damage.push(damage_taken.total());
+
+            if self.flat_check.evaluate_result(None) >= PERSISTENT_FLAT_CHECK_DC {
+                ended_effects.push(damage_type.clone());
+            }
+        }
+
+        for damage_type in ended_effects {
+            self.persistent_effects.remove(&damage_type);
+        }
+    }
+
+    /// Return every damage type dealt by the attacks and riders, including None for untyped damage.
+    ///
+    /// # Examples
+    /// ```
+    /// let damage_types = attack_profile.damage_types();
+    /// ```
+    pub fn damage_types(&self) -> BTreeSet<Option<String>> {
+        self.damage_collection
+            .iter()
+            .flat_map(|d| d.damage_types())
+            .chain(self.riders.iter().flat_map(|r| r.damage_types()))
+            .collect()
+    }
+
+    /// Return the damage types which the damage of a turn is split into when reported.
+    ///
+    /// Empty if all of the damage is untyped, as the split would only repeat the total.
+    ///
+    /// # Examples
+    /// ```
+    /// for damage_type in attack_profile.reported_damage_types() {
+    ///     println!("{:?}", damage_type);
+    /// }
+    /// ```
+    pub fn reported_damage_types(&self) -> Vec<Option<String>> {
+        let damage_types = self.damage_types();
+        match damage_types.iter().any(|t| t.is_some()) {
+            true => damage_types.into_iter().collect(),
+            false => Vec::new(),
+        }
+    }
+
+    /// Convert the target's degree of success on a saving throw into the hit result of the attack.
+    ///
+    /// Returns the hit result for which damage is rolled, and whether that damage is halved.
+    /// A critical failure takes critical damage (double damage under Pathfinder 2e rules),
+    /// a failure takes full damage, and a success takes half of the full damage without
+    /// counting as a hit. A critical success takes no damage.
+    ///
+    /// # Examples
+    /// ```
+    /// let (hit_result, halved) = AttackProfile::resolve_save(SaveResult::Success);
+    /// ```
+    fn resolve_save(save_result: SaveResult) -> (HitResult, bool) {
+        match save_result {
+            SaveResult::CriticalFailure => (HitResult::CriticalHit, false),
+            SaveResult::Failure => (HitResult::Hit, false),
+            SaveResult::Success => (HitResult::Miss, true),
+            SaveResult::CriticalSuccess => (HitResult::Miss, false),
+        }
+    }
+
+    /// Return the exact probability of each outcome of an attack, with the damage dealt.
+    ///
+    /// Each outcome is the hit result of the attack, the distribution of its total bonus
+    /// dice, and the distribution of its damage, along with the probability of the outcome.
+    /// A missed attack roll deals only its on-miss damage. A saving throw is resolved in
+    /// the same way as AttackProfile::roll_turn(), and has no bonus dice. Only the tallied
+    /// portion of the damage is included, so the damage distributions are zero when
+    /// tallying rider damage.
+    ///
+    /// # Examples
+    /// ```
+    /// let outcomes = AttackProfile::attack_outcomes(&attack, &dmg_collection, 15, DamageTally::All);
+    /// ```
+    fn attack_outcomes(
+        attack: &AttackKind,
+        dmg_collection: &RollCollection,
+        target_ac: i32,
+        tally: DamageTally,
+    ) -> Vec<(HitResult, Distribution, Distribution, f64)> {
+        // Halved damage halves the total once, mirroring TypedDamage::halve()
+        let damage_for = |hit_result: &HitResult, halved: bool| match (tally, hit_result, halved) {
+            (DamageTally::Riders, _, _) => Distribution::point(0),
+            (DamageTally::All, _, false) => dmg_collection.damage_distribution(hit_result),
+            (DamageTally::All, _, true) => {
+                dmg_collection.damage_distribution(&HitResult::Hit).halve()
+            }
+            (DamageTally::Type(t), _, false) => {
+                dmg_collection.type_damage_distribution(hit_result, t)
+            }
+            (DamageTally::Type(t), _, true) => dmg_collection.halved_type_damage_distribution(t),
+        };
+
+        match attack {
+            AttackKind::AttackRoll(hit_collection) => hit_collection
+                .attack_outcomes(target_ac)
+                .into_iter()
+                .map(|(hit_result, bonus, probability)| {
+                    let damage = damage_for(&hit_result, false);
+                    (hit_result, Distribution::point(bonus), damage, probability)
+                })
+                .collect(),
+            AttackKind::SavingThrow(saving_throw) => saving_throw
+                .save_probabilities()
+                .into_iter()
+                .map(|(save_result, probability)| {
+                    let (hit_result, halved) = AttackProfile::resolve_save(save_result);
+                    let damage = match (hit_result, halved) {
+                        (HitResult::Miss, false) => Distribution::point(0),
+                        (_, _) => damage_for(&hit_result, halved),
+                    };
+                    (hit_result, Distribution::point(0), damage, probability)
+                })
+                .collect(),
+        }
+    }
+
+    /// Compute the exact distributions of the tallies returned by AttackProfile::roll_turn().
+    ///
+    /// Each attack in the sequence is independent, so the hit, critical hit and bonus dice
+    /// distributions are obtained by convolving the distribution of each attack. The
+    /// damage distributions depend on which riders have already been applied in the
+    /// turn, so are computed by AttackProfile::damage_distribution().
+    ///
+    /// # Examples
+    /// ```
+    /// let hit_collection = RollCollection::parse_user_input("1d20+5", Ruleset::DND5e)?;
+    /// let dmg_collection = RollCollection::parse_user_input("1d8+3", Ruleset::DND5e)?;
+    ///
+    /// let attack_profile = AttackProfile::new(Target::from_ac(15), vec![hit_collection], vec![dmg_collection], Ruleset::DND5e);
+    /// let turn_distribution = attack_profile.turn_distribution();
+    /// ```
+    pub fn turn_distribution(&self) -> TurnDistribution {
+        let mut hits = Distribution::point(0);
+        let mut crits = Distribution::point(0);
+        let mut bonus_roll = Distribution::point(0);
+
+        for (attack, dmg_collection) in self.attacks.iter().zip(self.damage_collection.iter()) {
+            let mut p_crit = 0.0;
+            let mut p_hit = 0.0;
+            let mut attack_bonus = Distribution::empty();
+
+            for (hit_result, bonus, _, probability) in AttackProfile::attack_outcomes(
+                attack,
+                dmg_collection,
+                self.target.ac,
+                DamageTally::Riders,
+            ) {
+                attack_bonus = attack_bonus.add(&bonus.scale(probability));
+                match hit_result {
+                    HitResult::CriticalHit => {
+                        p_crit += probability;
+                        p_hit += probability;
+                    }
+                    HitResult::Hit => p_hit += probability,
+                    HitResult::Miss => (),
+                }
+            }
+
+            hits = hits.convolve(&Distribution::new(0, vec![1.0 - p_hit, p_hit]));
+            crits = crits.convolve(&Distribution::new(0, vec![1.0 - p_crit, p_crit]));
+            bonus_roll = bonus_roll.convolve(&attack_bonus);
+        }
+
+        TurnDistribution {
+            hits,
+            crits,
+            damage: self.damage_distribution(DamageTally::All),
+            rider_damage: self.damage_distribution(DamageTally::Riders),
+            bonus_roll,
+            damage_by_type: self
+                .reported_damage_types()
+                .into_iter()
+                .map(|t| {
+                    let dist = self.damage_distribution(DamageTally::Type(&t));
+                    (t, dist)
+                })
+                .collect(),
+        }
+    }
+
+    /// Compute the exact distribution of the damage dealt over a turn.
+    ///
+    /// Tracks the damage distribution separately for each combination of riders which
+    /// have already been applied in the turn, since an applied rider cannot trigger again.
+    /// Each attack branches the distribution over the outcomes returned by
+    /// AttackProfile::attack_outcomes(), weighted by the probability of each outcome. Either
+    /// all of the damage, only the rider damage, or only the damage of a single type is
+    /// tallied.
+    ///
+    /// # Examples
+    /// ```
+    /// let total_damage = attack_profile.damage_distribution(DamageTally::All);
+    /// let rider_damage = attack_profile.damage_distribution(DamageTally::Riders);
+    /// ```
+    fn damage_distribution(&self, tally: DamageTally) -> Distribution {
+        // Keyed by a vector flagging which riders have been applied
+        let mut rider_states: HashMap<Vec<bool>, Distribution> =
+            HashMap::from([(vec![false; self.riders.len()], Distribution::point(0))]);
+
+        for (attack, dmg_collection) in self.attacks.iter().zip(self.damage_collection.iter()) {
+            let mut next_states: HashMap<Vec<bool>, Distribution> = HashMap::new();
+
+            for (hit_result, _, attack_damage, probability) in
+                AttackProfile::attack_outcomes(attack, dmg_collection, self.target.ac, tally)
+            {
+                if probability == 0.0 {
+                    continue;
+                }
+
+                for (rider_applied, state_damage) in &rider_states {
+                    let mut next_applied = rider_applied.clone();
+                    let mut outcome_damage = state_damage.convolve(&attack_damage);
+
+                    for (i, rider) in self.riders.iter().enumerate() {
+                        if !rider_applied[i] && rider.triggers_on(&hit_result) {
+                            let rider_damage = match tally {
+                                DamageTally::Type(t) => {
+                                    rider.type_damage_distribution(&hit_result, t)
+                                }
+                                _ => rider.damage_distribution(&hit_result),
+                            };
+                            outcome_damage = outcome_damage.convolve(&rider_damage);
+                            next_applied[i] = true;
+                        }
+                    }
+
+                    let next_damage = next_states
+                        .entry(next_applied)
+                        .or_insert_with(Distribution::empty);
+                    *next_damage = next_damage.add(&outcome_damage.scale(probability));
+                }
+            }
+            rider_states = next_states;
+        }
+
+        rider_states
+            .values()
+            .fold(Distribution::empty(), |total, dist| total.add(dist))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{dice::DiceBuilder, static_modifier::StaticModifier, ModifierBehaviour, Ruleset};
+    use crate::{
+        dice::DiceBuilder, static_modifier::StaticModifier, ModifierBehaviour, RiderTrigger,
+        Ruleset,
+    };
 
     fn spawn_roll_collections(
         dice_pairs: Vec<(i32, i32)>,
@@ -80,8 +716,7 @@ mod tests {
         rule_mode: Ruleset,
     ) -> Vec<RollCollection> {
         let mut roll_collections: Vec<RollCollection> = Vec::new();
-        for (dice_values, modifier_value) in dice_pairs.into_iter().zip(modifier_values.into_iter())
-        {
+        for (dice_values, modifier_value) in dice_pairs.into_iter().zip(modifier_values) {
             let (min_value, max_value) = dice_values;
 
             let die = DiceBuilder::new()
@@ -95,16 +730,134 @@ mod tests {
         roll_collections
     }
 
+    fn spawn_attack_rolls(
+        dice_pairs: Vec<(i32, i32)>,
+        modifier_values: Vec<i32>,
+        rule_mode: Ruleset,
+    ) -> Vec<AttackKind> {
+        spawn_roll_collections(dice_pairs, modifier_values, rule_mode)
+            .into_iter()
+            .map(AttackKind::AttackRoll)
+            .collect()
+    }
+
+    fn untyped_damage(damage_value: i32) -> TypedDamage {
+        let mut typed_damage = TypedDamage::new();
+        typed_damage.add(&None, damage_value);
+        typed_damage
+    }
+
+    // region: AttackKind::parse_user_input() tests
+
+    #[test]
+    fn test_attack_kind_parse_user_input() {
+        let exp_attack = AttackKind::AttackRoll(
+            RollCollection::parse_user_input("1d20+5", Ruleset::DND5e).unwrap(),
+        );
+        let obs_attack =
+            AttackKind::parse_user_input("1d20+5", Ruleset::DND5e, &Target::from_ac(10)).unwrap();
+        assert_eq!(exp_attack, obs_attack);
+
+        let exp_attack = AttackKind::SavingThrow(
+            SavingThrow::parse_user_input("dc15:1d20+2", Ruleset::DND5e, &Target::from_ac(10))
+                .unwrap(),
+        );
+        let obs_attack =
+            AttackKind::parse_user_input("dc15:1d20+2", Ruleset::DND5e, &Target::from_ac(10))
+                .unwrap();
+        assert_eq!(exp_attack, obs_attack);
+    }
+
+    // endregion:
+
+    // region: AttackCondition tests
+
+    #[test]
+    fn test_condition_parse_user_input() {
+        let exp_condition = AttackCondition {
+            attack_index: 0,
+            trigger: FollowUpTrigger::OnCrit,
+        };
+        let (obs_condition, obs_notation) =
+            AttackCondition::parse_user_input("crit1:1d20+5", 2).unwrap();
+        assert_eq!(Some(exp_condition), obs_condition);
+        assert_eq!("1d20+5", obs_notation);
+    }
+
+    #[test]
+    fn test_condition_parse_user_input_previous() {
+        // Test that a condition without an attack number refers to the preceding attack
+        let exp_condition = AttackCondition {
+            attack_index: 1,
+            trigger: FollowUpTrigger::OnMiss,
+        };
+        let (obs_condition, obs_notation) =
+            AttackCondition::parse_user_input("miss:dc15:1d20+2", 2).unwrap();
+        assert_eq!(Some(exp_condition), obs_condition);
+        assert_eq!("dc15:1d20+2", obs_notation);
+    }
+
+    #[test]
+    fn test_condition_parse_user_input_unconditional() {
+        for notation in ["1d20+5", "dc15:1d20+2"] {
+            let (obs_condition, obs_notation) =
+                AttackCondition::parse_user_input(notation, 1).unwrap();
+            assert_eq!(None, obs_condition);
+            assert_eq!(notation, obs_notation);
+        }
+    }
+
+    #[test]
+    fn test_condition_parse_user_input_invalid() {
+        // Test that a condition must refer to an earlier attack
+        let obs_error = AttackCondition::parse_user_input("hit:1d20+5", 0).unwrap_err();
+        assert_eq!(3, obs_error.position);
+        assert_eq!("", obs_error.token);
+
+        let obs_error = AttackCondition::parse_user_input("crit2:1d20+5", 1).unwrap_err();
+        assert_eq!(4, obs_error.position);
+        assert_eq!("2", obs_error.token);
+    }
+
+    #[test]
+    fn test_condition_is_met() {
+        let hit_results = [Some(HitResult::CriticalHit), Some(HitResult::Miss), None];
+        let spawn_condition = |attack_index, trigger| AttackCondition {
+            attack_index,
+            trigger,
+        };
+
+        assert!(spawn_condition(0, FollowUpTrigger::OnHit).is_met(&hit_results));
+        assert!(spawn_condition(0, FollowUpTrigger::OnCrit).is_met(&hit_results));
+        assert!(!spawn_condition(0, FollowUpTrigger::OnMiss).is_met(&hit_results));
+        assert!(spawn_condition(1, FollowUpTrigger::OnMiss).is_met(&hit_results));
+        assert!(!spawn_condition(1, FollowUpTrigger::OnHit).is_met(&hit_results));
+        // An attack which was not made neither hits nor misses
+        assert!(!spawn_condition(2, FollowUpTrigger::OnMiss).is_met(&hit_results));
+    }
+
+    // endregion:
+
     // region: roll_turn() single tests
 
     #[test]
     fn test_roll_turn_crit() {
         // Test the behaviour of the function when there is a single pair of dice in the attack profile which crit.
-        let hit_collection = spawn_roll_collections(vec![(20, 20)], vec![0], Ruleset::DND5e);
+        let hit_collection = spawn_attack_rolls(vec![(20, 20)], vec![0], Ruleset::DND5e);
         let damage_collection = spawn_roll_collections(vec![(1, 1)], vec![0], Ruleset::DND5e);
 
-        let mut attack_profile = AttackProfile::new(1, hit_collection, damage_collection);
-        let (obs_crit, obs_hit, obs_dmg) = attack_profile.roll_turn();
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(1),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        );
+        let TurnResult {
+            crits: obs_crit,
+            hits: obs_hit,
+            damage: obs_dmg,
+            ..
+        } = attack_profile.roll_turn();
 
         assert_eq!(obs_crit, 1);
         assert_eq!(obs_hit, 1);
@@ -114,11 +867,21 @@ mod tests {
     #[test]
     fn test_roll_turn_hit() {
         // Test the behaviour of the function when there is a single pair of dice in the attack profile.
-        let hit_collection = spawn_roll_collections(vec![(2, 5)], vec![0], Ruleset::DND5e);
+        let hit_collection = spawn_attack_rolls(vec![(2, 5)], vec![0], Ruleset::DND5e);
         let damage_collection = spawn_roll_collections(vec![(1, 1)], vec![0], Ruleset::DND5e);
 
-        let mut attack_profile = AttackProfile::new(1, hit_collection, damage_collection);
-        let (obs_crit, obs_hit, obs_dmg) = attack_profile.roll_turn();
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(1),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        );
+        let TurnResult {
+            crits: obs_crit,
+            hits: obs_hit,
+            damage: obs_dmg,
+            ..
+        } = attack_profile.roll_turn();
 
         assert_eq!(obs_crit, 0);
         assert_eq!(obs_hit, 1);
@@ -128,11 +891,21 @@ mod tests {
     #[test]
     fn test_roll_turn_miss() {
         // Test the behaviour of the function when there is a single pair of dice in the attack profile.
-        let hit_collection = spawn_roll_collections(vec![(2, 5)], vec![0], Ruleset::DND5e);
+        let hit_collection = spawn_attack_rolls(vec![(2, 5)], vec![0], Ruleset::DND5e);
         let damage_collection = spawn_roll_collections(vec![(1, 1)], vec![0], Ruleset::DND5e);
 
-        let mut attack_profile = AttackProfile::new(10, hit_collection, damage_collection);
-        let (obs_crit, obs_hit, obs_dmg) = attack_profile.roll_turn();
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(10),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        );
+        let TurnResult {
+            crits: obs_crit,
+            hits: obs_hit,
+            damage: obs_dmg,
+            ..
+        } = attack_profile.roll_turn();
 
         assert_eq!(obs_crit, 0);
         assert_eq!(obs_hit, 0);
@@ -147,12 +920,22 @@ mod tests {
     fn test_roll_turn_crit_multiple() {
         // Test the behaviour of the function when there are multiple pairs of dice which crit.
         let hit_collection =
-            spawn_roll_collections(vec![(11, 12), (13, 14)], vec![0, 0], Ruleset::PF2e);
+            spawn_attack_rolls(vec![(11, 12), (13, 14)], vec![0, 0], Ruleset::PF2e);
         let damage_collection =
             spawn_roll_collections(vec![(1, 1), (2, 2)], vec![0, 0], Ruleset::DND5e);
 
-        let mut attack_profile = AttackProfile::new(1, hit_collection, damage_collection);
-        let (obs_crit, obs_hit, obs_dmg) = attack_profile.roll_turn();
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(1),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        );
+        let TurnResult {
+            crits: obs_crit,
+            hits: obs_hit,
+            damage: obs_dmg,
+            ..
+        } = attack_profile.roll_turn();
 
         assert_eq!(obs_crit, 2);
         assert_eq!(obs_hit, 2);
@@ -163,13 +946,22 @@ mod tests {
     fn test_roll_turn_hit_multiple() {
         // Test the behaviour of the function when there are multiple pairs of dice in the attack profile
         // and they all hit.
-        let hit_collection =
-            spawn_roll_collections(vec![(2, 5), (2, 6)], vec![0, 0], Ruleset::DND5e);
+        let hit_collection = spawn_attack_rolls(vec![(2, 5), (2, 6)], vec![0, 0], Ruleset::DND5e);
         let damage_collection =
             spawn_roll_collections(vec![(1, 1), (1, 1)], vec![0, 0], Ruleset::DND5e);
 
-        let mut attack_profile = AttackProfile::new(1, hit_collection, damage_collection);
-        let (obs_crit, obs_hit, obs_dmg) = attack_profile.roll_turn();
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(1),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        );
+        let TurnResult {
+            crits: obs_crit,
+            hits: obs_hit,
+            damage: obs_dmg,
+            ..
+        } = attack_profile.roll_turn();
 
         assert_eq!(obs_crit, 0);
         assert_eq!(obs_hit, 2);
@@ -180,13 +972,22 @@ mod tests {
     fn test_roll_turn_miss_multiple() {
         // Test the behaviour of the function when there are multiple pairs of dice in the attack profile
         // and they all miss.
-        let hit_collection =
-            spawn_roll_collections(vec![(2, 5), (2, 6)], vec![0, 0], Ruleset::DND5e);
+        let hit_collection = spawn_attack_rolls(vec![(2, 5), (2, 6)], vec![0, 0], Ruleset::DND5e);
         let damage_collection =
             spawn_roll_collections(vec![(1, 1), (1, 1)], vec![0, 0], Ruleset::DND5e);
 
-        let mut attack_profile = AttackProfile::new(10, hit_collection, damage_collection);
-        let (obs_crit, obs_hit, obs_dmg) = attack_profile.roll_turn();
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(10),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        );
+        let TurnResult {
+            crits: obs_crit,
+            hits: obs_hit,
+            damage: obs_dmg,
+            ..
+        } = attack_profile.roll_turn();
 
         assert_eq!(obs_crit, 0);
         assert_eq!(obs_hit, 0);
@@ -200,7 +1001,7 @@ mod tests {
     #[test]
     fn test_roll_turn_crit_mixed() {
         // Test the behaviour of the function when there are a mix of pairs of dice which crit, hit, and miss.
-        let hit_collection = spawn_roll_collections(
+        let hit_collection = spawn_attack_rolls(
             vec![(20, 20), (12, 14), (1, 2)],
             vec![0, 0, 0],
             Ruleset::DND5e,
@@ -208,8 +1009,18 @@ mod tests {
         let damage_collection =
             spawn_roll_collections(vec![(2, 2), (1, 1), (1, 1)], vec![0, 0, 0], Ruleset::DND5e);
 
-        let mut attack_profile = AttackProfile::new(10, hit_collection, damage_collection);
-        let (obs_crit, obs_hit, obs_dmg) = attack_profile.roll_turn();
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(10),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        );
+        let TurnResult {
+            crits: obs_crit,
+            hits: obs_hit,
+            damage: obs_dmg,
+            ..
+        } = attack_profile.roll_turn();
 
         assert_eq!(obs_crit, 1);
         assert_eq!(obs_hit, 2);
@@ -220,18 +1031,774 @@ mod tests {
     fn test_roll_turn_mixed() {
         // Test the behaviour of the function when there are multiple pairs of dice in the attack profile
         // and one hits, one misses
-        let hit_collection =
-            spawn_roll_collections(vec![(2, 5), (12, 14)], vec![0, 0], Ruleset::DND5e);
+        let hit_collection = spawn_attack_rolls(vec![(2, 5), (12, 14)], vec![0, 0], Ruleset::DND5e);
         let damage_collection =
             spawn_roll_collections(vec![(1, 1), (1, 1)], vec![0, 0], Ruleset::DND5e);
 
-        let mut attack_profile = AttackProfile::new(10, hit_collection, damage_collection);
-        let (obs_crit, obs_hit, obs_dmg) = attack_profile.roll_turn();
-
-        assert_eq!(obs_crit, 0);
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(10),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        );
+        let TurnResult {
+            crits: obs_crit,
+            hits: obs_hit,
+            damage: obs_dmg,
+            ..
+        } = attack_profile.roll_turn();
+
+        assert_eq!(obs_crit, 0);
         assert_eq!(obs_hit, 1);
         assert_eq!(obs_dmg, 1);
     }
 
     // endregion:
+
+    // region: roll_turn() saving throw tests
+
+    fn spawn_save_profile(save_notation: &str, rule_mode: Ruleset) -> AttackProfile {
+        let saving_throw =
+            SavingThrow::parse_user_input(save_notation, rule_mode, &Target::from_ac(10)).unwrap();
+        let damage_collection = RollCollection::parse_user_input("1d1+3", rule_mode).unwrap();
+
+        AttackProfile::new(
+            Target::from_ac(10),
+            vec![AttackKind::SavingThrow(saving_throw)],
+            vec![damage_collection],
+            rule_mode,
+        )
+    }
+
+    #[test]
+    fn test_roll_turn_save_failure() {
+        // Test that a failed save deals full damage and counts as a hit.
+        let mut attack_profile = spawn_save_profile("dc20:1d1", Ruleset::DND5e);
+
+        let exp_result = TurnResult {
+            crits: 0,
+            hits: 1,
+            raw_damage: 4,
+            damage: 4,
+            rider_damage: 0,
+            persistent_damage: 0,
+            bonus_roll: 0,
+            triggered_attacks: 0,
+            damage_by_type: untyped_damage(4),
+            instance_damage: vec![4],
+        };
+        assert_eq!(exp_result, attack_profile.roll_turn());
+    }
+
+    #[test]
+    fn test_roll_turn_save_success() {
+        // Test that a successful save deals half damage, rounded down, and does not count as a hit.
+        let mut attack_profile = spawn_save_profile("dc10:1d1+9", Ruleset::DND5e);
+
+        let exp_result = TurnResult {
+            crits: 0,
+            hits: 0,
+            raw_damage: 2,
+            damage: 2,
+            rider_damage: 0,
+            persistent_damage: 0,
+            bonus_roll: 0,
+            triggered_attacks: 0,
+            damage_by_type: untyped_damage(2),
+            instance_damage: vec![2],
+        };
+        assert_eq!(exp_result, attack_profile.roll_turn());
+    }
+
+    #[test]
+    fn test_roll_turn_save_critical_failure() {
+        // Test that a critical failure under PF2e rules deals double damage and counts as a critical hit.
+        let mut attack_profile = spawn_save_profile("dc20:1d1-1", Ruleset::PF2e);
+
+        let exp_result = TurnResult {
+            crits: 1,
+            hits: 1,
+            raw_damage: 8,
+            damage: 8,
+            rider_damage: 0,
+            persistent_damage: 0,
+            bonus_roll: 0,
+            triggered_attacks: 0,
+            damage_by_type: untyped_damage(8),
+            instance_damage: vec![8],
+        };
+        assert_eq!(exp_result, attack_profile.roll_turn());
+    }
+
+    #[test]
+    fn test_roll_turn_save_critical_success() {
+        // Test that a critical success under PF2e rules deals no damage.
+        let mut attack_profile = spawn_save_profile("dc10:1d1+19", Ruleset::PF2e);
+
+        let exp_result = TurnResult {
+            crits: 0,
+            hits: 0,
+            raw_damage: 0,
+            damage: 0,
+            rider_damage: 0,
+            persistent_damage: 0,
+            bonus_roll: 0,
+            triggered_attacks: 0,
+            damage_by_type: untyped_damage(0),
+            instance_damage: vec![0],
+        };
+        assert_eq!(exp_result, attack_profile.roll_turn());
+    }
+
+    // endregion:
+
+    // region: turn_distribution() tests
+
+    #[test]
+    fn test_turn_distribution_single() {
+        // A d2 hit roll against AC 2 hits half the time for a flat 3 damage
+        let hit_collection = spawn_attack_rolls(vec![(1, 2)], vec![0], Ruleset::DND5e);
+        let damage_collection = spawn_roll_collections(vec![(1, 1)], vec![2], Ruleset::DND5e);
+
+        let attack_profile = AttackProfile::new(
+            Target::from_ac(2),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        );
+        let obs_dist = attack_profile.turn_distribution();
+
+        assert_eq!(Distribution::new(0, vec![0.5, 0.5]), obs_dist.hits);
+        assert_eq!(Distribution::new(0, vec![1.0, 0.0]), obs_dist.crits);
+        assert_eq!(
+            Distribution::new(0, vec![0.5, 0.0, 0.0, 0.5]),
+            obs_dist.damage
+        );
+        assert_eq!(Distribution::point(0), obs_dist.rider_damage);
+    }
+
+    #[test]
+    fn test_turn_distribution_multiple() {
+        // Two attacks which always crit, each for 2 damage, and an attack which always misses
+        let hit_collection = spawn_attack_rolls(
+            vec![(20, 20), (20, 20), (1, 1)],
+            vec![0, 0, 0],
+            Ruleset::DND5e,
+        );
+        let damage_collection =
+            spawn_roll_collections(vec![(1, 1), (1, 1), (1, 1)], vec![0, 0, 0], Ruleset::DND5e);
+
+        let attack_profile = AttackProfile::new(
+            Target::from_ac(10),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        );
+        let obs_dist = attack_profile.turn_distribution();
+
+        assert_eq!(2.0, obs_dist.hits.mean());
+        assert_eq!(2.0, obs_dist.crits.mean());
+        assert_eq!(4.0, obs_dist.damage.mean());
+        assert_eq!(0.0, obs_dist.damage.variance());
+    }
+
+    #[test]
+    fn test_turn_distribution_save() {
+        // A d20 save against DC 11 fails half the time for 4 damage, and succeeds for half damage
+        let attack_profile = spawn_save_profile("dc11:1d20", Ruleset::DND5e);
+        let obs_dist = attack_profile.turn_distribution();
+
+        let exp_damage = Distribution::from_pairs(vec![(2, 0.5), (4, 0.5)]);
+        for ((exp_value, exp_p), (obs_value, obs_p)) in
+            exp_damage.iter().zip(obs_dist.damage.iter())
+        {
+            assert_eq!(exp_value, obs_value);
+            assert!((exp_p - obs_p).abs() < 1e-9);
+        }
+        assert!((0.5 - obs_dist.hits.mean()).abs() < 1e-9);
+        assert_eq!(0.0, obs_dist.crits.mean());
+        assert_eq!(0.0, obs_dist.bonus_roll.mean());
+    }
+
+    // endregion:
+
+    // region: roll_turn() rider tests
+
+    fn spawn_rider(damage_value: i32, trigger: RiderTrigger) -> Rider {
+        let die = DiceBuilder::new()
+            .set_roll_min(damage_value)
+            .set_roll_max(damage_value)
+            .build();
+        Rider::new(
+            RollCollection::new(vec![die], vec![], Ruleset::DND5e),
+            trigger,
+        )
+    }
+
+    #[test]
+    fn test_roll_turn_rider_first_hit() {
+        // Test that a rider is applied once only, on the first attack that hits.
+        let hit_collection = spawn_attack_rolls(
+            vec![(1, 2), (12, 14), (12, 14)],
+            vec![0, 0, 0],
+            Ruleset::DND5e,
+        );
+        let damage_collection =
+            spawn_roll_collections(vec![(1, 1), (1, 1), (1, 1)], vec![0, 0, 0], Ruleset::DND5e);
+
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(10),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        )
+        .set_riders(vec![spawn_rider(5, RiderTrigger::FirstHit)]);
+
+        let exp_result = TurnResult {
+            crits: 0,
+            hits: 2,
+            raw_damage: 7,
+            damage: 7,
+            rider_damage: 5,
+            persistent_damage: 0,
+            bonus_roll: 0,
+            triggered_attacks: 0,
+            damage_by_type: untyped_damage(7),
+            instance_damage: vec![0, 6, 1],
+        };
+        assert_eq!(exp_result, attack_profile.roll_turn());
+    }
+
+    #[test]
+    fn test_roll_turn_rider_first_crit() {
+        // Test that a crit rider skips the regular hit, and applies to (and doubles on) the crit.
+        let hit_collection = spawn_attack_rolls(
+            vec![(12, 14), (20, 20), (20, 20)],
+            vec![0, 0, 0],
+            Ruleset::DND5e,
+        );
+        let damage_collection =
+            spawn_roll_collections(vec![(1, 1), (1, 1), (1, 1)], vec![0, 0, 0], Ruleset::DND5e);
+
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(10),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        )
+        .set_riders(vec![spawn_rider(5, RiderTrigger::FirstCrit)]);
+
+        let exp_result = TurnResult {
+            crits: 2,
+            hits: 3,
+            raw_damage: 15,
+            damage: 15,
+            rider_damage: 10,
+            persistent_damage: 0,
+            bonus_roll: 0,
+            triggered_attacks: 0,
+            damage_by_type: untyped_damage(15),
+            instance_damage: vec![1, 12, 2],
+        };
+        assert_eq!(exp_result, attack_profile.roll_turn());
+    }
+
+    #[test]
+    fn test_roll_turn_rider_miss() {
+        // Test that no rider damage is applied when every attack misses.
+        let hit_collection = spawn_attack_rolls(vec![(1, 2)], vec![0], Ruleset::DND5e);
+        let damage_collection = spawn_roll_collections(vec![(1, 1)], vec![0], Ruleset::DND5e);
+
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(10),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        )
+        .set_riders(vec![spawn_rider(5, RiderTrigger::FirstHit)]);
+
+        let exp_result = TurnResult {
+            crits: 0,
+            hits: 0,
+            raw_damage: 0,
+            damage: 0,
+            rider_damage: 0,
+            persistent_damage: 0,
+            bonus_roll: 0,
+            triggered_attacks: 0,
+            damage_by_type: untyped_damage(0),
+            instance_damage: vec![0],
+        };
+        assert_eq!(exp_result, attack_profile.roll_turn());
+    }
+
+    // endregion:
+
+    // region: turn_distribution() rider tests
+
+    #[test]
+    fn test_turn_distribution_rider() {
+        // Two attacks, each hitting half the time for 1 damage. The rider of 5 damage applies
+        //  if either attack hits, so 3/4 of the time.
+        let hit_collection = spawn_attack_rolls(vec![(1, 2), (1, 2)], vec![0, 0], Ruleset::DND5e);
+        let damage_collection =
+            spawn_roll_collections(vec![(1, 1), (1, 1)], vec![0, 0], Ruleset::DND5e);
+
+        let attack_profile = AttackProfile::new(
+            Target::from_ac(2),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        )
+        .set_riders(vec![spawn_rider(5, RiderTrigger::FirstHit)]);
+        let obs_dist = attack_profile.turn_distribution();
+
+        let exp_rider = Distribution::from_pairs(vec![(0, 0.25), (5, 0.75)]);
+        assert_eq!(exp_rider, obs_dist.rider_damage);
+
+        // Damage is 0 with no hits, 6 with one hit, and 7 with two hits.
+        let exp_damage = Distribution::from_pairs(vec![(0, 0.25), (6, 0.5), (7, 0.25)]);
+        assert_eq!(exp_damage, obs_dist.damage);
+    }
+
+    // endregion:
+
+    // region: damage type tests
+
+    fn fire() -> Option<String> {
+        Some("fire".to_string())
+    }
+
+    fn cold() -> Option<String> {
+        Some("cold".to_string())
+    }
+
+    fn spawn_typed_save_profile(save_notation: &str) -> AttackProfile {
+        let saving_throw =
+            SavingThrow::parse_user_input(save_notation, Ruleset::DND5e, &Target::from_ac(10))
+                .unwrap();
+        let damage_collection =
+            RollCollection::parse_user_input("3d1 fire + 3d1 cold", Ruleset::DND5e).unwrap();
+
+        AttackProfile::new(
+            Target::from_ac(10),
+            vec![AttackKind::SavingThrow(saving_throw)],
+            vec![damage_collection],
+            Ruleset::DND5e,
+        )
+    }
+
+    #[test]
+    fn test_roll_turn_typed_rider() {
+        // Test that the rider damage is split by type alongside the attack damage.
+        let hit_collection = spawn_attack_rolls(vec![(12, 14)], vec![0], Ruleset::DND5e);
+        let damage_collection =
+            vec![RollCollection::parse_user_input("1d1 fire + 2", Ruleset::DND5e).unwrap()];
+        let rider = Rider::parse_user_input("2d1 cold", Ruleset::DND5e).unwrap();
+
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(10),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        )
+        .set_riders(vec![rider]);
+
+        let mut exp_damage = TypedDamage::new();
+        exp_damage.add(&fire(), 3);
+        exp_damage.add(&cold(), 2);
+
+        let obs_result = attack_profile.roll_turn();
+        assert_eq!(5, obs_result.damage);
+        assert_eq!(exp_damage, obs_result.damage_by_type);
+    }
+
+    #[test]
+    fn test_roll_turn_typed_save_success() {
+        // Test that the total damage is halved once on a successful save, so 3 fire and 3 cold
+        //  damage is halved to 3 rather than 2.
+        let mut attack_profile = spawn_typed_save_profile("dc10:1d1+9");
+
+        let obs_result = attack_profile.roll_turn();
+        assert_eq!(3, obs_result.damage);
+        assert_eq!(2, obs_result.damage_by_type.get(&cold()));
+        assert_eq!(1, obs_result.damage_by_type.get(&fire()));
+    }
+
+    #[test]
+    fn test_save_success_mixed_types() {
+        // Test that 1 fire and 1 cold damage is halved to 1 on a successful save, rather than 0
+        let saving_throw =
+            SavingThrow::parse_user_input("dc10:1d1+9", Ruleset::DND5e, &Target::from_ac(10))
+                .unwrap();
+        let damage_collection = RollCollection::parse_user_input("1 fire, 1 cold", Ruleset::DND5e);
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(10),
+            vec![AttackKind::SavingThrow(saving_throw)],
+            vec![damage_collection.unwrap()],
+            Ruleset::DND5e,
+        );
+
+        assert_eq!(
+            Distribution::point(1),
+            attack_profile.turn_distribution().damage
+        );
+        assert_eq!(1, attack_profile.roll_turn().damage);
+    }
+
+    #[test]
+    fn test_reported_damage_types() {
+        let attack_profile = spawn_typed_save_profile("dc10:1d1+9");
+        assert_eq!(vec![cold(), fire()], attack_profile.reported_damage_types());
+
+        // Untyped damage is not split
+        let attack_profile = spawn_save_profile("dc10:1d1+9", Ruleset::DND5e);
+        assert!(attack_profile.reported_damage_types().is_empty());
+    }
+
+    #[test]
+    fn test_turn_distribution_typed() {
+        // A d2 hit roll against AC 2 hits half the time for 1d2 fire and 1 cold damage
+        let hit_collection = spawn_attack_rolls(vec![(1, 2)], vec![0], Ruleset::DND5e);
+        let damage_collection =
+            vec![RollCollection::parse_user_input("1d2 fire, 1 cold", Ruleset::DND5e).unwrap()];
+        let attack_profile = AttackProfile::new(
+            Target::from_ac(2),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        );
+
+        let obs_dist = attack_profile.turn_distribution();
+        assert_eq!(2, obs_dist.damage_by_type.len());
+        assert_eq!(
+            Distribution::new(0, vec![0.5, 0.25, 0.25]),
+            obs_dist.damage_by_type[&fire()]
+        );
+        assert_eq!(
+            Distribution::new(0, vec![0.5, 0.5]),
+            obs_dist.damage_by_type[&cold()]
+        );
+    }
+
+    #[test]
+    fn test_turn_distribution_typed_save_success() {
+        // Test that the exact distribution halves the total damage once, matching roll_turn()
+        let attack_profile = spawn_typed_save_profile("dc10:1d1+9");
+
+        let obs_dist = attack_profile.turn_distribution();
+        assert_eq!(Distribution::point(3), obs_dist.damage);
+        assert_eq!(Distribution::point(2), obs_dist.damage_by_type[&cold()]);
+        assert_eq!(Distribution::point(1), obs_dist.damage_by_type[&fire()]);
+    }
+
+    // endregion:
+
+    // region: roll_turn() defence tests
+
+    fn spawn_fire_profile(target: Target, rule_mode: Ruleset) -> AttackProfile {
+        // Two attacks which always hit for 3 fire damage, with a 3 fire damage rider
+        let hit_collection =
+            spawn_attack_rolls(vec![(12, 14), (12, 14)], vec![0, 0], Ruleset::PF2e);
+        let damage_collection = vec![
+            RollCollection::parse_user_input("1d1+2 fire", Ruleset::PF2e).unwrap(),
+            RollCollection::parse_user_input("1d1+2 fire", Ruleset::PF2e).unwrap(),
+        ];
+        let rider = Rider::parse_user_input("3d1 fire", Ruleset::PF2e).unwrap();
+
+        AttackProfile::new(target, hit_collection, damage_collection, rule_mode)
+            .set_riders(vec![rider])
+    }
+
+    #[test]
+    fn test_roll_turn_resistance_per_instance() {
+        // Test that resistance applies to each hit, with the rider part of the first hit. The
+        //  first hit takes 6 - 5 damage, and the second is reduced to zero rather than below.
+        let mut target = Target::from_ac(10);
+        target.resistances.insert("fire".to_string(), 5);
+        let mut attack_profile = spawn_fire_profile(target, Ruleset::PF2e);
+
+        let obs_result = attack_profile.roll_turn();
+        assert_eq!(9, obs_result.raw_damage);
+        assert_eq!(1, obs_result.damage);
+        assert_eq!(1, obs_result.rider_damage);
+        assert_eq!(1, obs_result.damage_by_type.get(&Some("fire".to_string())));
+    }
+
+    #[test]
+    fn test_roll_turn_weakness_per_instance() {
+        // Test that weakness applies once to each hit, but not to the rider separately.
+        let mut target = Target::from_ac(10);
+        target.weaknesses.insert("fire".to_string(), 2);
+        let mut attack_profile = spawn_fire_profile(target, Ruleset::PF2e);
+
+        let obs_result = attack_profile.roll_turn();
+        assert_eq!(9, obs_result.raw_damage);
+        assert_eq!(13, obs_result.damage);
+        assert_eq!(3, obs_result.rider_damage);
+    }
+
+    #[test]
+    fn test_roll_turn_immunity() {
+        let mut target = Target::from_ac(10);
+        target.immunities.push("fire".to_string());
+        let mut attack_profile = spawn_fire_profile(target, Ruleset::PF2e);
+
+        let obs_result = attack_profile.roll_turn();
+        assert_eq!(9, obs_result.raw_damage);
+        assert_eq!(0, obs_result.damage);
+        assert_eq!(0, obs_result.rider_damage);
+    }
+
+    #[test]
+    fn test_roll_turn_resistance_dnd() {
+        // Test that resistance halves each hit under the D&D 5e rules, rounding down. The first
+        //  hit takes 6 / 2 damage, of which 3 / 2 comes from the attack, and the second 3 / 2.
+        let mut target = Target::from_ac(10);
+        target.resistances.insert("fire".to_string(), 5);
+        let mut attack_profile = spawn_fire_profile(target, Ruleset::DND5e);
+
+        let obs_result = attack_profile.roll_turn();
+        assert_eq!(9, obs_result.raw_damage);
+        assert_eq!(4, obs_result.damage);
+        assert_eq!(2, obs_result.rider_damage);
+    }
+
+    #[test]
+    fn test_roll_turn_vulnerability_dnd() {
+        // Test that vulnerability doubles each hit under the D&D 5e rules, whatever its amount
+        let mut target = Target::from_ac(10);
+        target.weaknesses.insert("fire".to_string(), 2);
+        let mut attack_profile = spawn_fire_profile(target, Ruleset::DND5e);
+
+        let obs_result = attack_profile.roll_turn();
+        assert_eq!(9, obs_result.raw_damage);
+        assert_eq!(18, obs_result.damage);
+        assert_eq!(6, obs_result.rider_damage);
+    }
+
+    // endregion:
+
+    // region: roll_turn() persistent damage tests
+
+    fn spawn_persistent_profile(riders: Vec<&str>, flat_check_roll: i32) -> AttackProfile {
+        // A single attack which always hits for 1 damage, with a fixed flat check roll
+        let hit_collection = spawn_attack_rolls(vec![(12, 14)], vec![0], Ruleset::PF2e);
+        let damage_collection = spawn_roll_collections(vec![(1, 1)], vec![0], Ruleset::PF2e);
+        let riders = riders
+            .into_iter()
+            .map(|r| Rider::parse_user_input(r, Ruleset::PF2e).unwrap())
+            .collect();
+
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(10),
+            hit_collection,
+            damage_collection,
+            Ruleset::PF2e,
+        )
+        .set_riders(riders);
+        attack_profile.flat_check = DiceBuilder::new()
+            .set_roll_min(flat_check_roll)
+            .set_roll_max(flat_check_roll)
+            .build();
+        attack_profile
+    }
+
+    #[test]
+    fn test_roll_turn_persistent_damage() {
+        // Test that persistent damage is dealt at the end of the round, and is not rider damage.
+        let mut attack_profile = spawn_persistent_profile(vec!["persistent:2d1 fire"], 1);
+
+        let obs_result = attack_profile.roll_turn();
+        assert_eq!(3, obs_result.damage);
+        assert_eq!(0, obs_result.rider_damage);
+        assert_eq!(2, obs_result.persistent_damage);
+        assert_eq!(2, obs_result.damage_by_type.get(&Some("fire".to_string())));
+    }
+
+    #[test]
+    fn test_roll_turn_persistent_no_stacking() {
+        // Test that persistent damage of the same type does not stack, keeping the higher
+        //  effect, across riders and across rounds.
+        let mut attack_profile =
+            spawn_persistent_profile(vec!["persistent:1d1 fire", "persistent:3d1 fire"], 1);
+
+        assert_eq!(3, attack_profile.roll_turn().persistent_damage);
+        assert_eq!(3, attack_profile.roll_turn().persistent_damage);
+    }
+
+    #[test]
+    fn test_roll_turn_persistent_different_types() {
+        let mut attack_profile =
+            spawn_persistent_profile(vec!["persistent:1d1 fire", "persistent:3d1 bleed"], 1);
+
+        assert_eq!(4, attack_profile.roll_turn().persistent_damage);
+    }
+
+    #[test]
+    fn test_roll_turn_persistent_flat_check() {
+        // Test that a successful DC 15 flat check ends the persistent damage, and a failure does not.
+        let mut attack_profile = spawn_persistent_profile(vec!["persistent:2d1 fire"], 15);
+        attack_profile.roll_turn();
+        assert!(attack_profile.persistent_effects.is_empty());
+
+        let mut attack_profile = spawn_persistent_profile(vec!["persistent:2d1 fire"], 14);
+        attack_profile.roll_turn();
+        assert_eq!(1, attack_profile.persistent_effects.len());
+    }
+
+    #[test]
+    fn test_roll_turn_persistent_carries_over() {
+        // Test that persistent damage continues to be dealt in a round where every attack misses.
+        let mut attack_profile = spawn_persistent_profile(vec!["persistent:2d1 fire"], 1);
+        attack_profile.roll_turn();
+
+        attack_profile.target.ac = 100;
+        let obs_result = attack_profile.roll_turn();
+        assert_eq!(0, obs_result.hits);
+        assert_eq!(2, obs_result.damage);
+        assert_eq!(2, obs_result.persistent_damage);
+    }
+
+    #[test]
+    fn test_reset_persistent_damage() {
+        let mut attack_profile = spawn_persistent_profile(vec!["persistent:2d1 fire"], 1);
+        attack_profile.roll_turn();
+
+        attack_profile.reset_persistent_damage();
+        assert!(attack_profile.persistent_effects.is_empty());
+    }
+
+    #[test]
+    fn test_has_persistent_damage() {
+        assert!(spawn_persistent_profile(vec!["3d6", "persistent:1d6"], 1).has_persistent_damage());
+        assert!(!spawn_persistent_profile(vec!["3d6"], 1).has_persistent_damage());
+    }
+
+    // endregion:
+
+    // region: roll_turn() follow-up attack tests
+
+    fn spawn_follow_up_profile() -> AttackProfile {
+        // A critical hit and a miss, followed by attacks conditional on each of them
+        let hit_collection = spawn_attack_rolls(
+            vec![(20, 20), (2, 2), (15, 15), (15, 15), (15, 15)],
+            vec![0; 5],
+            Ruleset::DND5e,
+        );
+        let damage_collection = spawn_roll_collections(vec![(1, 1); 5], vec![0; 5], Ruleset::DND5e);
+        let conditions = ["1d20", "1d20", "crit1:1d20", "hit2:1d20", "hit:1d20"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, n)| AttackCondition::parse_user_input(n, i).unwrap().0)
+            .collect();
+
+        AttackProfile::new(
+            Target::from_ac(10),
+            hit_collection,
+            damage_collection,
+            Ruleset::DND5e,
+        )
+        .set_conditions(conditions)
+    }
+
+    #[test]
+    fn test_roll_turn_follow_up() {
+        // Test that only the follow-up attack whose condition is met is made, and that an
+        //  attack conditional on an attack which was not made is skipped.
+        let mut attack_profile = spawn_follow_up_profile();
+
+        let exp_result = TurnResult {
+            crits: 1,
+            hits: 2,
+            raw_damage: 3,
+            damage: 3,
+            rider_damage: 0,
+            persistent_damage: 0,
+            bonus_roll: 0,
+            triggered_attacks: 1,
+            damage_by_type: untyped_damage(3),
+            instance_damage: vec![2, 0, 1],
+        };
+        assert_eq!(exp_result, attack_profile.roll_turn());
+    }
+
+    #[test]
+    fn test_has_conditional_attacks() {
+        assert!(spawn_follow_up_profile().has_conditional_attacks());
+        assert!(!spawn_persistent_profile(vec![], 1).has_conditional_attacks());
+    }
+
+    // endregion:
+
+    // region: on-miss damage tests
+
+    fn spawn_graze_profile(hit_dice: (i32, i32)) -> AttackProfile {
+        // An attack dealing 4 damage on a hit, and 2 damage on a miss
+        let hit_collection = spawn_attack_rolls(vec![hit_dice], vec![0], Ruleset::DND5e);
+        let damage_collection = RollCollection::parse_user_input("1d1+3,miss:2", Ruleset::DND5e);
+
+        AttackProfile::new(
+            Target::from_ac(2),
+            hit_collection,
+            vec![damage_collection.unwrap()],
+            Ruleset::DND5e,
+        )
+    }
+
+    #[test]
+    fn test_roll_turn_on_miss() {
+        // Test that a missed attack deals its on-miss damage, but does not count as a hit.
+        let mut attack_profile = spawn_graze_profile((1, 1));
+
+        let exp_result = TurnResult {
+            crits: 0,
+            hits: 0,
+            raw_damage: 2,
+            damage: 2,
+            rider_damage: 0,
+            persistent_damage: 0,
+            bonus_roll: 0,
+            triggered_attacks: 0,
+            damage_by_type: untyped_damage(2),
+            instance_damage: vec![2],
+        };
+        assert_eq!(exp_result, attack_profile.roll_turn());
+    }
+
+    #[test]
+    fn test_turn_distribution_on_miss() {
+        // A d2 hit roll against AC 2 hits half the time for 4 damage, and misses for 2
+        let attack_profile = spawn_graze_profile((1, 2));
+        let obs_dist = attack_profile.turn_distribution();
+
+        assert_eq!(
+            Distribution::from_pairs(vec![(2, 0.5), (4, 0.5)]),
+            obs_dist.damage
+        );
+        assert_eq!(Distribution::new(0, vec![0.5, 0.5]), obs_dist.hits);
+    }
+
+    #[test]
+    fn test_save_critical_success_on_miss() {
+        // Test that on-miss damage is not dealt when the target critically succeeds at a save.
+        let saving_throw =
+            SavingThrow::parse_user_input("dc10:1d1+19", Ruleset::PF2e, &Target::from_ac(10))
+                .unwrap();
+        let damage_collection = RollCollection::parse_user_input("1d1+3,miss:2", Ruleset::PF2e);
+        let mut attack_profile = AttackProfile::new(
+            Target::from_ac(10),
+            vec![AttackKind::SavingThrow(saving_throw)],
+            vec![damage_collection.unwrap()],
+            Ruleset::DND5e,
+        );
+
+        assert_eq!(
+            Distribution::point(0),
+            attack_profile.turn_distribution().damage
+        );
+        assert_eq!(0, attack_profile.roll_turn().damage);
+    }
+
+    // endregion:
 }
@@ -0,0 +1,772 @@
+use serde::Deserialize;
+use simple_error::bail;
+use std::{error::Error, fs};
+
+use crate::{Ruleset, Target, TurnPlan};
+
+/// A representation of a file of build definitions, in TOML format
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BuildFile {
+    pub builds: Vec<Build>,
+}
+
+/// A single named build, holding all the information required to run a simulation
+///
+/// A build either describes a single set of targets and attacks, or a progression of
+/// levels each with their own targets and attacks. Targets are given either as bare AC
+/// values, or as named targets with their full defences, which are applied following the
+/// ruleset of the build. Attacks are given either as an ordered list, or as a Pathfinder
+/// 2e turn plan.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Build {
+    pub name: String,
+    pub ruleset: Ruleset,
+    #[serde(default)]
+    pub ac_targets: Vec<i32>,
+    #[serde(default)]
+    pub targets: Vec<Target>,
+    #[serde(default)]
+    pub attacks: Vec<AttackDetails>,
+    pub turn_plan: Option<TurnPlan>,
+    #[serde(default)]
+    pub riders: Vec<String>,
+    pub levels: Option<Vec<LevelDetails>>,
+    pub number_turns: Option<i32>,
+    pub number_rounds: Option<i32>,
+    pub output: Option<String>,
+}
+
+/// The targets and attacks of a build at a single level of its progression
+///
+/// Riders are inherited from the build unless set for the level.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LevelDetails {
+    pub level: i32,
+    #[serde(default)]
+    pub ac_targets: Vec<i32>,
+    #[serde(default)]
+    pub targets: Vec<Target>,
+    #[serde(default)]
+    pub attacks: Vec<AttackDetails>,
+    pub turn_plan: Option<TurnPlan>,
+    pub riders: Option<Vec<String>>,
+}
+
+/// The to-hit and damage notation of a single attack in a build
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AttackDetails {
+    pub to_hit: String,
+    pub damage: String,
+}
+
+impl BuildFile {
+    /// Parse the text content of a build file into its component builds.
+    ///
+    /// # Examples
+    /// ```
+    /// let content = r#"
+    /// [[builds]]
+    /// name = "Fighter"
+    /// ruleset = "DND5e"
+    /// ac_targets = [14, 16, 18]
+    /// attacks = [{ to_hit = "1d20+7", damage = "1d8+4" }]
+    /// "#;
+    ///
+    /// let build_file = BuildFile::parse_content(content)?;
+    /// ```
+    pub fn parse_content(content: &str) -> Result<BuildFile, Box<dyn Error>> {
+        let build_file: BuildFile = match toml::from_str(content) {
+            Ok(b) => b,
+            Err(e) => bail!("Unable to parse build file: {}", e.message()),
+        };
+
+        if build_file.builds.is_empty() {
+            bail!("The build file does not define any builds!");
+        }
+
+        for build in &build_file.builds {
+            let build_label = format!("Build '{}'", build.name);
+            let has_attacks = !build.attacks.is_empty() || build.turn_plan.is_some();
+            match &build.levels {
+                None => {
+                    if !has_attacks {
+                        bail!("{} does not define any attacks!", build_label);
+                    }
+                    if build.resolve_targets().is_empty() {
+                        bail!("{} does not define any ac_targets or targets!", build_label);
+                    }
+                }
+                Some(levels) => {
+                    if levels.is_empty() {
+                        bail!("{} defines an empty list of levels!", build_label);
+                    }
+                    if has_attacks {
+                        bail!(
+                            "{} defines both attacks and levels, only one may be used!",
+                            build_label
+                        );
+                    }
+                }
+            }
+            validate_turn_plan(
+                &build_label,
+                &build.attacks,
+                &build.turn_plan,
+                build.ruleset,
+            )?;
+
+            for level_details in build.levels.iter().flatten() {
+                let level_label =
+                    format!("Level {} of build '{}'", level_details.level, build.name);
+                if level_details.attacks.is_empty() && level_details.turn_plan.is_none() {
+                    bail!("{} does not define any attacks!", level_label);
+                }
+                if level_details.resolve_targets().is_empty() {
+                    bail!("{} does not define any ac_targets or targets!", level_label);
+                }
+                validate_turn_plan(
+                    &level_label,
+                    &level_details.attacks,
+                    &level_details.turn_plan,
+                    build.ruleset,
+                )?;
+            }
+        }
+
+        Ok(build_file)
+    }
+
+    /// Read and parse a build file from the specified file path.
+    ///
+    /// # Examples
+    /// ```
+    /// let build_file = BuildFile::from_file("builds.toml")?;
+    /// ```
+    pub fn from_file(file_path: &str) -> Result<BuildFile, Box<dyn Error>> {
+        let content = match fs::read_to_string(file_path) {
+            Ok(c) => c,
+            _ => bail!("Unable to read build file '{}'!", file_path),
+        };
+
+        BuildFile::parse_content(&content)
+    }
+}
+
+impl Build {
+    /// Separate the attacks of the build into the to-hit and damage notation vectors.
+    ///
+    /// # Examples
+    /// ```
+    /// let (hit_details, weapon_details) = build.split_attacks();
+    /// ```
+    pub fn split_attacks(&self) -> (Vec<String>, Vec<String>) {
+        split_attack_details(&self.attacks, &self.turn_plan)
+    }
+
+    /// Return every target of the build, with the bare AC values ahead of the named targets.
+    ///
+    /// # Examples
+    /// ```
+    /// let targets = build.resolve_targets();
+    /// ```
+    pub fn resolve_targets(&self) -> Vec<Target> {
+        combine_targets(&self.ac_targets, &self.targets)
+    }
+}
+
+impl LevelDetails {
+    /// Separate the attacks of the level into the to-hit and damage notation vectors.
+    ///
+    /// # Examples
+    /// ```
+    /// let (hit_details, weapon_details) = level_details.split_attacks();
+    /// ```
+    pub fn split_attacks(&self) -> (Vec<String>, Vec<String>) {
+        split_attack_details(&self.attacks, &self.turn_plan)
+    }
+
+    /// Return the riders applied at this level, falling back to those of the build.
+    ///
+    /// # Examples
+    /// ```
+    /// let riders = level_details.resolve_riders(&build.riders);
+    /// ```
+    pub fn resolve_riders(&self, build_riders: &[String]) -> Vec<String> {
+        match &self.riders {
+            Some(riders) => riders.to_vec(),
+            None => build_riders.to_vec(),
+        }
+    }
+
+    /// Return every target of the level, with the bare AC values ahead of the named targets.
+    ///
+    /// # Examples
+    /// ```
+    /// let targets = level_details.resolve_targets();
+    /// ```
+    pub fn resolve_targets(&self) -> Vec<Target> {
+        combine_targets(&self.ac_targets, &self.targets)
+    }
+}
+
+fn combine_targets(ac_targets: &[i32], targets: &[Target]) -> Vec<Target> {
+    ac_targets
+        .iter()
+        .map(|ac| Target::from_ac(*ac))
+        .chain(targets.iter().cloned())
+        .collect()
+}
+
+fn split_attack_details(
+    attacks: &[AttackDetails],
+    turn_plan: &Option<TurnPlan>,
+) -> (Vec<String>, Vec<String>) {
+    match turn_plan {
+        // Turn plans are validated when the build file is parsed, so cannot fail here
+        Some(plan) => plan.plan_attacks().unwrap(),
+        None => attacks
+            .iter()
+            .map(|a| (a.to_hit.to_string(), a.damage.to_string()))
+            .unzip(),
+    }
+}
+
+fn validate_turn_plan(
+    label: &str,
+    attacks: &[AttackDetails],
+    turn_plan: &Option<TurnPlan>,
+    ruleset: Ruleset,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(plan) = turn_plan {
+        if !attacks.is_empty() {
+            bail!(
+                "{} defines both attacks and a turn plan, only one may be used!",
+                label
+            );
+        }
+        if ruleset != Ruleset::PF2e {
+            bail!(
+                "{} uses a turn plan, which requires the PF2e ruleset!",
+                label
+            );
+        }
+        if let Err(e) = plan.plan_attacks() {
+            bail!("{} has an invalid turn plan: {}", label, e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_attack(to_hit: &str, damage: &str) -> AttackDetails {
+        AttackDetails {
+            to_hit: to_hit.to_string(),
+            damage: damage.to_string(),
+        }
+    }
+
+    // region: parse_content() tests
+
+    #[test]
+    fn test_parse_content() {
+        let content = r#"
+            [[builds]]
+            name = "Fighter"
+            ruleset = "PF2e"
+            ac_targets = [16, 18]
+            number_turns = 1000
+            output = "fighter.parquet"
+            attacks = [
+                { to_hit = "1d20+9", damage = "1d8+4" },
+                { to_hit = "1d20+4", damage = "1d8+4" },
+            ]
+
+            [[builds]]
+            name = "Rogue"
+            ruleset = "DND5e"
+            ac_targets = [14]
+            riders = ["3d6"]
+
+            [[builds.attacks]]
+            to_hit = "1d20+7"
+            damage = "1d6+4"
+        "#;
+
+        let exp_file = BuildFile {
+            builds: vec![
+                Build {
+                    name: "Fighter".to_string(),
+                    ruleset: Ruleset::PF2e,
+                    ac_targets: vec![16, 18],
+                    targets: vec![],
+                    attacks: vec![
+                        spawn_attack("1d20+9", "1d8+4"),
+                        spawn_attack("1d20+4", "1d8+4"),
+                    ],
+                    turn_plan: None,
+                    riders: vec![],
+                    levels: None,
+                    number_turns: Some(1000),
+                    number_rounds: None,
+                    output: Some("fighter.parquet".to_string()),
+                },
+                Build {
+                    name: "Rogue".to_string(),
+                    ruleset: Ruleset::DND5e,
+                    ac_targets: vec![14],
+                    targets: vec![],
+                    attacks: vec![spawn_attack("1d20+7", "1d6+4")],
+                    turn_plan: None,
+                    riders: vec!["3d6".to_string()],
+                    levels: None,
+                    number_turns: None,
+                    number_rounds: None,
+                    output: None,
+                },
+            ],
+        };
+
+        let obs_file = BuildFile::parse_content(content).unwrap();
+        assert_eq!(exp_file, obs_file);
+    }
+
+    #[test]
+    fn test_parse_content_levels() {
+        let content = r#"
+            [[builds]]
+            name = "Druid"
+            ruleset = "PF2e"
+            riders = ["1d6"]
+
+            [[builds.levels]]
+            level = 1
+            ac_targets = [16]
+            attacks = [{ to_hit = "1d20+7", damage = "2d6+2" }]
+
+            [[builds.levels]]
+            level = 2
+            ac_targets = [17]
+            riders = []
+            attacks = [{ to_hit = "1d20+8", damage = "2d6+2" }]
+        "#;
+
+        let exp_levels = vec![
+            LevelDetails {
+                level: 1,
+                ac_targets: vec![16],
+                targets: vec![],
+                attacks: vec![spawn_attack("1d20+7", "2d6+2")],
+                turn_plan: None,
+                riders: None,
+            },
+            LevelDetails {
+                level: 2,
+                ac_targets: vec![17],
+                targets: vec![],
+                attacks: vec![spawn_attack("1d20+8", "2d6+2")],
+                turn_plan: None,
+                riders: Some(vec![]),
+            },
+        ];
+
+        let obs_file = BuildFile::parse_content(content).unwrap();
+        assert_eq!(Some(exp_levels), obs_file.builds[0].levels);
+        assert!(obs_file.builds[0].attacks.is_empty());
+    }
+
+    #[test]
+    fn test_parse_content_targets() {
+        let content = r#"
+            [[builds]]
+            name = "Wizard"
+            ruleset = "PF2e"
+            attacks = [{ to_hit = "dc21:reflex", damage = "6d6" }]
+
+            [[builds.targets]]
+            name = "Ogre Warrior"
+            ac = 17
+            hp = 50
+            saves = { fortitude = 13, reflex = 6, will = 5 }
+            weaknesses = { cold = 5 }
+
+            [[builds.targets]]
+            name = "Skeleton Guard"
+            ac = 16
+            resistances = { piercing = 5, slashing = 5 }
+            immunities = ["poison"]
+        "#;
+
+        let mut exp_ogre = Target::from_ac(17);
+        exp_ogre.name = Some("Ogre Warrior".to_string());
+        exp_ogre.hp = Some(50);
+        exp_ogre.saves.insert("fortitude".to_string(), 13);
+        exp_ogre.saves.insert("reflex".to_string(), 6);
+        exp_ogre.saves.insert("will".to_string(), 5);
+        exp_ogre.weaknesses.insert("cold".to_string(), 5);
+
+        let mut exp_skeleton = Target::from_ac(16);
+        exp_skeleton.name = Some("Skeleton Guard".to_string());
+        exp_skeleton.resistances.insert("piercing".to_string(), 5);
+        exp_skeleton.resistances.insert("slashing".to_string(), 5);
+        exp_skeleton.immunities.push("poison".to_string());
+
+        let obs_file = BuildFile::parse_content(content).unwrap();
+        assert!(obs_file.builds[0].ac_targets.is_empty());
+        assert_eq!(vec![exp_ogre, exp_skeleton], obs_file.builds[0].targets);
+    }
+
+    #[test]
+    fn test_parse_content_level_targets() {
+        // A level with only named targets is valid, as is a build with only AC values
+        let content = r#"
+            [[builds]]
+            name = "Wizard"
+            ruleset = "PF2e"
+
+            [[builds.levels]]
+            level = 5
+            attacks = [{ to_hit = "dc21:reflex", damage = "6d6" }]
+            targets = [{ name = "Ogre Warrior", ac = 17, saves = { reflex = 6 } }]
+
+            [[builds.levels]]
+            level = 7
+            ac_targets = [24]
+            attacks = [{ to_hit = "dc25:reflex", damage = "8d6" }]
+            targets = []
+        "#;
+
+        let obs_file = BuildFile::parse_content(content).unwrap();
+        let obs_levels = obs_file.builds[0].levels.as_ref().unwrap();
+        assert_eq!(1, obs_levels[0].resolve_targets().len());
+        assert_eq!(vec![Target::from_ac(24)], obs_levels[1].resolve_targets());
+    }
+
+    #[test]
+    fn test_parse_content_levels_and_attacks() {
+        let content = r#"
+            [[builds]]
+            name = "Druid"
+            ruleset = "PF2e"
+            ac_targets = [16]
+            attacks = [{ to_hit = "1d20+7", damage = "2d6+2" }]
+
+            [[builds.levels]]
+            level = 1
+            ac_targets = [16]
+            attacks = [{ to_hit = "1d20+7", damage = "2d6+2" }]
+        "#;
+
+        let obs_result = BuildFile::parse_content(content);
+        assert!(obs_result.is_err());
+        assert_eq!(
+            "Build 'Druid' defines both attacks and levels, only one may be used!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_content_level_no_attacks() {
+        let content = r#"
+            [[builds]]
+            name = "Druid"
+            ruleset = "PF2e"
+
+            [[builds.levels]]
+            level = 3
+            ac_targets = [18]
+            attacks = []
+        "#;
+
+        let obs_result = BuildFile::parse_content(content);
+        assert!(obs_result.is_err());
+        assert_eq!(
+            "Level 3 of build 'Druid' does not define any attacks!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_content_no_attacks() {
+        let content = r#"
+            [[builds]]
+            name = "Fighter"
+            ruleset = "PF2e"
+            ac_targets = [16, 18]
+            attacks = []
+        "#;
+
+        let obs_result = BuildFile::parse_content(content);
+        assert!(obs_result.is_err());
+        assert_eq!(
+            "Build 'Fighter' does not define any attacks!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_content_no_builds() {
+        let obs_result = BuildFile::parse_content("builds = []");
+        assert!(obs_result.is_err());
+        assert_eq!(
+            "The build file does not define any builds!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_content_no_targets() {
+        let content = r#"
+            [[builds]]
+            name = "Fighter"
+            ruleset = "PF2e"
+            attacks = [{ to_hit = "1d20+9", damage = "1d8+4" }]
+        "#;
+
+        let obs_result = BuildFile::parse_content(content);
+        assert!(obs_result.is_err());
+        assert_eq!(
+            "Build 'Fighter' does not define any ac_targets or targets!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_content_turn_plan() {
+        let content = r#"
+            [[builds]]
+            name = "Fighter"
+            ruleset = "PF2e"
+            ac_targets = [18]
+            turn_plan = { attack_bonus = 11, actions = ["2a:2d12+4", "1d12+4"] }
+        "#;
+
+        let exp_plan = TurnPlan::new(11, vec!["2a:2d12+4".to_string(), "1d12+4".to_string()]);
+
+        let obs_file = BuildFile::parse_content(content).unwrap();
+        assert_eq!(Some(exp_plan), obs_file.builds[0].turn_plan);
+
+        let (obs_hit, obs_dmg) = obs_file.builds[0].split_attacks();
+        assert_eq!(vec!["1d20+11", "1d20+6"], obs_hit);
+        assert_eq!(vec!["2d12+4", "1d12+4"], obs_dmg);
+    }
+
+    #[test]
+    fn test_parse_content_turn_plan_and_attacks() {
+        let content = r#"
+            [[builds]]
+            name = "Fighter"
+            ruleset = "PF2e"
+            ac_targets = [18]
+            attacks = [{ to_hit = "1d20+11", damage = "1d12+4" }]
+            turn_plan = { attack_bonus = 11, actions = ["1d12+4"] }
+        "#;
+
+        let obs_result = BuildFile::parse_content(content);
+        assert_eq!(
+            "Build 'Fighter' defines both attacks and a turn plan, only one may be used!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_content_turn_plan_ruleset() {
+        let content = r#"
+            [[builds]]
+            name = "Fighter"
+            ruleset = "DND5e"
+            ac_targets = [18]
+            turn_plan = { attack_bonus = 7, actions = ["1d8+4"] }
+        "#;
+
+        let obs_result = BuildFile::parse_content(content);
+        assert_eq!(
+            "Build 'Fighter' uses a turn plan, which requires the PF2e ruleset!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_content_level_turn_plan_invalid() {
+        let content = r#"
+            [[builds]]
+            name = "Monk"
+            ruleset = "PF2e"
+
+            [[builds.levels]]
+            level = 1
+            ac_targets = [16]
+            turn_plan = { attack_bonus = 7, actions = ["agile:1d6+4", "agile:1d6+4", "2a:2d6+4"] }
+        "#;
+
+        let obs_result = BuildFile::parse_content(content);
+        assert_eq!(
+            "Level 1 of build 'Monk' has an invalid turn plan: The turn plan takes 4 actions, but only 3 are available each turn!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_content_invalid() {
+        // Test that misspelled or unknown keys are not silently ignored
+        let content = r#"
+            [[builds]]
+            name = "Fighter"
+            ruleset = "PF2e"
+            ac_target = [16, 18]
+            attacks = [{ to_hit = "1d20+9", damage = "1d8+4" }]
+        "#;
+
+        let obs_result = BuildFile::parse_content(content);
+        assert!(obs_result.is_err());
+        assert!(obs_result
+            .unwrap_err()
+            .to_string()
+            .starts_with("Unable to parse build file:"));
+    }
+
+    #[test]
+    fn test_parse_content_level_no_targets() {
+        let content = r#"
+            [[builds]]
+            name = "Druid"
+            ruleset = "PF2e"
+
+            [[builds.levels]]
+            level = 3
+            attacks = [{ to_hit = "1d20+9", damage = "2d6+2" }]
+        "#;
+
+        let obs_result = BuildFile::parse_content(content);
+        assert!(obs_result.is_err());
+        assert_eq!(
+            "Level 3 of build 'Druid' does not define any ac_targets or targets!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_content_empty_levels() {
+        let content = r#"
+            [[builds]]
+            name = "Druid"
+            ruleset = "PF2e"
+            ac_targets = [18]
+            levels = []
+        "#;
+
+        let obs_result = BuildFile::parse_content(content);
+        assert!(obs_result.is_err());
+        assert_eq!(
+            "Build 'Druid' defines an empty list of levels!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    // endregion:
+
+    // region: from_file() tests
+
+    #[test]
+    fn test_from_file_missing() {
+        let obs_result = BuildFile::from_file("bad_path/builds.toml");
+
+        assert!(obs_result.is_err());
+        assert_eq!(
+            "Unable to read build file 'bad_path/builds.toml'!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    // endregion:
+
+    // region: split_attacks() tests
+
+    #[test]
+    fn test_split_attacks() {
+        let build = Build {
+            name: "Fighter".to_string(),
+            ruleset: Ruleset::PF2e,
+            ac_targets: vec![16],
+            targets: vec![],
+            attacks: vec![
+                spawn_attack("1d20+9", "1d8+4"),
+                spawn_attack("1d20+4", "1d8+5"),
+            ],
+            turn_plan: None,
+            riders: vec![],
+            levels: None,
+            number_turns: None,
+            number_rounds: None,
+            output: None,
+        };
+
+        let (obs_hit, obs_dmg) = build.split_attacks();
+        assert_eq!(vec!["1d20+9", "1d20+4"], obs_hit);
+        assert_eq!(vec!["1d8+4", "1d8+5"], obs_dmg);
+    }
+
+    // endregion:
+
+    // region: resolve_riders() tests
+
+    #[test]
+    fn test_resolve_riders_inherited() {
+        let level_details = LevelDetails {
+            level: 1,
+            ac_targets: vec![16],
+            targets: vec![],
+            attacks: vec![spawn_attack("1d20+7", "2d6+2")],
+            turn_plan: None,
+            riders: None,
+        };
+
+        let obs_riders = level_details.resolve_riders(&["1d6".to_string()]);
+        assert_eq!(vec!["1d6"], obs_riders);
+    }
+
+    #[test]
+    fn test_resolve_riders_override() {
+        let level_details = LevelDetails {
+            level: 1,
+            ac_targets: vec![16],
+            targets: vec![],
+            attacks: vec![spawn_attack("1d20+7", "2d6+2")],
+            turn_plan: None,
+            riders: Some(vec!["2d6".to_string()]),
+        };
+
+        let obs_riders = level_details.resolve_riders(&["1d6".to_string()]);
+        assert_eq!(vec!["2d6"], obs_riders);
+    }
+
+    // endregion:
+
+    // region: resolve_targets() tests
+
+    #[test]
+    fn test_resolve_targets() {
+        let mut ogre = Target::from_ac(15);
+        ogre.name = Some("Ogre".to_string());
+
+        let level_details = LevelDetails {
+            level: 1,
+            ac_targets: vec![16],
+            targets: vec![ogre.clone()],
+            attacks: vec![spawn_attack("1d20+7", "2d6+2")],
+            turn_plan: None,
+            riders: None,
+        };
+
+        let obs_targets = level_details.resolve_targets();
+        assert_eq!(vec![Target::from_ac(16), ogre], obs_targets);
+    }
+
+    // endregion:
+}
@@ -0,0 +1,205 @@
+use std::collections::BTreeMap;
+
+/// The damage dealt by a roll or a turn, split by damage type
+///
+/// Damage without a type is recorded under the `None` key. Two TypedDamage are equal if
+/// they deal the same damage of every type, so a type dealing zero damage is the same as
+/// a type not dealt at all.
+#[derive(Clone, Debug, Default)]
+pub struct TypedDamage {
+    by_type: BTreeMap<Option<String>, i32>,
+}
+
+impl TypedDamage {
+    /// Creates a new TypedDamage representation with no damage dealt.
+    ///
+    /// # Examples
+    /// ```
+    /// let typed_damage = TypedDamage::new();
+    /// ```
+    pub fn new() -> TypedDamage {
+        TypedDamage {
+            by_type: BTreeMap::new(),
+        }
+    }
+
+    /// Add an amount of damage of a single type.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut typed_damage = TypedDamage::new();
+    /// typed_damage.add(&Some(String::from("fire")), 4);
+    /// ```
+    pub fn add(&mut self, damage_type: &Option<String>, value: i32) {
+        *self.by_type.entry(damage_type.clone()).or_insert(0) += value;
+    }
+
+    /// Add all of the damage of another TypedDamage, type by type.
+    ///
+    /// # Examples
+    /// ```
+    /// turn_damage.merge(&attack_damage);
+    /// ```
+    pub fn merge(&mut self, other: &TypedDamage) {
+        for (damage_type, value) in &other.by_type {
+            self.add(damage_type, *value);
+        }
+    }
+
+    /// Return the damage dealt of a single type, which is zero if none was dealt.
+    ///
+    /// # Examples
+    /// ```
+    /// let fire_damage = typed_damage.get(&Some(String::from("fire")));
+    /// ```
+    pub fn get(&self, damage_type: &Option<String>) -> i32 {
+        self.by_type.get(damage_type).copied().unwrap_or(0)
+    }
+
+    /// Iterate over the damage dealt of each type.
+    ///
+    /// # Examples
+    /// ```
+    /// for (damage_type, value) in typed_damage.iter() {
+    ///     println!("{:?}: {}", damage_type, value);
+    /// }
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (&Option<String>, i32)> {
+        self.by_type
+            .iter()
+            .map(|(damage_type, value)| (damage_type, *value))
+    }
+
+    /// Return the total damage dealt across every type.
+    ///
+    /// # Examples
+    /// ```
+    /// let total_damage = typed_damage.total();
+    /// ```
+    pub fn total(&self) -> i32 {
+        self.by_type.values().sum()
+    }
+
+    /// Return the damage halved, halving the total once and rounding down.
+    ///
+    /// Each type is halved and rounded down, and any point lost to rounding the types
+    /// separately is returned to the first types (in order) dealt an odd amount, so that
+    /// 1 fire and 1 cold damage is halved to 1 rather than 0.
+    ///
+    /// # Examples
+    /// ```
+    /// // A successful basic save against 3 fire and 3 cold damage takes 3 damage
+    /// let halved_damage = typed_damage.halve();
+    /// ```
+    pub fn halve(&self) -> TypedDamage {
+        let mut halved_damage = TypedDamage {
+            by_type: self
+                .by_type
+                .iter()
+                .map(|(damage_type, value)| (damage_type.clone(), value / 2))
+                .collect(),
+        };
+
+        let mut remainder = self.total() / 2 - halved_damage.total();
+        for (damage_type, value) in &self.by_type {
+            if remainder == 0 {
+                break;
+            }
+            if value % 2 != 0 {
+                halved_damage.add(damage_type, remainder.signum());
+                remainder -= remainder.signum();
+            }
+        }
+        halved_damage
+    }
+}
+
+impl PartialEq for TypedDamage {
+    fn eq(&self, other: &Self) -> bool {
+        self.by_type
+            .keys()
+            .chain(other.by_type.keys())
+            .all(|damage_type| self.get(damage_type) == other.get(damage_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fire() -> Option<String> {
+        Some("fire".to_string())
+    }
+
+    #[test]
+    fn test_add() {
+        let mut typed_damage = TypedDamage::new();
+        typed_damage.add(&fire(), 4);
+        typed_damage.add(&None, 2);
+        typed_damage.add(&fire(), 3);
+
+        assert_eq!(7, typed_damage.get(&fire()));
+        assert_eq!(2, typed_damage.get(&None));
+        assert_eq!(0, typed_damage.get(&Some("cold".to_string())));
+        assert_eq!(9, typed_damage.total());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut exp_damage = TypedDamage::new();
+        exp_damage.add(&fire(), 5);
+        exp_damage.add(&None, 1);
+
+        let mut obs_damage = TypedDamage::new();
+        obs_damage.add(&fire(), 2);
+        let mut other_damage = TypedDamage::new();
+        other_damage.add(&fire(), 3);
+        other_damage.add(&None, 1);
+        obs_damage.merge(&other_damage);
+
+        assert_eq!(exp_damage, obs_damage);
+    }
+
+    #[test]
+    fn test_eq_zero_damage() {
+        let mut typed_damage = TypedDamage::new();
+        typed_damage.add(&fire(), 0);
+
+        assert_eq!(TypedDamage::new(), typed_damage);
+        typed_damage.add(&None, 1);
+        assert_ne!(TypedDamage::new(), typed_damage);
+    }
+
+    #[test]
+    fn test_halve() {
+        let mut typed_damage = TypedDamage::new();
+        typed_damage.add(&fire(), 3);
+        typed_damage.add(&Some("cold".to_string()), 3);
+
+        // The total is rounded once, with the point lost to rounding returned to the first type
+        let obs_damage = typed_damage.halve();
+        assert_eq!(2, obs_damage.get(&Some("cold".to_string())));
+        assert_eq!(1, obs_damage.get(&fire()));
+        assert_eq!(3, obs_damage.total());
+    }
+
+    #[test]
+    fn test_halve_mixed_types() {
+        let mut typed_damage = TypedDamage::new();
+        typed_damage.add(&fire(), 1);
+        typed_damage.add(&Some("cold".to_string()), 1);
+
+        assert_eq!(1, typed_damage.halve().total());
+    }
+
+    #[test]
+    fn test_halve_even_types() {
+        let mut typed_damage = TypedDamage::new();
+        typed_damage.add(&fire(), 4);
+        typed_damage.add(&None, 3);
+
+        let obs_damage = typed_damage.halve();
+        assert_eq!(2, obs_damage.get(&fire()));
+        assert_eq!(1, obs_damage.get(&None));
+    }
+}
@@ -1,23 +1,52 @@
 use rand::{rngs::StdRng, Rng, SeedableRng};
-use std::cmp::{max, min};
+use std::cmp::max;
 
-use crate::{HitResult, RollBehaviour};
+use crate::distribution::Distribution;
+use crate::{DiceBehaviour, HitResult, RollBehaviour};
+
+/// The maximum number of times a single exploding die may roll again, keeping the
+/// simulated and exact results finite for small dice
+const MAX_EXPLOSIONS: i32 = 20;
 
 /// A representation of a collection of dice
 #[derive(Debug)]
 pub struct Dice {
     pub max: i32,
+    pub damage_type: Option<String>,
     min: i32,
     roll_behaviour: RollBehaviour,
+    compounding: bool,
+    dice_behaviour: DiceBehaviour,
     alt_value: i32,
+    reroll_threshold: i32,
+    face_floor: i32,
     rng_element: StdRng,
 }
 
 // Implement PartialEq to avoid comparing the StdRng instance.
 impl PartialEq for Dice {
     fn eq(&self, other: &Self) -> bool {
-        (self.min, self.max, self.roll_behaviour, self.alt_value)
-            == (other.min, other.max, other.roll_behaviour, other.alt_value)
+        (
+            self.min,
+            self.max,
+            self.roll_behaviour,
+            self.compounding,
+            self.dice_behaviour,
+            self.alt_value,
+            self.reroll_threshold,
+            self.face_floor,
+            &self.damage_type,
+        ) == (
+            other.min,
+            other.max,
+            other.roll_behaviour,
+            other.compounding,
+            other.dice_behaviour,
+            other.alt_value,
+            other.reroll_threshold,
+            other.face_floor,
+            &other.damage_type,
+        )
     }
 }
 
@@ -38,14 +67,22 @@ impl Dice {
         Dice {
             min: 1,
             max: 4,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         }
     }
 
     /// Returns the result of a single dice roll
     ///
+    /// Exploding and compounding dice roll again whenever the maximum face is rolled, adding
+    /// each new roll to the result, up to a limit of MAX_EXPLOSIONS additional rolls.
+    ///
     /// # Examples
     /// ```
     /// let mut my_die = Dice::new();
@@ -53,7 +90,107 @@ impl Dice {
     /// let result = my_die.make_roll();
     /// ```
     fn make_roll(&mut self) -> i32 {
-        self.rng_element.random_range(self.min..self.max + 1)
+        let mut face = self.roll_face(self.max);
+        let mut result = face;
+
+        if self.explodes() {
+            let mut n_explosions = 0;
+            while face == self.max && n_explosions < MAX_EXPLOSIONS {
+                face = self.roll_face(self.max);
+                result += face;
+                n_explosions += 1;
+            }
+        }
+
+        result
+    }
+
+    /// Return true if the die rolls again on its maximum face, either exploding or compounding.
+    ///
+    /// # Examples
+    /// ```
+    /// let my_die = DiceBuilder::new().set_compounding(true).build();
+    /// assert!(my_die.explodes());
+    /// ```
+    fn explodes(&self) -> bool {
+        self.compounding || self.roll_behaviour == RollBehaviour::Exploding
+    }
+
+    /// Return the exact probability distribution of a single dice roll, mirroring Dice::make_roll().
+    ///
+    /// # Examples
+    /// ```
+    /// let my_die = Dice::new();
+    ///
+    /// let dist = my_die.roll_distribution();
+    /// ```
+    fn roll_distribution(&self) -> Distribution {
+        let roll = self.face_distribution(self.max);
+
+        if !self.explodes() {
+            return roll;
+        }
+
+        // Build outwards from the final permitted roll, which cannot explode any further
+        let p_max = roll.iter().last().map_or(0.0, |(_, p)| p);
+        let non_max =
+            Distribution::from_pairs(roll.iter().filter(|(x, _)| *x < self.max).collect());
+
+        let mut dist = roll;
+        for _ in 0..MAX_EXPLOSIONS {
+            let exploded = dist.convolve(&Distribution::point(self.max)).scale(p_max);
+            dist = non_max.add(&exploded);
+        }
+        dist
+    }
+
+    /// Returns the number of dice rolled, the number kept, and whether the highest are kept,
+    /// for roll behaviours which roll a pool of dice.
+    ///
+    /// Advantage, double advantage, and disadvantage are treated as pools of a single kept die.
+    ///
+    /// # Examples
+    /// ```
+    /// let my_die = DiceBuilder::new()
+    ///     .set_roll_behaviour(RollBehaviour::Advantage, None)
+    ///     .build();
+    ///
+    /// assert_eq!(Some((2, 1, true)), my_die.keep_rule());
+    /// ```
+    fn keep_rule(&self) -> Option<(i32, i32, bool)> {
+        match self.roll_behaviour {
+            RollBehaviour::Advantage => Some((2, 1, true)),
+            RollBehaviour::DoubleAdvantage => Some((3, 1, true)),
+            RollBehaviour::Disadvantage => Some((2, 1, false)),
+            RollBehaviour::KeepHighest(pool_size, keep) => Some((pool_size, keep, true)),
+            RollBehaviour::KeepLowest(pool_size, keep) => Some((pool_size, keep, false)),
+            _ => None,
+        }
+    }
+
+    /// Returns the total of the kept dice for pooled roll behaviours, or a single dice roll otherwise
+    ///
+    /// # Examples
+    /// ```
+    /// let mut my_die = DiceBuilder::new()
+    ///     .set_roll_max(6)
+    ///     .set_roll_behaviour(RollBehaviour::KeepHighest(4, 3), None)
+    ///     .build();
+    ///
+    /// let result = my_die.make_pool_roll();
+    /// ```
+    fn make_pool_roll(&mut self) -> i32 {
+        match self.keep_rule() {
+            Some((pool_size, keep, keep_highest)) => {
+                let mut roll_results: Vec<i32> = (0..pool_size).map(|_| self.make_roll()).collect();
+                roll_results.sort_unstable();
+                if keep_highest {
+                    roll_results.reverse();
+                }
+                roll_results.iter().take(keep as usize).sum()
+            }
+            None => self.make_roll(),
+        }
     }
 
     /// Returns the result of a single dice roll using the alternate maximum
@@ -65,7 +202,58 @@ impl Dice {
     /// let result = my_die.make_roll();
     /// ```
     fn make_alt_roll(&mut self) -> i32 {
-        self.rng_element.random_range(self.min..self.alt_value + 1)
+        self.roll_face(self.alt_value)
+    }
+
+    /// Returns the face shown on a single throw of the die, with a face size of max_value
+    ///
+    /// Faces at or below the reroll threshold are rerolled once, and the second result kept. Any
+    /// face below the floor is then counted as the floor value.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut my_die = Dice::new();
+    ///
+    /// let result = my_die.roll_face(4);
+    /// ```
+    fn roll_face(&mut self, max_value: i32) -> i32 {
+        let mut face = self.rng_element.random_range(self.min..max_value + 1);
+        if face <= self.reroll_threshold {
+            face = self.rng_element.random_range(self.min..max_value + 1);
+        }
+        max(face, self.face_floor)
+    }
+
+    /// Return the exact probability distribution of a single throw of the die, mirroring Dice::roll_face().
+    ///
+    /// # Examples
+    /// ```
+    /// let my_die = Dice::new();
+    ///
+    /// let dist = my_die.face_distribution(4);
+    /// ```
+    fn face_distribution(&self, max_value: i32) -> Distribution {
+        let throw = Distribution::uniform(self.min, max_value);
+        let n_rerolled = (self.reroll_threshold.min(max_value) - self.min + 1).max(0);
+        let p_reroll = n_rerolled as f64 / (max_value - self.min + 1) as f64;
+
+        let kept = Distribution::from_pairs(
+            throw
+                .iter()
+                .filter(|(x, _)| *x > self.reroll_threshold)
+                .collect(),
+        );
+        let rerolled = kept.add(&throw.scale(p_reroll));
+
+        match self.face_floor > self.min {
+            true => Distribution::from_pairs(
+                rerolled
+                    .iter()
+                    .map(|(x, p)| (max(x, self.face_floor), p))
+                    .collect(),
+            ),
+            false => rerolled,
+        }
     }
 
     /// Assess the value of the dice roll for an instance of application.
@@ -74,7 +262,8 @@ impl Dice {
     /// on the RollBehaviour flag. If None is provided, the value is returned
     /// unmodified without consideration of a hit result. The main use case for
     /// this is for hit rolls, where as value modification is most likely to be
-    /// required for damage rolls.
+    /// required for damage rolls. Dice with the OnMiss behaviour are rolled only
+    /// when the attack misses.
     ///
     /// # Examples
     /// ```
@@ -87,26 +276,66 @@ impl Dice {
         // Code path for damage rolls, where hit result is considered
         if let Some(hit_result) = hit_condition {
             match (hit_result, &self.roll_behaviour) {
+                (&HitResult::Miss, _) if self.dice_behaviour == DiceBehaviour::OnMiss => {
+                    self.make_pool_roll()
+                }
+                (_, _) if self.dice_behaviour == DiceBehaviour::OnMiss => 0,
                 (&HitResult::CriticalHit, &RollBehaviour::Fatal) => {
                     self.make_alt_roll() + self.make_alt_roll() + self.make_alt_roll()
                 }
+                (&HitResult::CriticalHit, &RollBehaviour::Deadly) => self.make_roll(),
                 (&HitResult::CriticalHit, &RollBehaviour::ExclusiveCrit) => self.make_roll(),
-                (&HitResult::CriticalHit, _) => self.make_roll() + self.make_roll(),
-                (&HitResult::Hit, &RollBehaviour::ExclusiveCrit) => 0,
-                (&HitResult::Hit, _) => self.make_roll(),
+                (&HitResult::CriticalHit, _) => self.make_pool_roll() + self.make_pool_roll(),
+                (&HitResult::Hit, &RollBehaviour::Deadly | &RollBehaviour::ExclusiveCrit) => 0,
+                (&HitResult::Hit, _) => self.make_pool_roll(),
                 (_, _) => 0,
             }
         // Code paths for hit rolls, where hit result is not considered
         } else {
-            match self.roll_behaviour {
-                RollBehaviour::DoubleAdvantage => {
-                    let roll_results = [self.make_roll(), self.make_roll(), self.make_roll()];
-                    *roll_results.iter().max().unwrap()
+            self.make_pool_roll()
+        }
+    }
+
+    /// Return the exact probability distribution of the dice roll for an instance of application.
+    ///
+    /// Mirrors each code path of the Dice::evaluate_result() function, but returns the
+    /// probability of every possible outcome rather than sampling a single result.
+    ///
+    /// # Examples
+    /// ```
+    /// let my_die = Dice::new();
+    ///
+    /// let dist = my_die.distribution(None);
+    /// let dist = my_die.distribution(Some(&HitResult::CriticalHit));
+    /// ```
+    pub fn distribution(&self, hit_condition: Option<&HitResult>) -> Distribution {
+        let roll = self.roll_distribution();
+        let pool_roll = match self.keep_rule() {
+            Some((pool_size, keep, keep_highest)) => roll.keep_of(pool_size, keep, keep_highest),
+            None => roll.clone(),
+        };
+
+        // Code path for damage rolls, where hit result is considered
+        if let Some(hit_result) = hit_condition {
+            match (hit_result, &self.roll_behaviour) {
+                (&HitResult::Miss, _) if self.dice_behaviour == DiceBehaviour::OnMiss => pool_roll,
+                (_, _) if self.dice_behaviour == DiceBehaviour::OnMiss => Distribution::point(0),
+                (&HitResult::CriticalHit, &RollBehaviour::Fatal) => {
+                    let alt_roll = self.face_distribution(self.alt_value);
+                    alt_roll.convolve(&alt_roll).convolve(&alt_roll)
                 }
-                RollBehaviour::Advantage => max(self.make_roll(), self.make_roll()),
-                RollBehaviour::Disadvantage => min(self.make_roll(), self.make_roll()),
-                _ => self.make_roll(),
+                (&HitResult::CriticalHit, &RollBehaviour::Deadly) => roll,
+                (&HitResult::CriticalHit, &RollBehaviour::ExclusiveCrit) => roll,
+                (&HitResult::CriticalHit, _) => pool_roll.convolve(&pool_roll),
+                (&HitResult::Hit, &RollBehaviour::Deadly | &RollBehaviour::ExclusiveCrit) => {
+                    Distribution::point(0)
+                }
+                (&HitResult::Hit, _) => pool_roll,
+                (_, _) => Distribution::point(0),
             }
+        // Code paths for hit rolls, where hit result is not considered
+        } else {
+            pool_roll
         }
     }
 }
@@ -116,8 +345,13 @@ impl Dice {
 pub struct DiceBuilder {
     min: i32,
     max: i32,
+    damage_type: Option<String>,
     roll_behaviour: RollBehaviour,
+    compounding: bool,
+    dice_behaviour: DiceBehaviour,
     alt_value: i32,
+    reroll_threshold: i32,
+    face_floor: i32,
     rng_seed: Option<u64>,
 }
 
@@ -132,8 +366,13 @@ impl DiceBuilder {
         Self {
             min: 1,
             max: 4,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_seed: None,
         }
     }
@@ -179,6 +418,70 @@ impl DiceBuilder {
         self
     }
 
+    /// Set whether the dice compound, adding each roll made on the maximum face to the same die.
+    ///
+    /// Unlike exploding dice, compounding dice keep a single result per die, so can be combined
+    /// with a keep rule or advantage.
+    ///
+    /// # Examples
+    /// ```
+    /// // Rolling four compounding d6 and keeping the highest three (4d6!!kh3)
+    /// let dice_builder = DiceBuilder::new()
+    ///     .set_roll_max(6)
+    ///     .set_roll_behaviour(RollBehaviour::KeepHighest(4, 3), None)
+    ///     .set_compounding(true);
+    /// ```
+    pub fn set_compounding(mut self, compounding: bool) -> Self {
+        self.compounding = compounding;
+        self
+    }
+
+    /// Change whether the dice are rolled on a hit, or only when the attack misses.
+    ///
+    /// # Examples
+    /// ```
+    /// let dice_builder = DiceBuilder::new().set_dice_behaviour(DiceBehaviour::OnMiss);
+    /// ```
+    pub fn set_dice_behaviour(mut self, dice_behaviour: DiceBehaviour) -> Self {
+        self.dice_behaviour = dice_behaviour;
+        self
+    }
+
+    /// Set the face value at or below which a roll is rerolled once, keeping the second result.
+    ///
+    /// # Examples
+    /// ```
+    /// // Great Weapon Fighting, rerolling 1s and 2s
+    /// let dice_builder = DiceBuilder::new().set_roll_max(6).set_reroll_threshold(2);
+    /// ```
+    pub fn set_reroll_threshold(mut self, threshold: i32) -> Self {
+        self.reroll_threshold = threshold;
+        self
+    }
+
+    /// Set the lowest value a single die can contribute, with any lower face counted as this value.
+    ///
+    /// # Examples
+    /// ```
+    /// // Elemental Adept, treating 1s as 2s
+    /// let dice_builder = DiceBuilder::new().set_roll_max(8).set_face_floor(2);
+    /// ```
+    pub fn set_face_floor(mut self, floor: i32) -> Self {
+        self.face_floor = floor;
+        self
+    }
+
+    /// Set the type of the damage dealt by the dice, or None for untyped damage.
+    ///
+    /// # Examples
+    /// ```
+    /// let dice_builder = DiceBuilder::new().set_roll_max(6).set_damage_type(Some(String::from("fire")));
+    /// ```
+    pub fn set_damage_type(mut self, damage_type: Option<String>) -> Self {
+        self.damage_type = damage_type;
+        self
+    }
+
     /// Set the RNG for the roll seed to a specific value.
     ///
     /// # Examples
@@ -209,8 +512,13 @@ impl DiceBuilder {
         Dice {
             min: self.min,
             max: self.max,
+            damage_type: self.damage_type,
             roll_behaviour: self.roll_behaviour,
+            compounding: self.compounding,
+            dice_behaviour: self.dice_behaviour,
             alt_value: self.alt_value,
+            reroll_threshold: self.reroll_threshold,
+            face_floor: self.face_floor,
             rng_element: rng_init,
         }
     }
@@ -220,7 +528,7 @@ impl DiceBuilder {
 mod tests {
     use super::*;
 
-    fn unpack_roll_vector(roll_capture: &Vec<i32>) -> (i32, i32) {
+    fn unpack_roll_vector(roll_capture: &[i32]) -> (i32, i32) {
         let obs_min: i32 = *roll_capture.iter().min().unwrap();
         let obs_max: i32 = *roll_capture.iter().max().unwrap();
 
@@ -232,8 +540,13 @@ mod tests {
         let exp_die = Dice {
             min: 1,
             max: 4,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         };
 
@@ -256,8 +569,13 @@ mod tests {
         let mut my_die = Dice {
             min: 1,
             max: 4,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 10,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         };
 
@@ -267,6 +585,75 @@ mod tests {
         assert_eq!((1, 10), obs_results);
     }
 
+    #[test]
+    fn test_make_roll_exploding() {
+        let mut my_die = DiceBuilder::new()
+            .set_roll_max(2)
+            .set_roll_behaviour(RollBehaviour::Exploding, None)
+            .build();
+
+        // Rolling a 2 always explodes into a further roll, so a total of exactly 2 is impossible
+        let roll_results: Vec<i32> = (0..10_000).map(|_| my_die.make_roll()).collect();
+        let obs_results: (i32, i32) = unpack_roll_vector(&roll_results);
+
+        assert_eq!(1, obs_results.0);
+        assert!(obs_results.1 > 2);
+        assert!(!roll_results.contains(&2));
+    }
+
+    #[test]
+    fn test_make_roll_exploding_capped() {
+        // A single-sided die always explodes, so only stops at the explosion limit
+        let mut my_die = DiceBuilder::new()
+            .set_roll_max(1)
+            .set_compounding(true)
+            .build();
+
+        assert_eq!(MAX_EXPLOSIONS + 1, my_die.make_roll());
+    }
+
+    #[test]
+    fn test_make_roll_reroll() {
+        let mut my_die = DiceBuilder::new()
+            .set_roll_max(6)
+            .set_reroll_threshold(2)
+            .set_rng_seed(1)
+            .build();
+
+        // Low faces are still possible on the reroll, but are seen at a reduced rate
+        let roll_results: Vec<i32> = (0..60_000).map(|_| my_die.make_roll()).collect();
+        let obs_results: (i32, i32) = unpack_roll_vector(&roll_results);
+        assert_eq!((1, 6), obs_results);
+
+        let n_ones = roll_results.iter().filter(|x| **x == 1).count();
+        let n_sixes = roll_results.iter().filter(|x| **x == 6).count();
+        assert!(n_ones < 4_000);
+        assert!(n_sixes > 12_000);
+    }
+
+    #[test]
+    fn test_make_roll_floor() {
+        let mut my_die = DiceBuilder::new().set_roll_max(6).set_face_floor(3).build();
+
+        let roll_results: Vec<i32> = (0..10_000).map(|_| my_die.make_roll()).collect();
+        let obs_results: (i32, i32) = unpack_roll_vector(&roll_results);
+
+        assert_eq!((3, 6), obs_results);
+    }
+
+    #[test]
+    fn test_make_pool_roll() {
+        let mut my_die = DiceBuilder::new()
+            .set_roll_max(6)
+            .set_roll_behaviour(RollBehaviour::KeepLowest(4, 3), None)
+            .build();
+
+        let roll_results: Vec<i32> = (0..10_000).map(|_| my_die.make_pool_roll()).collect();
+        let obs_results: (i32, i32) = unpack_roll_vector(&roll_results);
+
+        assert_eq!((3, 18), obs_results);
+    }
+
     // region: Dice::evaluate_result() with HitResult tests
 
     #[test]
@@ -274,8 +661,13 @@ mod tests {
         let mut my_die = Dice {
             min: 1,
             max: 4,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Fatal,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 10,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         };
 
@@ -301,13 +693,55 @@ mod tests {
         assert_eq!((0, 0), obs_results);
     }
 
+    #[test]
+    fn test_evaluate_result_hit_deadly() {
+        let mut my_die = Dice {
+            min: 1,
+            max: 10,
+            damage_type: None,
+            roll_behaviour: RollBehaviour::Deadly,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
+            alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
+            rng_element: StdRng::from_os_rng(),
+        };
+
+        // Test for result on critical hit, where the die is added but not doubled
+        let roll_results: Vec<i32> = (0..10_000)
+            .map(|_| my_die.evaluate_result(Some(&HitResult::CriticalHit)))
+            .collect();
+        let obs_results: (i32, i32) = unpack_roll_vector(&roll_results);
+        assert_eq!((1, 10), obs_results);
+
+        // Test for result on regular hit
+        let roll_results: Vec<i32> = (0..10_000)
+            .map(|_| my_die.evaluate_result(Some(&HitResult::Hit)))
+            .collect();
+        let obs_results: (i32, i32) = unpack_roll_vector(&roll_results);
+        assert_eq!((0, 0), obs_results);
+
+        // Test for result on miss
+        let roll_results: Vec<i32> = (0..10_000)
+            .map(|_| my_die.evaluate_result(Some(&HitResult::Miss)))
+            .collect();
+        let obs_results: (i32, i32) = unpack_roll_vector(&roll_results);
+        assert_eq!((0, 0), obs_results);
+    }
+
     #[test]
     fn test_evaluate_result_hit_excl_crit() {
         let mut my_die = Dice {
             min: 1,
             max: 4,
+            damage_type: None,
             roll_behaviour: RollBehaviour::ExclusiveCrit,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         };
 
@@ -338,8 +772,13 @@ mod tests {
         let mut my_die = Dice {
             min: 1,
             max: 4,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         };
 
@@ -365,6 +804,29 @@ mod tests {
         assert_eq!((0, 0), obs_results);
     }
 
+    #[test]
+    fn test_evaluate_result_hit_on_miss() {
+        let mut my_die = DiceBuilder::new()
+            .set_roll_max(4)
+            .set_dice_behaviour(DiceBehaviour::OnMiss)
+            .build();
+
+        // Dice dealt on a miss are rolled once, and deal no damage on a hit or critical hit
+        let roll_results: Vec<i32> = (0..10_000)
+            .map(|_| my_die.evaluate_result(Some(&HitResult::Miss)))
+            .collect();
+        let obs_results: (i32, i32) = unpack_roll_vector(&roll_results);
+        assert_eq!((1, 4), obs_results);
+
+        for hit_result in [HitResult::Hit, HitResult::CriticalHit] {
+            let roll_results: Vec<i32> = (0..1_000)
+                .map(|_| my_die.evaluate_result(Some(&hit_result)))
+                .collect();
+            let obs_results: (i32, i32) = unpack_roll_vector(&roll_results);
+            assert_eq!((0, 0), obs_results);
+        }
+    }
+
     // endregion:
 
     // region: Dice::evaluate_result() with no modifier tests
@@ -377,16 +839,26 @@ mod tests {
         let mut adv_die = Dice {
             min: 1,
             max: 20,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Advantage,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         };
 
         let mut dbl_die = Dice {
             min: 1,
             max: 20,
+            damage_type: None,
             roll_behaviour: RollBehaviour::DoubleAdvantage,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         };
 
@@ -412,16 +884,26 @@ mod tests {
         let mut std_die = Dice {
             min: 1,
             max: 20,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         };
 
         let mut adv_die = Dice {
             min: 1,
             max: 20,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Advantage,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         };
 
@@ -447,16 +929,26 @@ mod tests {
         let mut std_die = Dice {
             min: 1,
             max: 20,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         };
 
         let mut dis_die = Dice {
             min: 1,
             max: 20,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Disadvantage,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         };
 
@@ -479,8 +971,13 @@ mod tests {
         let mut std_die = Dice {
             min: 1,
             max: 20,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         };
 
@@ -491,6 +988,332 @@ mod tests {
 
     // endregion:
 
+    // region: Dice::distribution() tests
+
+    #[test]
+    fn test_distribution_none_standard() {
+        let my_die = Dice::new();
+
+        let exp_dist = Distribution::uniform(1, 4);
+        assert_eq!(exp_dist, my_die.distribution(None));
+    }
+
+    #[test]
+    fn test_distribution_none_advantage() {
+        let my_die = DiceBuilder::new()
+            .set_roll_max(2)
+            .set_roll_behaviour(RollBehaviour::Advantage, None)
+            .build();
+
+        let exp_dist = Distribution::new(1, vec![0.25, 0.75]);
+        assert_eq!(exp_dist, my_die.distribution(None));
+    }
+
+    #[test]
+    fn test_distribution_none_dbl_advantage() {
+        let my_die = DiceBuilder::new()
+            .set_roll_max(2)
+            .set_roll_behaviour(RollBehaviour::DoubleAdvantage, None)
+            .build();
+
+        let exp_dist = Distribution::new(1, vec![0.125, 0.875]);
+        assert_eq!(exp_dist, my_die.distribution(None));
+    }
+
+    #[test]
+    fn test_distribution_none_disadvantage() {
+        let my_die = DiceBuilder::new()
+            .set_roll_max(2)
+            .set_roll_behaviour(RollBehaviour::Disadvantage, None)
+            .build();
+
+        let exp_dist = Distribution::new(1, vec![0.75, 0.25]);
+        assert_eq!(exp_dist, my_die.distribution(None));
+    }
+
+    #[test]
+    fn test_distribution_none_exploding() {
+        let my_die = DiceBuilder::new()
+            .set_roll_max(4)
+            .set_roll_behaviour(RollBehaviour::Exploding, None)
+            .build();
+
+        let obs_dist = my_die.distribution(None);
+        let obs_total: f64 = obs_dist.iter().map(|(_, p)| p).sum();
+        assert!((obs_total - 1.0).abs() < 1e-9);
+
+        // Rolls of 1-3 stop immediately, while a 4 is always followed by a further roll
+        let obs_pairs: Vec<(i32, f64)> = obs_dist.iter().take(7).collect();
+        let exp_pairs = [
+            (1, 0.25),
+            (2, 0.25),
+            (3, 0.25),
+            (4, 0.0),
+            (5, 0.0625),
+            (6, 0.0625),
+            (7, 0.0625),
+        ];
+        for ((exp_value, exp_p), (obs_value, obs_p)) in exp_pairs.iter().zip(obs_pairs.iter()) {
+            assert_eq!(exp_value, obs_value);
+            assert!((exp_p - obs_p).abs() < 1e-9);
+        }
+
+        // Expected value of an exploding die is (n + 1) / 2 * n / (n - 1)
+        assert!((obs_dist.mean() - 10.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_distribution_none_exploding_capped() {
+        let my_die = DiceBuilder::new()
+            .set_roll_max(1)
+            .set_roll_behaviour(RollBehaviour::Exploding, None)
+            .build();
+
+        assert_eq!(
+            Distribution::point(MAX_EXPLOSIONS + 1),
+            my_die.distribution(None)
+        );
+    }
+
+    #[test]
+    fn test_distribution_hit_exploding() {
+        let my_die = DiceBuilder::new()
+            .set_roll_max(6)
+            .set_compounding(true)
+            .build();
+
+        // A critical hit rolls two independent exploding dice
+        let obs_dist = my_die.distribution(Some(&HitResult::CriticalHit));
+        assert!((obs_dist.mean() - 8.4).abs() < 1e-9);
+
+        let obs_dist = my_die.distribution(Some(&HitResult::Hit));
+        assert!((obs_dist.mean() - 4.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_distribution_none_reroll() {
+        let my_die = DiceBuilder::new()
+            .set_roll_max(4)
+            .set_reroll_threshold(1)
+            .build();
+
+        let exp_dist = Distribution::new(1, vec![0.0625, 0.3125, 0.3125, 0.3125]);
+        assert_eq!(exp_dist, my_die.distribution(None));
+    }
+
+    #[test]
+    fn test_distribution_hit_reroll() {
+        // Great Weapon Fighting on a greatsword, which rerolls each die separately on a critical hit
+        let my_die = DiceBuilder::new()
+            .set_roll_max(6)
+            .set_reroll_threshold(2)
+            .build();
+
+        let obs_dist = my_die.distribution(Some(&HitResult::Hit));
+        assert!((obs_dist.mean() - 25.0 / 6.0).abs() < 1e-9);
+
+        let obs_dist = my_die.distribution(Some(&HitResult::CriticalHit));
+        assert!((obs_dist.mean() - 50.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_distribution_hit_fatal_reroll() {
+        let my_die = DiceBuilder::new()
+            .set_roll_max(1)
+            .set_roll_behaviour(RollBehaviour::Fatal, Some(2))
+            .set_reroll_threshold(1)
+            .build();
+
+        // Each fatal d2 rerolls a 1, giving a 3 in 4 chance of rolling a 2
+        let exp_dist = Distribution::new(3, vec![1.0 / 64.0, 9.0 / 64.0, 27.0 / 64.0, 27.0 / 64.0]);
+        assert_eq!(exp_dist, my_die.distribution(Some(&HitResult::CriticalHit)));
+    }
+
+    #[test]
+    fn test_distribution_none_floor() {
+        let my_die = DiceBuilder::new().set_roll_max(4).set_face_floor(3).build();
+
+        let exp_dist = Distribution::new(3, vec![0.75, 0.25]);
+        assert_eq!(exp_dist, my_die.distribution(None));
+    }
+
+    #[test]
+    fn test_distribution_hit_floor() {
+        // Both dice of a critical hit are raised to the floor separately
+        let my_die = DiceBuilder::new().set_roll_max(2).set_face_floor(2).build();
+
+        let obs_dist = my_die.distribution(Some(&HitResult::CriticalHit));
+        assert_eq!(Distribution::new(4, vec![1.0]), obs_dist);
+
+        let obs_dist = my_die.distribution(Some(&HitResult::Hit));
+        assert_eq!(Distribution::new(2, vec![1.0]), obs_dist);
+    }
+
+    #[test]
+    fn test_distribution_none_reroll_floor() {
+        // Rerolls are resolved before the floor is applied
+        let my_die = DiceBuilder::new()
+            .set_roll_max(4)
+            .set_reroll_threshold(1)
+            .set_face_floor(2)
+            .build();
+
+        let exp_dist = Distribution::new(2, vec![0.375, 0.3125, 0.3125]);
+        assert_eq!(exp_dist, my_die.distribution(None));
+    }
+
+    #[test]
+    fn test_distribution_none_keep_matches_advantage() {
+        // Advantage and disadvantage are pools of two dice keeping one
+        let pairs = [
+            (RollBehaviour::Advantage, RollBehaviour::KeepHighest(2, 1)),
+            (
+                RollBehaviour::DoubleAdvantage,
+                RollBehaviour::KeepHighest(3, 1),
+            ),
+            (RollBehaviour::Disadvantage, RollBehaviour::KeepLowest(2, 1)),
+        ];
+
+        for (named_behaviour, pool_behaviour) in pairs {
+            let named_die = DiceBuilder::new()
+                .set_roll_max(20)
+                .set_roll_behaviour(named_behaviour, None)
+                .build();
+            let pool_die = DiceBuilder::new()
+                .set_roll_max(20)
+                .set_roll_behaviour(pool_behaviour, None)
+                .build();
+
+            assert_eq!(named_die.distribution(None), pool_die.distribution(None));
+        }
+    }
+
+    #[test]
+    fn test_distribution_hit_keep() {
+        let my_die = DiceBuilder::new()
+            .set_roll_max(2)
+            .set_roll_behaviour(RollBehaviour::KeepHighest(3, 2), None)
+            .build();
+
+        // Only a roll of three 1s keeps a total of 2, and only one 2 keeps a total of 3
+        let exp_dist = Distribution::new(2, vec![0.125, 0.375, 0.5]);
+        let obs_dist = my_die.distribution(Some(&HitResult::Hit));
+        assert_eq!(exp_dist, obs_dist);
+
+        // A critical hit rolls the whole pool twice
+        let obs_dist = my_die.distribution(Some(&HitResult::CriticalHit));
+        assert!((obs_dist.mean() - 6.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_distribution_keep_compounding() {
+        // Each d2 in the pool compounds on a 2, so a kept total of exactly 2 is impossible
+        let my_die = DiceBuilder::new()
+            .set_roll_max(2)
+            .set_roll_behaviour(RollBehaviour::KeepHighest(2, 1), None)
+            .set_compounding(true)
+            .build();
+
+        let obs_pairs: Vec<(i32, f64)> = my_die.distribution(None).iter().take(3).collect();
+        let exp_pairs = [(1, 0.25), (2, 0.0), (3, 0.3125)];
+        for ((exp_value, exp_p), (obs_value, obs_p)) in exp_pairs.iter().zip(obs_pairs.iter()) {
+            assert_eq!(exp_value, obs_value);
+            assert!((exp_p - obs_p).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_make_pool_roll_compounding() {
+        let mut my_die = DiceBuilder::new()
+            .set_roll_max(2)
+            .set_roll_behaviour(RollBehaviour::KeepHighest(2, 1), None)
+            .set_compounding(true)
+            .build();
+
+        let roll_results: Vec<i32> = (0..10_000).map(|_| my_die.make_pool_roll()).collect();
+        assert!(!roll_results.contains(&2));
+        assert!(roll_results.iter().any(|x| *x > 2));
+    }
+
+    #[test]
+    fn test_distribution_hit_standard() {
+        let my_die = DiceBuilder::new().set_roll_max(2).build();
+
+        let obs_dist = my_die.distribution(Some(&HitResult::CriticalHit));
+        assert_eq!(Distribution::new(2, vec![0.25, 0.5, 0.25]), obs_dist);
+
+        let obs_dist = my_die.distribution(Some(&HitResult::Hit));
+        assert_eq!(Distribution::uniform(1, 2), obs_dist);
+
+        let obs_dist = my_die.distribution(Some(&HitResult::Miss));
+        assert_eq!(Distribution::point(0), obs_dist);
+    }
+
+    #[test]
+    fn test_distribution_hit_fatal() {
+        let my_die = DiceBuilder::new()
+            .set_roll_max(1)
+            .set_roll_behaviour(RollBehaviour::Fatal, Some(2))
+            .build();
+
+        // Three d2 rolled on a critical hit
+        let obs_dist = my_die.distribution(Some(&HitResult::CriticalHit));
+        assert_eq!(
+            Distribution::new(3, vec![0.125, 0.375, 0.375, 0.125]),
+            obs_dist
+        );
+
+        let obs_dist = my_die.distribution(Some(&HitResult::Hit));
+        assert_eq!(Distribution::point(1), obs_dist);
+    }
+
+    #[test]
+    fn test_distribution_hit_deadly() {
+        let my_die = DiceBuilder::new()
+            .set_roll_max(2)
+            .set_roll_behaviour(RollBehaviour::Deadly, None)
+            .build();
+
+        let obs_dist = my_die.distribution(Some(&HitResult::CriticalHit));
+        assert_eq!(Distribution::uniform(1, 2), obs_dist);
+
+        let obs_dist = my_die.distribution(Some(&HitResult::Hit));
+        assert_eq!(Distribution::point(0), obs_dist);
+    }
+
+    #[test]
+    fn test_distribution_hit_excl_crit() {
+        let my_die = DiceBuilder::new()
+            .set_roll_max(2)
+            .set_roll_behaviour(RollBehaviour::ExclusiveCrit, None)
+            .build();
+
+        let obs_dist = my_die.distribution(Some(&HitResult::CriticalHit));
+        assert_eq!(Distribution::uniform(1, 2), obs_dist);
+
+        let obs_dist = my_die.distribution(Some(&HitResult::Hit));
+        assert_eq!(Distribution::point(0), obs_dist);
+    }
+
+    #[test]
+    fn test_distribution_hit_on_miss() {
+        let my_die = DiceBuilder::new()
+            .set_roll_max(2)
+            .set_dice_behaviour(DiceBehaviour::OnMiss)
+            .build();
+
+        let obs_dist = my_die.distribution(Some(&HitResult::Miss));
+        assert_eq!(Distribution::uniform(1, 2), obs_dist);
+
+        let obs_dist = my_die.distribution(Some(&HitResult::Hit));
+        assert_eq!(Distribution::point(0), obs_dist);
+
+        let obs_dist = my_die.distribution(Some(&HitResult::CriticalHit));
+        assert_eq!(Distribution::point(0), obs_dist);
+    }
+
+    // endregion:
+
     // region: DiceBuilder
 
     #[test]
@@ -498,8 +1321,13 @@ mod tests {
         let exp_result = DiceBuilder {
             min: 1,
             max: 4,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_seed: None,
         };
 
@@ -512,8 +1340,13 @@ mod tests {
         let exp_result = DiceBuilder {
             min: 4,
             max: 4,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_seed: None,
         };
 
@@ -526,8 +1359,13 @@ mod tests {
         let exp_result = DiceBuilder {
             min: 1,
             max: 10,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_seed: None,
         };
 
@@ -540,8 +1378,13 @@ mod tests {
         let exp_result = DiceBuilder {
             min: 1,
             max: 4,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Advantage,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_seed: None,
         };
 
@@ -554,8 +1397,13 @@ mod tests {
         let exp_result = DiceBuilder {
             min: 1,
             max: 4,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Fatal,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 10,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_seed: None,
         };
 
@@ -563,17 +1411,117 @@ mod tests {
         assert_eq!(exp_result, obs_result);
     }
 
+    #[test]
+    fn test_builder_set_compounding() {
+        let exp_result = DiceBuilder {
+            min: 1,
+            max: 4,
+            damage_type: None,
+            roll_behaviour: RollBehaviour::Standard,
+            compounding: true,
+            dice_behaviour: DiceBehaviour::Standard,
+            alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
+            rng_seed: None,
+        };
+
+        let obs_result = DiceBuilder::new().set_compounding(true);
+        assert_eq!(exp_result, obs_result);
+    }
+
+    #[test]
+    fn test_builder_set_dice_behaviour() {
+        let exp_result = DiceBuilder {
+            min: 1,
+            max: 4,
+            damage_type: None,
+            roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::OnMiss,
+            alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
+            rng_seed: None,
+        };
+
+        let obs_result = DiceBuilder::new().set_dice_behaviour(DiceBehaviour::OnMiss);
+        assert_eq!(exp_result, obs_result);
+    }
+
+    #[test]
+    fn test_builder_set_reroll_threshold() {
+        let exp_result = DiceBuilder {
+            min: 1,
+            max: 4,
+            damage_type: None,
+            roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
+            alt_value: 0,
+            reroll_threshold: 2,
+            face_floor: 0,
+            rng_seed: None,
+        };
+
+        let obs_result = DiceBuilder::new().set_reroll_threshold(2);
+        assert_eq!(exp_result, obs_result);
+    }
+
+    #[test]
+    fn test_builder_set_face_floor() {
+        let exp_result = DiceBuilder {
+            min: 1,
+            max: 4,
+            damage_type: None,
+            roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
+            alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 3,
+            rng_seed: None,
+        };
+
+        let obs_result = DiceBuilder::new().set_face_floor(3);
+        assert_eq!(exp_result, obs_result);
+    }
+
+    #[test]
+    fn test_builder_set_damage_type() {
+        let exp_result = DiceBuilder {
+            min: 1,
+            max: 4,
+            damage_type: Some("fire".to_string()),
+            roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
+            alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
+            rng_seed: None,
+        };
+
+        let obs_result = DiceBuilder::new().set_damage_type(Some("fire".to_string()));
+        assert_eq!(exp_result, obs_result);
+    }
+
     #[test]
     fn test_builder_set_rng_seed() {
         let exp_result = DiceBuilder {
             min: 1,
             max: 4,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Standard,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_seed: Some(10),
         };
 
-        let obs_result = DiceBuilder::new().set_rng_seed(10 as u64);
+        let obs_result = DiceBuilder::new().set_rng_seed(10_u64);
         assert_eq!(exp_result, obs_result);
     }
 
@@ -586,8 +1534,13 @@ mod tests {
         let exp_result = Dice {
             min: 2,
             max: 10,
+            damage_type: None,
             roll_behaviour: RollBehaviour::Advantage,
+            compounding: false,
+            dice_behaviour: DiceBehaviour::Standard,
             alt_value: 0,
+            reroll_threshold: 0,
+            face_floor: 0,
             rng_element: StdRng::from_os_rng(),
         };
 
@@ -0,0 +1,437 @@
+use std::cmp::{max, min};
+use std::collections::BTreeMap;
+
+/// A discrete probability mass function over a contiguous range of integer outcomes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Distribution {
+    offset: i32,
+    probabilities: Vec<f64>,
+}
+
+impl Distribution {
+    /// Creates a new Distribution from the probability of each value, starting at the offset.
+    ///
+    /// # Examples
+    /// ```
+    /// // A fair coin scoring 0 or 1
+    /// let coin = Distribution::new(0, vec![0.5, 0.5]);
+    /// ```
+    pub fn new(offset: i32, probabilities: Vec<f64>) -> Distribution {
+        Distribution {
+            offset,
+            probabilities,
+        }
+    }
+
+    /// Creates a Distribution in which a single value occurs with certainty.
+    ///
+    /// # Examples
+    /// ```
+    /// let modifier = Distribution::point(5);
+    /// ```
+    pub fn point(value: i32) -> Distribution {
+        Distribution::new(value, vec![1.0])
+    }
+
+    /// Creates a Distribution in which no outcome occurs, used as the identity for Distribution::add().
+    ///
+    /// # Examples
+    /// ```
+    /// let empty = Distribution::empty();
+    /// ```
+    pub fn empty() -> Distribution {
+        Distribution::new(0, Vec::new())
+    }
+
+    /// Creates a Distribution with equal probability for every value between min and max (inclusive).
+    ///
+    /// # Examples
+    /// ```
+    /// // A single d6
+    /// let d6 = Distribution::uniform(1, 6);
+    /// ```
+    pub fn uniform(min_value: i32, max_value: i32) -> Distribution {
+        let n_values = (max_value - min_value + 1) as usize;
+        Distribution::new(min_value, vec![1.0 / n_values as f64; n_values])
+    }
+
+    /// Creates a Distribution from a set of (value, probability) pairs.
+    ///
+    /// Repeated values are accumulated, so the pairs do not need to be unique.
+    ///
+    /// # Examples
+    /// ```
+    /// let dist = Distribution::from_pairs(vec![(1, 0.25), (3, 0.5), (1, 0.25)]);
+    /// ```
+    pub fn from_pairs(pairs: Vec<(i32, f64)>) -> Distribution {
+        if pairs.is_empty() {
+            return Distribution::empty();
+        }
+
+        let low = pairs.iter().map(|(v, _)| *v).min().unwrap();
+        let high = pairs.iter().map(|(v, _)| *v).max().unwrap();
+
+        let mut probabilities = vec![0.0; (high - low + 1) as usize];
+        for (value, probability) in pairs {
+            probabilities[(value - low) as usize] += probability;
+        }
+
+        Distribution::new(low, probabilities)
+    }
+
+    /// Return an iterator over the (value, probability) pairs of the Distribution.
+    ///
+    /// # Examples
+    /// ```
+    /// for (value, probability) in Distribution::uniform(1, 4).iter() {
+    ///     println!("{}: {}", value, probability);
+    /// }
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (i32, f64)> + '_ {
+        self.probabilities
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (self.offset + i as i32, *p))
+    }
+
+    /// Return the expected value of the Distribution.
+    ///
+    /// # Examples
+    /// ```
+    /// let mean = Distribution::uniform(1, 6).mean();
+    /// ```
+    #[allow(dead_code)]
+    pub fn mean(&self) -> f64 {
+        self.iter().map(|(v, p)| v as f64 * p).sum()
+    }
+
+    /// Return the variance of the Distribution.
+    ///
+    /// # Examples
+    /// ```
+    /// let variance = Distribution::uniform(1, 6).variance();
+    /// ```
+    #[allow(dead_code)]
+    pub fn variance(&self) -> f64 {
+        let mean = self.mean();
+        self.iter()
+            .map(|(v, p)| (v as f64 - mean).powi(2) * p)
+            .sum()
+    }
+
+    /// Return the Distribution of the sum of two independent Distributions.
+    ///
+    /// # Examples
+    /// ```
+    /// // Rolling 2d6
+    /// let d6 = Distribution::uniform(1, 6);
+    /// let two_d6 = d6.convolve(&d6);
+    /// ```
+    pub fn convolve(&self, other: &Distribution) -> Distribution {
+        if self.probabilities.is_empty() || other.probabilities.is_empty() {
+            return Distribution::empty();
+        }
+
+        let mut probabilities = vec![0.0; self.probabilities.len() + other.probabilities.len() - 1];
+        for (i, p_left) in self.probabilities.iter().enumerate() {
+            for (j, p_right) in other.probabilities.iter().enumerate() {
+                probabilities[i + j] += p_left * p_right;
+            }
+        }
+
+        Distribution::new(self.offset + other.offset, probabilities)
+    }
+
+    /// Return the Distribution with all probabilities multiplied by a weighting factor.
+    ///
+    /// # Examples
+    /// ```
+    /// // Damage dealt only when a 65% chance to hit succeeds
+    /// let on_hit = Distribution::uniform(1, 8).scale(0.65);
+    /// ```
+    pub fn scale(&self, factor: f64) -> Distribution {
+        let probabilities = self.probabilities.iter().map(|p| p * factor).collect();
+        Distribution::new(self.offset, probabilities)
+    }
+
+    /// Return the pointwise sum of two Distributions.
+    ///
+    /// Used to combine mutually exclusive branches of an event, each of which has been
+    /// weighted by its probability using Distribution::scale().
+    ///
+    /// # Examples
+    /// ```
+    /// let hit = Distribution::uniform(1, 8).scale(0.6);
+    /// let miss = Distribution::point(0).scale(0.4);
+    /// let damage = hit.add(&miss);
+    /// ```
+    pub fn add(&self, other: &Distribution) -> Distribution {
+        let mut pairs: Vec<(i32, f64)> = self.iter().collect();
+        pairs.extend(other.iter());
+        Distribution::from_pairs(pairs)
+    }
+
+    /// Return the Distribution of each value halved, rounding towards zero.
+    ///
+    /// # Examples
+    /// ```
+    /// // Half damage from 8d6 on a successful save
+    /// let half_damage = Distribution::uniform(1, 6).halve();
+    /// ```
+    pub fn halve(&self) -> Distribution {
+        Distribution::from_pairs(self.iter().map(|(v, p)| (v / 2, p)).collect())
+    }
+
+    /// Return the Distribution of the higher value of two independent Distributions.
+    ///
+    /// # Examples
+    /// ```
+    /// // Rolling a d20 with advantage
+    /// let d20 = Distribution::uniform(1, 20);
+    /// let advantage = d20.max_of(&d20);
+    /// ```
+    #[allow(dead_code)]
+    pub fn max_of(&self, other: &Distribution) -> Distribution {
+        self.combine(other, max)
+    }
+
+    /// Return the Distribution of the lower value of two independent Distributions.
+    ///
+    /// # Examples
+    /// ```
+    /// // Rolling a d20 with disadvantage
+    /// let d20 = Distribution::uniform(1, 20);
+    /// let disadvantage = d20.min_of(&d20);
+    /// ```
+    #[allow(dead_code)]
+    pub fn min_of(&self, other: &Distribution) -> Distribution {
+        self.combine(other, min)
+    }
+
+    /// Return the Distribution of the sum of the highest (or lowest) of a pool of independent draws.
+    ///
+    /// Works through the possible values from best to worst, tracking how many dice of the
+    /// pool have been assigned a value and the running total of those which are kept.
+    ///
+    /// # Examples
+    /// ```
+    /// // Rolling 4d6 and keeping the highest three
+    /// let d6 = Distribution::uniform(1, 6);
+    /// let ability_score = d6.keep_of(4, 3, true);
+    /// ```
+    pub fn keep_of(&self, pool_size: i32, keep: i32, keep_highest: bool) -> Distribution {
+        let mut values: Vec<(i32, f64)> = self.iter().filter(|(_, p)| *p > 0.0).collect();
+        if keep_highest {
+            values.reverse();
+        }
+
+        // States are keyed on the number of dice assigned, and the total of the kept dice. These
+        // are ordered so that the floating point sums do not depend on iteration order.
+        let mut states: BTreeMap<(i32, i32), f64> = BTreeMap::from([((0, 0), 1.0)]);
+        for (value, p_value) in values {
+            let mut next_states: BTreeMap<(i32, i32), f64> = BTreeMap::new();
+
+            for ((n_assigned, total), p_state) in states {
+                let n_remaining = pool_size - n_assigned;
+                let mut n_ways = 1.0;
+
+                for n_value in 0..=n_remaining {
+                    let n_kept = min(n_value, max(keep - n_assigned, 0));
+                    let p_next = p_state * n_ways * p_value.powi(n_value);
+                    *next_states
+                        .entry((n_assigned + n_value, total + n_kept * value))
+                        .or_insert(0.0) += p_next;
+
+                    n_ways *= (n_remaining - n_value) as f64 / (n_value + 1) as f64;
+                }
+            }
+            states = next_states;
+        }
+
+        Distribution::from_pairs(
+            states
+                .into_iter()
+                .filter(|((n_assigned, _), _)| *n_assigned == pool_size)
+                .map(|((_, total), p)| (total, p))
+                .collect(),
+        )
+    }
+
+    /// Return the Distribution of an arbitrary function of two independent Distributions.
+    #[allow(dead_code)]
+    fn combine(&self, other: &Distribution, func: fn(i32, i32) -> i32) -> Distribution {
+        let mut pairs: Vec<(i32, f64)> = Vec::new();
+        for (v_left, p_left) in self.iter() {
+            for (v_right, p_right) in other.iter() {
+                pairs.push((func(v_left, v_right), p_left * p_right));
+            }
+        }
+        Distribution::from_pairs(pairs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(left: f64, right: f64) {
+        assert!((left - right).abs() < 1e-9, "{} != {}", left, right);
+    }
+
+    #[test]
+    fn test_constructor() {
+        let exp_dist = Distribution {
+            offset: 2,
+            probabilities: vec![0.5, 0.5],
+        };
+
+        let obs_dist = Distribution::new(2, vec![0.5, 0.5]);
+        assert_eq!(exp_dist, obs_dist);
+    }
+
+    #[test]
+    fn test_point() {
+        let exp_dist = Distribution::new(5, vec![1.0]);
+        assert_eq!(exp_dist, Distribution::point(5));
+    }
+
+    #[test]
+    fn test_uniform() {
+        let exp_dist = Distribution::new(1, vec![0.25; 4]);
+        assert_eq!(exp_dist, Distribution::uniform(1, 4));
+    }
+
+    #[test]
+    fn test_from_pairs() {
+        let exp_dist = Distribution::new(1, vec![0.5, 0.0, 0.5]);
+
+        let obs_dist = Distribution::from_pairs(vec![(1, 0.25), (3, 0.5), (1, 0.25)]);
+        assert_eq!(exp_dist, obs_dist);
+    }
+
+    #[test]
+    fn test_from_pairs_empty() {
+        assert_eq!(Distribution::empty(), Distribution::from_pairs(vec![]));
+    }
+
+    // region: Summary statistic tests
+
+    #[test]
+    fn test_mean() {
+        assert_close(3.5, Distribution::uniform(1, 6).mean());
+        assert_close(5.0, Distribution::point(5).mean());
+    }
+
+    #[test]
+    fn test_variance() {
+        assert_close(35.0 / 12.0, Distribution::uniform(1, 6).variance());
+        assert_close(0.0, Distribution::point(5).variance());
+    }
+
+    // endregion:
+
+    // region: Combination tests
+
+    #[test]
+    fn test_convolve() {
+        // 2d2 has outcomes 2, 3, 4 at 1/4, 1/2, 1/4
+        let d2 = Distribution::uniform(1, 2);
+
+        let exp_dist = Distribution::new(2, vec![0.25, 0.5, 0.25]);
+        assert_eq!(exp_dist, d2.convolve(&d2));
+    }
+
+    #[test]
+    fn test_convolve_point() {
+        // Adding a static modifier shifts the distribution
+        let exp_dist = Distribution::new(4, vec![0.25; 4]);
+
+        let obs_dist = Distribution::uniform(1, 4).convolve(&Distribution::point(3));
+        assert_eq!(exp_dist, obs_dist);
+    }
+
+    #[test]
+    fn test_convolve_empty() {
+        let obs_dist = Distribution::uniform(1, 4).convolve(&Distribution::empty());
+        assert_eq!(Distribution::empty(), obs_dist);
+    }
+
+    #[test]
+    fn test_scale_add() {
+        let hit = Distribution::uniform(2, 3).scale(0.5);
+        let miss = Distribution::point(0).scale(0.5);
+
+        let exp_dist = Distribution::new(0, vec![0.5, 0.0, 0.25, 0.25]);
+        assert_eq!(exp_dist, hit.add(&miss));
+    }
+
+    #[test]
+    fn test_halve() {
+        let exp_dist = Distribution::new(0, vec![0.25, 0.5, 0.25]);
+        assert_eq!(exp_dist, Distribution::uniform(1, 4).halve());
+    }
+
+    #[test]
+    fn test_max_of() {
+        // Advantage on a d2 only rolls a 1 when both dice do
+        let d2 = Distribution::uniform(1, 2);
+
+        let exp_dist = Distribution::new(1, vec![0.25, 0.75]);
+        assert_eq!(exp_dist, d2.max_of(&d2));
+    }
+
+    #[test]
+    fn test_min_of() {
+        // Disadvantage on a d2 only rolls a 2 when both dice do
+        let d2 = Distribution::uniform(1, 2);
+
+        let exp_dist = Distribution::new(1, vec![0.75, 0.25]);
+        assert_eq!(exp_dist, d2.min_of(&d2));
+    }
+
+    #[test]
+    fn test_keep_of_single() {
+        // Keeping one die of a pool matches repeated use of max_of() and min_of()
+        let d4 = Distribution::uniform(1, 4);
+
+        let exp_dist = d4.max_of(&d4).max_of(&d4);
+        let obs_dist = d4.keep_of(3, 1, true);
+        for ((exp_value, exp_p), (obs_value, obs_p)) in exp_dist.iter().zip(obs_dist.iter()) {
+            assert_eq!(exp_value, obs_value);
+            assert_close(exp_p, obs_p);
+        }
+
+        let exp_dist = d4.min_of(&d4);
+        let obs_dist = d4.keep_of(2, 1, false);
+        for ((exp_value, exp_p), (obs_value, obs_p)) in exp_dist.iter().zip(obs_dist.iter()) {
+            assert_eq!(exp_value, obs_value);
+            assert_close(exp_p, obs_p);
+        }
+    }
+
+    #[test]
+    fn test_keep_of_multiple() {
+        // 4d6 keep highest three averages 15869 / 1296
+        let obs_dist = Distribution::uniform(1, 6).keep_of(4, 3, true);
+        assert_close(1.0, obs_dist.iter().map(|(_, p)| p).sum());
+        assert_close(15869.0 / 1296.0, obs_dist.mean());
+        assert_eq!(
+            (3, 18),
+            (
+                obs_dist.iter().next().unwrap().0,
+                obs_dist.iter().last().unwrap().0
+            )
+        );
+    }
+
+    #[test]
+    fn test_keep_of_all() {
+        // Keeping every die of the pool is the same as adding them together
+        let d2 = Distribution::uniform(1, 2);
+
+        let exp_dist = Distribution::new(2, vec![0.25, 0.5, 0.25]);
+        assert_eq!(exp_dist, d2.keep_of(2, 2, false));
+    }
+
+    // endregion:
+}
@@ -1,25 +1,48 @@
 use polars::prelude::*;
 use rayon::prelude::*;
+use serde::Deserialize;
 use simple_error::bail;
 use std::{cmp::Ordering, error::Error, fs::File};
 
 mod attack_profile;
-use attack_profile::AttackProfile;
+use attack_profile::{AttackCondition, AttackKind, AttackProfile, TurnDistribution};
+mod build_config;
+pub use build_config::{Build, BuildFile};
+mod damage;
 mod dice;
+mod distribution;
+use distribution::Distribution;
+mod notation;
+use notation::NotationError;
+mod rider;
+use rider::Rider;
 mod roll_collection;
 use roll_collection::RollCollection;
+mod saving_throw;
 mod static_modifier;
+mod target;
+pub use target::Target;
+mod turn_plan;
+pub use turn_plan::TurnPlan;
 
 // region: Enums
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum HitResult {
     CriticalHit,
     Hit,
     Miss,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum SaveResult {
+    CriticalFailure,
+    Failure,
+    Success,
+    CriticalSuccess,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum DiceBehaviour {
     Standard,
     Fatal,
@@ -42,7 +65,11 @@ pub enum RollBehaviour {
     DoubleAdvantage,
     Disadvantage,
     Fatal,
+    Deadly,
     ExclusiveCrit,
+    Exploding,
+    KeepHighest(i32, i32),
+    KeepLowest(i32, i32),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -53,103 +80,286 @@ pub enum EvalBehaviour {
     OnMiss,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
 pub enum Ruleset {
     DND5e,
+    DND5eNoAutoMiss,
     PF2e,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FortuneBehaviour {
+    Standard,
+    Fortune,
+    Misfortune,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RiderTrigger {
+    FirstHit,
+    FirstCrit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FollowUpTrigger {
+    OnHit,
+    OnCrit,
+    OnMiss,
+}
+
 // endregion:
 
 // region: Private functions
 
 /// Simulate a specified number of attack iterations and format the results as a DataFrame.
 ///
+/// If the target has any resistances, weaknesses, or immunities, the damage dealt before
+/// the defences of the target are applied is recorded in a Raw_damage column.
+///
+/// The turns are simulated as consecutive sequences of the specified number of rounds,
+/// with persistent damage carrying over between the rounds of a sequence. If there is
+/// more than one round in a sequence, the round of each turn is recorded in a Round
+/// column, and if any rider deals persistent damage it is recorded in a
+/// Persistent_damage column. If any attacks are follow-up attacks, the number made in
+/// each turn is recorded in a Triggered_attacks column.
+///
+/// If the target has hit points, the turns are instead simulated as a series of encounters,
+/// each lasting until the target drops, and the number of rounds is ignored. The round of
+/// each turn within its encounter is recorded in a Round column and the hit points of the
+/// target in a Target_HP column. On the round in which the target drops, the rounds taken
+/// to kill it are recorded in a Rounds_to_kill column, the damage of the killing blow in
+/// excess of the remaining hit points in an Overkill column, and all damage dealt beyond
+/// the remaining hit points, including any damage dealt after the killing blow, in a
+/// Wasted_damage column. These columns are null on all other rounds. The turns of an
+/// encounter still in progress when the number of turns runs out are dropped, so that only
+/// whole encounters are reported, unless the target never drops at all.
+///
 /// # Examples
 /// ```
 /// let number_of_turns = 1_000_000;
 /// let hit_die = vec![]
-/// let attack_profile = AttackProfile::new(16, vec![DiceContext::parse_user_input("1d20+2")], vec![DiceContext::parse_user_input("1d4+2")], Ruleset::DND5e);
+/// let attack_profile = AttackProfile::new(Target::from_ac(16), vec![DiceContext::parse_user_input("1d20+2")], vec![DiceContext::parse_user_input("1d4+2")], Ruleset::DND5e);
 ///
-/// let df = evaluate_attack_profile(attack_profile, number_of_turns);
+/// let df = evaluate_attack_profile("Fighter", attack_profile, number_of_turns, 1);
 /// ```
-fn evaluate_attack_profile(mut attack_profile: AttackProfile, number_turns: i32) -> DataFrame {
+fn evaluate_attack_profile(
+    build_name: &str,
+    mut attack_profile: AttackProfile,
+    number_turns: i32,
+    number_rounds: i32,
+) -> DataFrame {
     let mut crit_counter: Vec<i32> = Vec::new();
     let mut hit_counter: Vec<i32> = Vec::new();
+    let mut raw_counter: Vec<i32> = Vec::new();
     let mut damage_counter: Vec<i32> = Vec::new();
+    let mut rider_counter: Vec<i32> = Vec::new();
+    let mut persistent_counter: Vec<i32> = Vec::new();
+    let mut triggered_counter: Vec<i32> = Vec::new();
+    let mut round_counter: Vec<i32> = Vec::new();
+    let mut kill_counter: Vec<Option<i32>> = Vec::new();
+    let mut overkill_counter: Vec<Option<i32>> = Vec::new();
+    let mut wasted_counter: Vec<Option<i32>> = Vec::new();
+    let mut bonus_counter: Vec<i32> = Vec::new();
+    let mut type_counters: Vec<(Option<String>, Vec<i32>)> = attack_profile
+        .reported_damage_types()
+        .into_iter()
+        .map(|t| (t, Vec::new()))
+        .collect();
 
-    for _ in 0..number_turns {
-        let (n_crits, n_hits, damage_rolled) = attack_profile.roll_turn();
-        crit_counter.push(n_crits);
-        hit_counter.push(n_hits);
-        damage_counter.push(damage_rolled);
+    let target_hp = attack_profile.target.hp;
+    let mut remaining_hp = 0;
+    let mut round = 0;
+    let mut encounter_start = 0;
+    for turn in 0..number_turns {
+        let new_sequence = match target_hp {
+            Some(_) => remaining_hp <= 0,
+            None => turn % number_rounds == 0,
+        };
+        if new_sequence {
+            attack_profile.reset_persistent_damage();
+            remaining_hp = target_hp.unwrap_or(0);
+            round = 0;
+            encounter_start = turn as usize;
+        }
+        round += 1;
+        round_counter.push(round);
+
+        let turn_result = attack_profile.roll_turn();
+        if target_hp.is_some() {
+            let round_start_hp = remaining_hp;
+            match resolve_killing_blow(&mut remaining_hp, &turn_result.instance_damage) {
+                Some(overkill) => {
+                    kill_counter.push(Some(round));
+                    overkill_counter.push(Some(overkill));
+                    wasted_counter.push(Some(turn_result.damage - round_start_hp));
+                }
+                None => {
+                    kill_counter.push(None);
+                    overkill_counter.push(None);
+                    wasted_counter.push(None);
+                }
+            }
+        }
+        crit_counter.push(turn_result.crits);
+        hit_counter.push(turn_result.hits);
+        raw_counter.push(turn_result.raw_damage);
+        damage_counter.push(turn_result.damage);
+        rider_counter.push(turn_result.rider_damage);
+        persistent_counter.push(turn_result.persistent_damage);
+        triggered_counter.push(turn_result.triggered_attacks);
+        bonus_counter.push(turn_result.bonus_roll);
+        for (damage_type, type_counter) in type_counters.iter_mut() {
+            type_counter.push(turn_result.damage_by_type.get(damage_type));
+        }
     }
 
     // Bundle results into a DataFrame and return
-    results_to_dataframe(
-        attack_profile.target_ac,
+    let mut results_df = results_to_dataframe(
+        build_name,
+        &attack_profile.target,
         crit_counter,
         hit_counter,
         damage_counter,
-    )
+        rider_counter,
+        bonus_counter,
+    );
+
+    // Damage before defences is only reported if the target has defences to reduce it
+    if attack_profile.target.has_defences() {
+        let position = results_df.get_column_index("Total_damage").unwrap();
+        let raw_column = Series::new("Raw_damage", raw_counter);
+        results_df.insert_column(position, raw_column).unwrap();
+    }
+    if attack_profile.has_conditional_attacks() {
+        let position = results_df.get_column_index("Number_crits").unwrap() + 1;
+        let triggered_column = Series::new("Triggered_attacks", triggered_counter);
+        results_df
+            .insert_column(position, triggered_column)
+            .unwrap();
+    }
+    if attack_profile.has_persistent_damage() {
+        let position = results_df.get_column_index("Rider_damage").unwrap() + 1;
+        let persistent_column = Series::new("Persistent_damage", persistent_counter);
+        results_df
+            .insert_column(position, persistent_column)
+            .unwrap();
+    }
+    if let Some(hp) = target_hp {
+        let position = results_df.get_column_index("Target_AC").unwrap() + 1;
+        let hp_column = Series::new("Target_HP", vec![hp; results_df.height()]);
+        results_df.insert_column(position, hp_column).unwrap();
+        results_df
+            .with_column(Series::new("Rounds_to_kill", kill_counter))
+            .unwrap();
+        results_df
+            .with_column(Series::new("Overkill", overkill_counter))
+            .unwrap();
+        results_df
+            .with_column(Series::new("Wasted_damage", wasted_counter))
+            .unwrap();
+    }
+    if number_rounds > 1 || target_hp.is_some() {
+        let position = results_df.get_column_index("Iteration").unwrap() + 1;
+        let round_column = Series::new("Round", round_counter);
+        results_df.insert_column(position, round_column).unwrap();
+    }
+    let results_df = append_type_damage(results_df, type_counters);
+
+    // An encounter cut short by the end of the simulation would bias the rounds to kill
+    if remaining_hp > 0 && encounter_start > 0 {
+        return results_df.slice(0, encounter_start);
+    }
+    results_df
+}
+
+/// Compute the exact per-turn distributions of an attack profile and format the results as a DataFrame.
+///
+/// # Examples
+/// ```
+/// let attack_profile = AttackProfile::new(Target::from_ac(16), vec![DiceContext::parse_user_input("1d20+2")], vec![DiceContext::parse_user_input("1d4+2")], Ruleset::DND5e);
+///
+/// let df = evaluate_attack_profile_exact("Fighter", attack_profile);
+/// ```
+fn evaluate_attack_profile_exact(build_name: &str, attack_profile: AttackProfile) -> DataFrame {
+    let turn_distribution = attack_profile.turn_distribution();
+    distributions_to_dataframe(build_name, &attack_profile.target, &turn_distribution)
 }
 
-/// Create a vector of AttackProfile structs corresponding to a vector of AC values.
+/// Create a vector of AttackProfile structs corresponding to a vector of targets.
 ///
-/// Accepts a vector of target Armour Class values, and creates an attack profile for
-/// each individual value.
+/// Accepts a vector of targets, and creates an attack profile for each individual
+/// target.
 ///
 /// # Examples
 /// ```
-/// let ac_values = vec![10, 12, 14, 16, 18];
+/// let targets = vec![Target::from_ac(10), Target::from_ac(12), Target::from_ac(14)];
 /// let hit_details = vec![String::from("1d20+5"), "1d20+4"];
 /// let dmg_details = vec![String::from("1d8+3"), String::from("1d4")];
+/// let rider_details = vec![String::from("crit:2d8")];
 ///
-/// let attack_profile_vector = map_profiles_to_ac(ac_values, hit_details, dmg_details, Ruleset::DND5e)
+/// let attack_profile_vector = map_profiles_to_targets(targets, hit_details, dmg_details, rider_details, Ruleset::DND5e)?;
 /// ```
-fn map_profiles_to_ac(
-    ac_targets: Vec<i32>,
+fn map_profiles_to_targets(
+    targets: Vec<Target>,
     hit_details: Vec<String>,
     weapon_details: Vec<String>,
+    rider_details: Vec<String>,
     ruleset: Ruleset,
-) -> Vec<AttackProfile> {
-    let profile_vector: Vec<AttackProfile> = ac_targets
+) -> Result<Vec<AttackProfile>, NotationError> {
+    targets
         .into_iter()
-        .map(|i| produce_attackprofile(i, &hit_details, &weapon_details, &ruleset))
-        .collect();
-
-    profile_vector
+        .map(|t| produce_attackprofile(t, &hit_details, &weapon_details, &rider_details, &ruleset))
+        .collect()
 }
 
-/// Bundles together the user input strings and a target AC/ruleset into an AttackProfile.
+/// Bundles together the user input strings and a target/ruleset into an AttackProfile.
 ///
 /// THIS IS TEMPORARY UNTIL THIS FUNCTION IS MOVED INTO THE ATTACK_PROFILE STRUCT.
 ///
 /// # Examples
 /// ```
-/// let input_ac = 10;
-/// let hit_details = ["1d20+5"];
-/// let dmg_details = ["1d8+3"];
+/// let target = Target::from_ac(10);
+/// let hit_details = ["1d20+5", "dc15:1d20+2"];
+/// let dmg_details = ["1d8+3", "8d6"];
+/// let rider_details = ["3d6"];
 ///
-/// let attack_profile = produce_attackprofile(input_ac, &hit_details, &dmg_details, Ruleset::DND5e);
+/// let attack_profile = produce_attackprofile(target, &hit_details, &dmg_details, &rider_details, Ruleset::DND5e)?;
 /// ```
 fn produce_attackprofile(
-    target_ac: i32,
+    target: Target,
     hit_details: &[String],
     weapon_details: &[String],
+    rider_details: &[String],
     ruleset: &Ruleset,
-) -> AttackProfile {
-    let hit_context = hit_details
+) -> Result<AttackProfile, NotationError> {
+    let (conditions, hit_context): (Vec<Option<AttackCondition>>, Vec<AttackKind>) = hit_details
         .iter()
-        .map(|s| RollCollection::parse_user_input(s, *ruleset))
-        .collect();
+        .enumerate()
+        .map(|(i, s)| {
+            let (condition, attack_notation) = AttackCondition::parse_user_input(s, i)?;
+            let attack = AttackKind::parse_user_input(attack_notation, *ruleset, &target)
+                .map_err(|e| e.with_offset(s, s.len() - attack_notation.len()))?;
+            Ok((condition, attack))
+        })
+        .collect::<Result<Vec<_>, NotationError>>()?
+        .into_iter()
+        .unzip();
 
     let weapon_context = weapon_details
         .iter()
         .map(|s| RollCollection::parse_user_input(s, *ruleset))
-        .collect();
+        .collect::<Result<_, _>>()?;
+
+    let riders = rider_details
+        .iter()
+        .map(|s| Rider::parse_user_input(s, *ruleset))
+        .collect::<Result<_, _>>()?;
 
-    AttackProfile::new(target_ac, hit_context, weapon_context)
+    Ok(
+        AttackProfile::new(target, hit_context, weapon_context, *ruleset)
+            .set_conditions(conditions)
+            .set_riders(riders),
+    )
 }
 
 /// Extend the length of a vector by appending a new value the required number of times
@@ -168,46 +378,344 @@ fn resize_vector(base_vector: &mut Vec<String>, new_value: String, iterations: u
     base_vector.extend(new_vector);
 }
 
+/// Deal the damage instances of a turn to the remaining hit points of a target.
+///
+/// The instances are dealt in order until the target drops to 0 hit points or below, at
+/// which point the remaining instances are ignored. Returns the damage of the killing blow
+/// in excess of the remaining hit points, or None if the target survives the turn.
+///
+/// # Examples
+/// ```
+/// let mut remaining_hp = 10;
+///
+/// assert_eq!(Some(2), resolve_killing_blow(&mut remaining_hp, &[4, 8, 5]));
+/// assert_eq!(-2, remaining_hp);
+/// ```
+fn resolve_killing_blow(remaining_hp: &mut i32, instance_damage: &[i32]) -> Option<i32> {
+    for damage in instance_damage {
+        *remaining_hp -= damage;
+        if *remaining_hp <= 0 {
+            return Some(-*remaining_hp);
+        }
+    }
+    None
+}
+
 /// Collect the results of an attack profile simulation into a polars DataFrame
 ///
-/// Records the target AC as a single integer, and vectors of the tallies for critical
-/// hits, regular hits, and damage per turn for all turns simulated in the iteration.
-/// Formats the results into a table in the format:
+/// Records the build name and target AC as single values, and vectors of the tallies
+/// for critical hits, regular hits, damage, rider damage, and the total of the bonus dice
+/// added to attack rolls per turn for all turns simulated in the iteration. Rider damage
+/// is included in the total damage. Named targets are recorded in a Target column
+/// preceding the Target_AC column. Formats the results into a table in the format:
 ///
-/// |Iteration|Target_AC|Number_hits|Number_crits|Total_damage|
-/// |:---:|:---:|:---:|:---:|:---:|
-/// |1|...|...|...|...|
-/// |...|...|...|...|...|
-/// |n|...|...|...|...|
+/// |Build|Iteration|Target_AC|Number_hits|Number_crits|Total_damage|Rider_damage|Bonus_roll|
+/// |:---:|:---:|:---:|:---:|:---:|:---:|:---:|:---:|
+/// |...|1|...|...|...|...|...|...|
+/// |...|...|...|...|...|...|...|...|
+/// |...|n|...|...|...|...|...|...|
 ///
 /// # Examples
 /// ```
-/// let input_ac = 10;
+/// let build_name = "Fighter";
+/// let target = Target::from_ac(10);
 /// let crit_counts = vec![0, 0, 1, 0];
 /// let hit_counts = vec![0, 1, 1, 1];
-/// let damage_results = vec![0, 1, 4, 1];
+/// let damage_results = vec![0, 4, 7, 1];
+/// let rider_results = vec![0, 3, 6, 0];
+/// let bonus_results = vec![2, 1, 4, 3];
 ///
-/// let df = results_to_dataframe(input_ac, crit_counts, hit_counts, damage_results);
+/// let df = results_to_dataframe(build_name, &target, crit_counts, hit_counts, damage_results, rider_results, bonus_results);
 /// ```
 fn results_to_dataframe(
-    ac_value: i32,
+    build_name: &str,
+    target: &Target,
     crit_counter: Vec<i32>,
     hit_counter: Vec<i32>,
     damage_counter: Vec<i32>,
+    rider_counter: Vec<i32>,
+    bonus_counter: Vec<i32>,
 ) -> DataFrame {
     let max_len: i32 = (crit_counter.len() as i32) + 1;
     let iteration_counter: Vec<i32> = (1..max_len).collect();
-    let ac_counter: Vec<i32> = vec![ac_value; crit_counter.len()];
+    let ac_counter: Vec<i32> = vec![target.ac; crit_counter.len()];
+    let build_counter: Vec<&str> = vec![build_name; crit_counter.len()];
 
     // Create the DataFrame. This function cannot fail in this scope, so just unwrap and return.
-    df!(
+    let results_df = df!(
+        "Build" => &build_counter,
         "Iteration" => &iteration_counter,
         "Target_AC" => &ac_counter,
         "Number_hits" => &hit_counter,
         "Number_crits" => &crit_counter,
-        "Total_damage" => &damage_counter
+        "Total_damage" => &damage_counter,
+        "Rider_damage" => &rider_counter,
+        "Bonus_roll" => &bonus_counter
+    )
+    .unwrap();
+    label_target(results_df, target)
+}
+
+/// Append the per-turn damage of each damage type to the results of a simulation.
+///
+/// Each damage type is recorded in a column named by damage_type_column().
+///
+/// # Examples
+/// ```
+/// let type_counters = vec![(Some(String::from("fire")), vec![0, 4, 7, 1])];
+///
+/// let df = append_type_damage(results_df, type_counters);
+/// ```
+fn append_type_damage(
+    mut results_df: DataFrame,
+    type_counters: Vec<(Option<String>, Vec<i32>)>,
+) -> DataFrame {
+    for (damage_type, type_counter) in type_counters {
+        let type_column = Series::new(&damage_type_column(&damage_type), type_counter);
+        results_df.with_column(type_column).unwrap();
+    }
+    results_df
+}
+
+/// Return the name of the results column recording the damage of a single type.
+///
+/// Untyped damage is recorded as Damage_untyped.
+///
+/// # Examples
+/// ```
+/// assert_eq!("Damage_fire", damage_type_column(&Some(String::from("fire"))));
+/// ```
+fn damage_type_column(damage_type: &Option<String>) -> String {
+    format!("Damage_{}", damage_type.as_deref().unwrap_or("untyped"))
+}
+
+/// Return the summary label of a damage type column, such as "Fire damage per round".
+///
+/// # Examples
+/// ```
+/// assert_eq!("Fire damage per round", damage_type_label("Damage_fire"));
+/// ```
+fn damage_type_label(type_column: &str) -> String {
+    let damage_type = type_column.trim_start_matches("Damage_");
+    let mut chars = damage_type.chars();
+    let capitalised: String = match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    };
+    format!("{} damage per round", capitalised)
+}
+
+/// Collect the exact per-turn distributions of an attack profile into a polars DataFrame
+///
+/// Records the probability of every value that each per-turn tally can take, in long
+/// format. Values with zero probability are omitted. Damage split by type is recorded
+/// under the metric named by damage_type_column(). Named targets are recorded in a
+/// Target column preceding the Target_AC column. Formats the results into a table in the
+/// format:
+///
+/// |Build|Target_AC|Metric|Value|Probability|
+/// |:---:|:---:|:---:|:---:|:---:|
+/// |...|...|Number_hits|0|...|
+/// |...|...|...|...|...|
+/// |...|...|Bonus_roll|n|...|
+///
+/// # Examples
+/// ```
+/// let turn_distribution = attack_profile.turn_distribution();
+///
+/// let df = distributions_to_dataframe("Fighter", &Target::from_ac(10), &turn_distribution);
+/// ```
+fn distributions_to_dataframe(
+    build_name: &str,
+    target: &Target,
+    turn_distribution: &TurnDistribution,
+) -> DataFrame {
+    let mut metric_column: Vec<String> = Vec::new();
+    let mut value_column: Vec<i32> = Vec::new();
+    let mut probability_column: Vec<f64> = Vec::new();
+
+    let mut metric_distributions: Vec<(String, &Distribution)> = vec![
+        (String::from("Number_hits"), &turn_distribution.hits),
+        (String::from("Number_crits"), &turn_distribution.crits),
+        (String::from("Total_damage"), &turn_distribution.damage),
+        (
+            String::from("Rider_damage"),
+            &turn_distribution.rider_damage,
+        ),
+        (String::from("Bonus_roll"), &turn_distribution.bonus_roll),
+    ];
+    metric_distributions.extend(
+        turn_distribution
+            .damage_by_type
+            .iter()
+            .map(|(t, d)| (damage_type_column(t), d)),
+    );
+
+    for (metric, distribution) in metric_distributions {
+        for (value, probability) in distribution.iter().filter(|(_, p)| *p > 0.0) {
+            metric_column.push(metric.clone());
+            value_column.push(value);
+            probability_column.push(probability);
+        }
+    }
+    let ac_column: Vec<i32> = vec![target.ac; value_column.len()];
+    let build_column: Vec<&str> = vec![build_name; value_column.len()];
+
+    // Create the DataFrame. This function cannot fail in this scope, so just unwrap and return.
+    let results_df = df!(
+        "Build" => &build_column,
+        "Target_AC" => &ac_column,
+        "Metric" => &metric_column,
+        "Value" => &value_column,
+        "Probability" => &probability_column
     )
-    .unwrap()
+    .unwrap();
+    label_target(results_df, target)
+}
+
+/// Insert the name of the target into a results table, ahead of the Target_AC column.
+///
+/// Unnamed targets, such as those created from a bare AC value, are not labelled.
+///
+/// # Examples
+/// ```
+/// let df = df!("Build" => &["Fighter"], "Target_AC" => &[15]).unwrap();
+///
+/// let df = label_target(df, &target);
+/// ```
+fn label_target(mut results_df: DataFrame, target: &Target) -> DataFrame {
+    if let Some(name) = &target.name {
+        let position = results_df.get_column_index("Target_AC").unwrap();
+        let name_column = Series::new("Target", vec![name.as_str(); results_df.height()]);
+        results_df.insert_column(position, name_column).unwrap();
+    }
+    results_df
+}
+
+/// Concatenate the per-AC results of a simulation into a single DataFrame.
+///
+/// # Examples
+/// ```
+/// let attack_results = vec![df!("Temp" => &[1, 2]).unwrap().lazy(), df!("Temp" => &[3]).unwrap().lazy()];
+///
+/// let df = concatenate_results(attack_results)?;
+/// ```
+fn concatenate_results(attack_results: Vec<LazyFrame>) -> Result<DataFrame, Box<dyn Error>> {
+    /* Polars cannot concatenate an empty list of results, which happens when there are no
+       targets to simulate against, so this is reported before attempting it.
+
+       Concatenation is diagonal so that builds with and without a Level or Target column
+       can be combined, with the missing values filled as null.
+    */
+    if attack_results.is_empty() {
+        bail!("There are no results to combine, at least one target is required!");
+    }
+
+    let concat_args = UnionArgs {
+        parallel: true,
+        rechunk: true,
+        to_supertypes: false,
+        diagonal: true,
+        from_partitioned_ds: false,
+    };
+    Ok(concat(attack_results, concat_args)?.collect()?)
+}
+
+/// Return the names of the damage type columns of a simulation, or metrics of an exact calculation.
+///
+/// # Examples
+/// ```
+/// let df = df!("Build" => &["Fighter"], "Damage_fire" => &[3]).unwrap();
+///
+/// assert_eq!(vec!["Damage_fire"], damage_type_columns(&df));
+/// ```
+fn damage_type_columns(results_df: &DataFrame) -> Vec<String> {
+    let names: Vec<String> = match results_df.column("Metric") {
+        Ok(metrics) => metrics
+            .unique_stable()
+            .unwrap()
+            .str()
+            .unwrap()
+            .into_no_null_iter()
+            .map(|s| s.to_string())
+            .collect(),
+        Err(_) => results_df
+            .get_column_names()
+            .into_iter()
+            .map(|s| s.to_string())
+            .collect(),
+    };
+    names
+        .into_iter()
+        .filter(|n| n.starts_with("Damage_"))
+        .collect()
+}
+
+/// Return the columns which identify a single row of a summary table, in reporting order.
+///
+/// The Level column is only included when the results were produced from a level progression,
+/// the Target column only when the results include named targets, and the hit points column
+/// only when the results include targets with hit points.
+///
+/// # Examples
+/// ```
+/// let df = df!("Build" => &["Fighter"], "Target_AC" => &[10], "Total_damage" => &[3]).unwrap();
+///
+/// assert_eq!(vec!["Build", "Target_AC"], summary_keys(&df, "Target_AC", "Target_HP"));
+/// ```
+fn summary_keys<'a>(
+    results_df: &DataFrame,
+    ac_column: &'a str,
+    hp_column: &'a str,
+) -> Vec<&'a str> {
+    let column_names = results_df.get_column_names();
+    let mut group_keys = vec!["Build"];
+    for optional_key in ["Level", "Target"] {
+        if column_names.contains(&optional_key) {
+            group_keys.push(optional_key);
+        }
+    }
+    group_keys.push(ac_column);
+    if column_names.contains(&hp_column) {
+        group_keys.push(hp_column);
+    }
+    group_keys
+}
+
+/// Rename the target columns of a results table to the names used in summary tables.
+///
+/// # Examples
+/// ```
+/// let lf = df!("Build" => &["Fighter"], "Target_AC" => &[10]).unwrap().lazy();
+///
+/// let lf = rename_target_columns(lf, &["Build", "Target_AC"]);
+/// ```
+fn rename_target_columns(results_lf: LazyFrame, group_keys: &[&str]) -> LazyFrame {
+    let (existing, new): (Vec<&str>, Vec<&str>) =
+        [("Target_AC", "Target AC"), ("Target_HP", "Target HP")]
+            .into_iter()
+            .filter(|(column, _)| group_keys.contains(column))
+            .unzip();
+    results_lf.rename(existing, new)
+}
+
+/// Return the columns by which a summary table is ordered.
+///
+/// Summaries are ordered by the summary keys following the Build column, except that
+/// named targets are left in the order they were provided.
+///
+/// # Examples
+/// ```
+/// let sort_keys = sort_keys(&["Build", "Target", "Target_AC"]);
+///
+/// assert_eq!(vec!["Target_AC"], sort_keys);
+/// ```
+fn sort_keys<'a>(group_keys: &[&'a str]) -> Vec<&'a str> {
+    group_keys[1..]
+        .iter()
+        .filter(|k| **k != "Target")
+        .copied()
+        .collect()
 }
 
 // endregion:
@@ -246,36 +754,81 @@ pub fn equalise_input_vectors(first_vector: &mut Vec<String>, second_vector: &mu
     }
 }
 
-/// Partition the inputs over the range of AC values and simulate the attack turns.
+/// Check that no damage type given on the command line was split from its dice.
+///
+/// Spaces separate the values given to an option on the command line, so a damage type
+/// written after a space, as in "1d8+4 slashing", is read as a separate attack. A value
+/// which is only a damage type is reported as an error, rather than as malformed notation.
+///
+/// # Examples
+/// ```
+/// let weapon_details = vec![String::from("1d8+4slashing")];
+///
+/// check_separated_damage_types("--weapon-details", &weapon_details)?;
+/// ```
+pub fn check_separated_damage_types(
+    option_name: &str,
+    notations: &[String],
+) -> Result<(), Box<dyn Error>> {
+    let is_damage_type = |n: &String| n.len() >= 3 && n.chars().all(|c| c.is_ascii_lowercase());
+
+    if let Some(damage_type) = notations.iter().find(|n| is_damage_type(n)) {
+        bail!(
+            "The damage type '{}' was given to {} on its own. As spaces separate the values of an option, write a damage type directly after its dice (for example 1d8+4{})!",
+            damage_type,
+            option_name,
+            damage_type
+        );
+    }
+    Ok(())
+}
+
+/// Partition the inputs over the range of targets and simulate the attack turns.
 ///
 /// Instantiates the attack simulation conditions into a vector mapping each specified
-/// Armour Class value with the roll information. Runs the simulation in either single-
+/// target with the roll information. Runs the simulation in either single-
 /// or multi-threaded mode, defaulting to a simple map/iter structure when no thread
-/// information is provided.
+/// information is provided. The turns are simulated in sequences of the specified number
+/// of rounds, over which persistent damage carries, or as encounters lasting until the
+/// target drops if it has hit points. Returns an error if any of the input notation cannot
+/// be parsed, or if the number of rounds or the hit points of any target are less than one.
 ///
 /// # Examples
 /// ```
-/// let ac_input = vec![10, 12, 14, 16, 18];
+/// let target_input = vec![Target::from_ac(10), Target::from_ac(12), Target::from_ac(14)];
 /// let hit_input = vec![String::from("1d20+5"), String::from("1d20")];
 /// let dmg_input = vec![String::from("1d8+5"), String::from("1d8+5")];
+/// let rider_input = vec![String::from("2d6")];
 /// let number_of_turns = 1_000_000;
+/// let number_of_rounds = 1;
 ///
 /// // Single-threaded approach
-/// let df = process_simulation(asd, qwe, asd, Ruleset::PF2e, number_of_turns, None);
+/// let df = process_simulation("Fighter", target_input, hit_input, dmg_input, rider_input, Ruleset::PF2e, number_of_turns, number_of_rounds, None)?;
 ///
 /// // Multi-threaded approach
-/// let df = process_simulation(asd, qwe, asd, Ruleset::PF2e, number_of_turns, Some(10));
+/// let df = process_simulation("Fighter", target_input, hit_input, dmg_input, rider_input, Ruleset::PF2e, number_of_turns, number_of_rounds, Some(10))?;
 /// ```
+#[allow(clippy::too_many_arguments)]
 pub fn process_simulation(
-    ac_targets: Vec<i32>,
+    build_name: &str,
+    targets: Vec<Target>,
     hit_details: Vec<String>,
     weapon_details: Vec<String>,
+    rider_details: Vec<String>,
     ruleset: Ruleset,
     number_turns: i32,
+    number_rounds: i32,
     n_threads: Option<usize>,
-) -> DataFrame {
+) -> Result<DataFrame, Box<dyn Error>> {
+    if number_rounds < 1 {
+        bail!("The number of rounds must be at least 1!");
+    }
+    if targets.iter().any(|t| t.hp.is_some_and(|hp| hp < 1)) {
+        bail!("The hit points of a target must be at least 1!");
+    }
+
     let profile_vector: Vec<AttackProfile> =
-        map_profiles_to_ac(ac_targets, hit_details, weapon_details, ruleset);
+        map_profiles_to_targets(targets, hit_details, weapon_details, rider_details, ruleset)?;
 
     let attack_results: Vec<LazyFrame> = match n_threads {
         Some(n) => {
@@ -287,86 +840,359 @@ pub fn process_simulation(
             pool.install(|| {
                 profile_vector
                     .into_par_iter()
-                    .map(|ap| evaluate_attack_profile(ap, number_turns).lazy())
+                    .map(|ap| {
+                        evaluate_attack_profile(build_name, ap, number_turns, number_rounds).lazy()
+                    })
                     .collect()
             })
         }
         None => profile_vector
             .into_iter()
-            .map(|ap| evaluate_attack_profile(ap, number_turns).lazy())
+            .map(|ap| evaluate_attack_profile(build_name, ap, number_turns, number_rounds).lazy())
             .collect(),
     };
 
-    /* Documentation on the circumstances that cause the polars concat() function is lacking.
-       For now just unwrap and return until I get a sighting of an error, at which point a
-       separate function might be required.
-    */
-    let concat_args = UnionArgs {
-        parallel: true,
-        rechunk: true,
-        to_supertypes: false,
-        diagonal: false,
-        from_partitioned_ds: false,
-    };
-    concat(attack_results, concat_args)
-        .unwrap()
-        .collect()
-        .unwrap()
+    concatenate_results(attack_results)
+}
+
+/// Partition the inputs over the range of targets and compute the exact turn distributions.
+///
+/// The exact counterpart to process_simulation(). Rather than rolling a large number of
+/// turns and estimating the results, the probability of every possible number of hits,
+/// critical hits, and total damage per turn is computed directly from the dice. The
+/// computation is fast enough that no multi-threading option is offered. Returns an
+/// error if any of the input notation cannot be parsed, if any target has resistances,
+/// weaknesses, or immunities or hit points, if any rider deals persistent damage, or if
+/// any attack is a follow-up attack, none of which are yet supported by the exact
+/// calculation.
+///
+/// # Examples
+/// ```
+/// let target_input = vec![Target::from_ac(10), Target::from_ac(12), Target::from_ac(14)];
+/// let hit_input = vec![String::from("1d20+5"), String::from("1d20")];
+/// let dmg_input = vec![String::from("1d8+5"), String::from("1d8+5")];
+/// let rider_input = vec![String::from("2d6")];
+///
+/// let df = process_exact("Fighter", target_input, hit_input, dmg_input, rider_input, Ruleset::PF2e)?;
+/// ```
+pub fn process_exact(
+    build_name: &str,
+    targets: Vec<Target>,
+    hit_details: Vec<String>,
+    weapon_details: Vec<String>,
+    rider_details: Vec<String>,
+    ruleset: Ruleset,
+) -> Result<DataFrame, Box<dyn Error>> {
+    if targets.iter().any(|t| t.has_defences()) {
+        bail!("Exact mode does not support targets with resistances, weaknesses, or immunities!");
+    }
+    if targets.iter().any(|t| t.hp.is_some()) {
+        bail!("Exact mode does not support targets with hit points!");
+    }
+
+    let profile_vector: Vec<AttackProfile> =
+        map_profiles_to_targets(targets, hit_details, weapon_details, rider_details, ruleset)?;
+    if profile_vector.iter().any(|ap| ap.has_persistent_damage()) {
+        bail!("Exact mode does not support persistent damage!");
+    }
+    if profile_vector.iter().any(|ap| ap.has_conditional_attacks()) {
+        bail!("Exact mode does not support follow-up attacks!");
+    }
+
+    let attack_results: Vec<LazyFrame> = profile_vector
+        .into_iter()
+        .map(|ap| evaluate_attack_profile_exact(build_name, ap).lazy())
+        .collect();
+
+    concatenate_results(attack_results)
 }
 
 /// Summarise the raw simulation information to the average per-AC results
 ///
 /// Takes a table representing all simulation data produced during the run
-/// and reports the mean number of hits, critical hits, and damage for each
-/// Armour Class value evaluated in the simulation run.
+/// and reports the mean number of hits, critical hits, damage, rider damage, and
+/// attack bonus dice for each build and Armour Class value evaluated in the simulation run. If the
+/// results contain a Level column, each level is summarised separately, and likewise each
+/// named target in a Target column. If the damage is split by type, the mean damage of
+/// each type is also reported, as are the mean damage before defences, the mean
+/// persistent damage, and the mean number of follow-up attacks if the results record them.
+/// Targets with hit points are summarised separately for each value of hit points, and
+/// additionally report the mean and median rounds taken to kill the target, and the mean
+/// overkill and wasted damage per kill.
 ///
 /// # Examples
 /// ```
 /// let input_df = df!(
+///     "Build" => &["Fighter"; 6],
 ///     "Target_AC" => &[10, 10, 10, 12, 12, 12],
 ///     "Number_hits" => &[1, 1, 1, 1, 0, 0],
 ///     "Number_crits" => &[1, 0, 0, 0, 0, 0],
 ///     "Total_damage" => &[6, 3, 3, 2, 0, 0],
+///     "Rider_damage" => &[2, 1, 1, 1, 0, 0],
+///     "Bonus_roll" => &[3, 2, 4, 1, 2, 1],
 /// ).unwrap()
 ///
 /// let df = summarise_results(input_df);
 /// ```
 pub fn summarise_results(results_df: DataFrame) -> DataFrame {
-    let agg_exprs = vec![
+    let mut agg_exprs = vec![
         col("Number_hits").mean().alias("Hits per round (mean)"),
         col("Number_crits")
             .mean()
             .alias("Critical hits per round (mean)"),
         col("Total_damage").mean().alias("Damage per round (mean)"),
+        col("Rider_damage")
+            .mean()
+            .alias("Rider damage per round (mean)"),
+        col("Bonus_roll")
+            .mean()
+            .alias("Attack bonus dice per round (mean)"),
     ];
+    if results_df.get_column_names().contains(&"Raw_damage") {
+        let raw_expr = col("Raw_damage")
+            .mean()
+            .alias("Damage before defences per round (mean)");
+        agg_exprs.insert(3, raw_expr);
+    }
+    if results_df.get_column_names().contains(&"Triggered_attacks") {
+        let triggered_expr = col("Triggered_attacks")
+            .mean()
+            .alias("Triggered attacks per round (mean)");
+        agg_exprs.insert(2, triggered_expr);
+    }
+    if results_df.get_column_names().contains(&"Persistent_damage") {
+        let persistent_expr = col("Persistent_damage")
+            .mean()
+            .alias("Persistent damage per round (mean)");
+        agg_exprs.insert(agg_exprs.len() - 1, persistent_expr);
+    }
+    for type_column in damage_type_columns(&results_df) {
+        let label = damage_type_label(&type_column);
+        agg_exprs.push(col(&type_column).mean().alias(&format!("{} (mean)", label)));
+    }
+    if results_df.get_column_names().contains(&"Rounds_to_kill") {
+        agg_exprs.extend([
+            col("Rounds_to_kill").mean().alias("Rounds to kill (mean)"),
+            col("Rounds_to_kill")
+                .median()
+                .alias("Rounds to kill (median)"),
+            col("Overkill").mean().alias("Overkill per kill (mean)"),
+            col("Wasted_damage")
+                .mean()
+                .alias("Wasted damage per kill (mean)"),
+        ]);
+    }
 
-    results_df
+    let group_keys = summary_keys(&results_df, "Target_AC", "Target_HP");
+    let summary_lf = results_df
         .lazy()
-        .group_by(["Target_AC"])
+        .group_by_stable(&group_keys)
         .agg(agg_exprs)
-        .sort(["Target_AC"], Default::default())
-        .rename(["Target_AC"], ["Target AC"])
+        .sort(
+            sort_keys(&group_keys),
+            SortMultipleOptions::default().with_maintain_order(true),
+        );
+    rename_target_columns(summary_lf, &group_keys)
         .collect()
         .unwrap()
 }
 
-/// Write a DataFrame into the compressed parquet format.
+/// Summarise the exact distribution information to the per-AC mean and variance
+///
+/// Takes a table of the probability of each per-turn outcome, as produced by the
+/// process_exact() function, and reports the mean and variance of the number of
+/// hits, critical hits, damage, rider damage, and attack bonus dice for each build and Armour Class
+/// value evaluated. If the results contain a Level column, each level is summarised
+/// separately, and likewise each named target in a Target column. If the damage is split
+/// by type, the mean and variance of the damage of each type are also reported.
 ///
 /// # Examples
 /// ```
-/// let mut df = df!("Temp" => &[1, 2, 3]).unwrap();
+/// let input_df = df!(
+///     "Build" => &["Fighter"; 6],
+///     "Target_AC" => &[10, 10, 10, 10, 10, 10],
+///     "Metric" => &["Number_hits", "Number_hits", "Number_crits", "Total_damage", "Total_damage", "Total_damage"],
+///     "Value" => &[0, 1, 0, 0, 3, 6],
+///     "Probability" => &[0.5, 0.5, 1.0, 0.5, 0.25, 0.25],
+/// ).unwrap()
 ///
-/// write_to_parquet("example.parquet", &mut df)?;
+/// let df = summarise_exact_results(input_df);
 /// ```
-pub fn write_to_parquet(
-    output_path: &str,
-    file_content: &mut DataFrame,
-) -> Result<(), Box<dyn Error>> {
-    let error_msg = format!("Unable to write to output file path '{}'!", output_path);
+pub fn summarise_exact_results(results_df: DataFrame) -> DataFrame {
+    let metric_moment = |metric: &str, power: i32| {
+        let value = col("Value").cast(DataType::Float64);
+        let weighted_value = match power {
+            1 => value * col("Probability"),
+            _ => value.clone() * value * col("Probability"),
+        };
+        weighted_value.filter(col("Metric").eq(lit(metric))).sum()
+    };
+    let metric_variance = |metric: &str| metric_moment(metric, 2) - metric_moment(metric, 1).pow(2);
+
+    let mut agg_exprs = vec![
+        metric_moment("Number_hits", 1).alias("Hits per round (mean)"),
+        metric_variance("Number_hits").alias("Hits per round (variance)"),
+        metric_moment("Number_crits", 1).alias("Critical hits per round (mean)"),
+        metric_variance("Number_crits").alias("Critical hits per round (variance)"),
+        metric_moment("Total_damage", 1).alias("Damage per round (mean)"),
+        metric_variance("Total_damage").alias("Damage per round (variance)"),
+        metric_moment("Rider_damage", 1).alias("Rider damage per round (mean)"),
+        metric_variance("Rider_damage").alias("Rider damage per round (variance)"),
+        metric_moment("Bonus_roll", 1).alias("Attack bonus dice per round (mean)"),
+        metric_variance("Bonus_roll").alias("Attack bonus dice per round (variance)"),
+    ];
+    for type_metric in damage_type_columns(&results_df) {
+        let label = damage_type_label(&type_metric);
+        agg_exprs.push(metric_moment(&type_metric, 1).alias(&format!("{} (mean)", label)));
+        agg_exprs.push(metric_variance(&type_metric).alias(&format!("{} (variance)", label)));
+    }
 
-    // Create the file path, and premature return if it fails
-    let create_result = File::create(output_path);
-    if create_result.is_err() {
+    let group_keys = summary_keys(&results_df, "Target_AC", "Target_HP");
+    let summary_lf = results_df
+        .lazy()
+        .group_by_stable(&group_keys)
+        .agg(agg_exprs)
+        .sort(
+            sort_keys(&group_keys),
+            SortMultipleOptions::default().with_maintain_order(true),
+        );
+    rename_target_columns(summary_lf, &group_keys)
+        .collect()
+        .unwrap()
+}
+
+/// Label the results of a simulation with the character level of the build.
+///
+/// Adds a Level column following the Build column of a table produced by either the
+/// process_simulation() or process_exact() functions.
+///
+/// # Examples
+/// ```
+/// let df = process_exact("Druid", target_input, hit_input, dmg_input, rider_input, Ruleset::PF2e)?;
+///
+/// let df = assign_level(df, 5);
+/// ```
+pub fn assign_level(results_df: DataFrame, level: i32) -> DataFrame {
+    let mut column_order = vec![col("Build"), lit(level).alias("Level")];
+    column_order.extend(
+        results_df
+            .get_column_names()
+            .into_iter()
+            .filter(|c| *c != "Build")
+            .map(col),
+    );
+
+    results_df.lazy().select(column_order).collect().unwrap()
+}
+
+/// Combine the results of several builds into a single DataFrame.
+///
+/// Builds which were not run as a level progression have a null Level when combined with
+/// those which were.
+///
+/// # Examples
+/// ```
+/// let fighter_df = process_exact("Fighter", target_input.clone(), fighter_hit, fighter_dmg, vec![], Ruleset::PF2e)?;
+/// let rogue_df = process_exact("Rogue", target_input, rogue_hit, rogue_dmg, rogue_riders, Ruleset::PF2e)?;
+///
+/// let df = combine_results(vec![fighter_df, rogue_df])?;
+/// ```
+pub fn combine_results(build_results: Vec<DataFrame>) -> Result<DataFrame, Box<dyn Error>> {
+    let build_results: Vec<LazyFrame> = build_results.into_iter().map(|df| df.lazy()).collect();
+    concatenate_results(build_results)
+}
+
+/// Arrange a summary table of several builds so that the builds are reported side by side
+///
+/// Takes a table produced by either the summarise_results() or summarise_exact_results()
+/// functions, and pivots the builds into columns so that each row reports every build
+/// against a single Armour Class value (or named target). Columns are named in the form
+/// "<Build>: <Summary statistic>", and are grouped by summary statistic with builds in
+/// the order given, such as the order of a build file, rather than the sorted order of
+/// the summary table. Armour Class values (or levels) missing from a build are reported
+/// as null.
+///
+/// # Examples
+/// ```
+/// let summary_df = summarise_results(combine_results(vec![fighter_df, rogue_df])?);
+///
+/// let df = compare_summaries(summary_df, &[String::from("Fighter"), String::from("Rogue")]);
+/// ```
+pub fn compare_summaries(summary_df: DataFrame, build_names: &[String]) -> DataFrame {
+    let group_keys = summary_keys(&summary_df, "Target AC", "Target HP");
+    let join_keys: Vec<Expr> = group_keys[1..].iter().map(|k| col(k)).collect();
+
+    let metric_names: Vec<String> = summary_df
+        .get_column_names()
+        .into_iter()
+        .filter(|c| !group_keys.contains(c))
+        .map(|c| c.to_string())
+        .collect();
+
+    let mut comparison_lf = summary_df
+        .clone()
+        .lazy()
+        .select(&join_keys)
+        .unique_stable(None, UniqueKeepStrategy::First);
+
+    for build_name in build_names {
+        let build_metrics: Vec<String> = metric_names
+            .iter()
+            .map(|m| format!("{}: {}", build_name, m))
+            .collect();
+
+        let build_lf = summary_df
+            .clone()
+            .lazy()
+            .filter(col("Build").eq(lit(build_name.as_str())))
+            .drop(["Build"])
+            .rename(&metric_names, &build_metrics);
+
+        comparison_lf = comparison_lf
+            .join_builder()
+            .with(build_lf)
+            .left_on(&join_keys)
+            .right_on(&join_keys)
+            .how(JoinType::Left)
+            .join_nulls(true)
+            .finish();
+    }
+
+    // Order the columns so that each summary statistic is reported for all builds together
+    let mut column_order = join_keys.clone();
+    for metric_name in &metric_names {
+        for build_name in build_names {
+            column_order.push(col(&format!("{}: {}", build_name, metric_name)));
+        }
+    }
+
+    comparison_lf
+        .select(column_order)
+        .sort(
+            sort_keys(&group_keys),
+            SortMultipleOptions::default().with_maintain_order(true),
+        )
+        .collect()
+        .unwrap()
+}
+
+/// Write a DataFrame into the compressed parquet format.
+///
+/// # Examples
+/// ```
+/// let mut df = df!("Temp" => &[1, 2, 3]).unwrap();
+///
+/// write_to_parquet("example.parquet", &mut df)?;
+/// ```
+pub fn write_to_parquet(
+    output_path: &str,
+    file_content: &mut DataFrame,
+) -> Result<(), Box<dyn Error>> {
+    let error_msg = format!("Unable to write to output file path '{}'!", output_path);
+
+    // Create the file path, and premature return if it fails
+    let create_result = File::create(output_path);
+    if create_result.is_err() {
         bail!(error_msg);
     }
 
@@ -386,6 +1212,7 @@ mod tests {
     use crate::dice::DiceBuilder;
 
     use super::*;
+    use std::collections::BTreeMap;
     use std::fs;
     use std::path::Path;
 
@@ -394,7 +1221,7 @@ mod tests {
         input_values.iter().map(|x| x.to_string()).collect()
     }
 
-    fn dataframes_are_equal(left_df: DataFrame, right_df: DataFrame) -> () {
+    fn dataframes_are_equal(left_df: DataFrame, right_df: DataFrame) {
         // Check the shape and column sequence
         assert_eq!(left_df.shape(), right_df.shape());
         assert_eq!(left_df.get_column_names(), right_df.get_column_names());
@@ -417,73 +1244,97 @@ mod tests {
         // can be compared to an expected value.
         let hit_die = DiceBuilder::new().set_roll_min(2).set_roll_max(3).build();
         let hit_context = RollCollection::new(vec![hit_die], vec![], Ruleset::DND5e);
-        let dmg_context = RollCollection::parse_user_input("1d1+1", Ruleset::DND5e);
+        let dmg_context = RollCollection::parse_user_input("1d1+1", Ruleset::DND5e).unwrap();
 
-        let attackprofile = AttackProfile::new(1, vec![hit_context], vec![dmg_context]);
+        let attackprofile = AttackProfile::new(
+            Target::from_ac(1),
+            vec![AttackKind::AttackRoll(hit_context)],
+            vec![dmg_context],
+            Ruleset::DND5e,
+        );
 
         let exp_df = df![
+            "Build" => vec!["Fighter"; 5],
             "Iteration" => vec![1, 2, 3, 4, 5],
             "Target_AC" => vec![1; 5],
             "Number_hits" => vec![1; 5],
             "Number_crits" => vec![0; 5],
             "Total_damage" => vec![2; 5],
+            "Rider_damage" => vec![0; 5],
+            "Bonus_roll" => vec![0; 5],
         ]
         .unwrap();
 
-        let obs_df = evaluate_attack_profile(attackprofile, 5);
+        let obs_df = evaluate_attack_profile("Fighter", attackprofile, 5, 1);
         dataframes_are_equal(exp_df, obs_df);
     }
 
     // endregion:
 
-    // region: map_profiles_to_ac() tests
+    // region: map_profiles_to_targets().unwrap() tests
 
     #[test]
-    fn test_map_profiles_to_ac() {
+    fn test_map_profiles_to_targets() {
         // Test the ability to produce multiple AttackProfiles from a single set of input
         //  strings to produce the DiceContext structs.
 
         let exp_aps = vec![
             AttackProfile::new(
-                10,
-                vec![RollCollection::parse_user_input("1d4+1", Ruleset::DND5e)],
-                vec![RollCollection::parse_user_input("1d12+4", Ruleset::DND5e)],
+                Target::from_ac(10),
+                vec![
+                    AttackKind::parse_user_input("1d4+1", Ruleset::DND5e, &Target::from_ac(10))
+                        .unwrap(),
+                ],
+                vec![RollCollection::parse_user_input("1d12+4", Ruleset::DND5e).unwrap()],
+                Ruleset::DND5e,
             ),
             AttackProfile::new(
-                15,
-                vec![RollCollection::parse_user_input("1d4+1", Ruleset::DND5e)],
-                vec![RollCollection::parse_user_input("1d12+4", Ruleset::DND5e)],
+                Target::from_ac(15),
+                vec![
+                    AttackKind::parse_user_input("1d4+1", Ruleset::DND5e, &Target::from_ac(10))
+                        .unwrap(),
+                ],
+                vec![RollCollection::parse_user_input("1d12+4", Ruleset::DND5e).unwrap()],
+                Ruleset::DND5e,
             ),
         ];
 
-        let obs_aps = map_profiles_to_ac(
-            vec![10, 15],
+        let obs_aps = map_profiles_to_targets(
+            vec![Target::from_ac(10), Target::from_ac(15)],
             vec!["1d4+1".to_string()],
             vec!["1d12+4".to_string()],
+            vec![],
             Ruleset::DND5e,
-        );
+        )
+        .unwrap();
 
         assert_eq!(exp_aps, obs_aps);
     }
 
     // endregion:
 
-    // region: produce_attackprofile() tests
+    // region: produce_attackprofile().unwrap() tests
 
     #[test]
     fn test_produce_attackprofile_single() {
         let exp_ap = AttackProfile::new(
-            10,
-            vec![RollCollection::parse_user_input("1d4+1", Ruleset::DND5e)],
-            vec![RollCollection::parse_user_input("1d10+1", Ruleset::DND5e)],
+            Target::from_ac(10),
+            vec![
+                AttackKind::parse_user_input("1d4+1", Ruleset::DND5e, &Target::from_ac(10))
+                    .unwrap(),
+            ],
+            vec![RollCollection::parse_user_input("1d10+1", Ruleset::DND5e).unwrap()],
+            Ruleset::DND5e,
         );
 
         let obs_ap = produce_attackprofile(
-            10,
-            &vec!["1d4+1".to_string()],
-            &vec!["1d10+1".to_string()],
+            Target::from_ac(10),
+            &["1d4+1".to_string()],
+            &["1d10+1".to_string()],
+            &[],
             &Ruleset::DND5e,
-        );
+        )
+        .unwrap();
 
         assert_eq!(exp_ap, obs_ap);
     }
@@ -491,27 +1342,79 @@ mod tests {
     #[test]
     fn test_produce_attackprofile_multiple() {
         let exp_ap = AttackProfile::new(
-            10,
+            Target::from_ac(10),
             vec![
-                RollCollection::parse_user_input("1d4+1", Ruleset::DND5e),
-                RollCollection::parse_user_input("1d6+2", Ruleset::DND5e),
+                AttackKind::parse_user_input("1d4+1", Ruleset::DND5e, &Target::from_ac(10))
+                    .unwrap(),
+                AttackKind::parse_user_input("1d6+2", Ruleset::DND5e, &Target::from_ac(10))
+                    .unwrap(),
             ],
             vec![
-                RollCollection::parse_user_input("1d10+3", Ruleset::DND5e),
-                RollCollection::parse_user_input("1d12+4", Ruleset::DND5e),
+                RollCollection::parse_user_input("1d10+3", Ruleset::DND5e).unwrap(),
+                RollCollection::parse_user_input("1d12+4", Ruleset::DND5e).unwrap(),
             ],
+            Ruleset::DND5e,
         );
 
         let obs_ap = produce_attackprofile(
-            10,
-            &vec!["1d4+1".to_string(), "1d6+2".to_string()],
-            &vec!["1d10+3".to_string(), "1d12+4".to_string()],
+            Target::from_ac(10),
+            &["1d4+1".to_string(), "1d6+2".to_string()],
+            &["1d10+3".to_string(), "1d12+4".to_string()],
+            &[],
             &Ruleset::DND5e,
-        );
+        )
+        .unwrap();
+
+        assert_eq!(exp_ap, obs_ap);
+    }
+
+    #[test]
+    fn test_produce_attackprofile_follow_up() {
+        let (condition, _) = AttackCondition::parse_user_input("crit:1d20+2", 1).unwrap();
+        let exp_ap = AttackProfile::new(
+            Target::from_ac(10),
+            vec![
+                AttackKind::parse_user_input("1d20+7", Ruleset::DND5e, &Target::from_ac(10))
+                    .unwrap(),
+                AttackKind::parse_user_input("1d20+2", Ruleset::DND5e, &Target::from_ac(10))
+                    .unwrap(),
+            ],
+            vec![
+                RollCollection::parse_user_input("2d6+4", Ruleset::DND5e).unwrap(),
+                RollCollection::parse_user_input("2d6+14", Ruleset::DND5e).unwrap(),
+            ],
+            Ruleset::DND5e,
+        )
+        .set_conditions(vec![None, condition]);
+
+        let obs_ap = produce_attackprofile(
+            Target::from_ac(10),
+            &["1d20+7".to_string(), "crit:1d20+2".to_string()],
+            &["2d6+4".to_string(), "2d6+14".to_string()],
+            &[],
+            &Ruleset::DND5e,
+        )
+        .unwrap();
 
         assert_eq!(exp_ap, obs_ap);
     }
 
+    #[test]
+    fn test_produce_attackprofile_follow_up_invalid() {
+        // Test that the error position accounts for the condition prefix
+        let obs_result = produce_attackprofile(
+            Target::from_ac(10),
+            &["1d20+7".to_string(), "crit1:1d20+2x".to_string()],
+            &["2d6+4".to_string(), "2d6+14".to_string()],
+            &[],
+            &Ruleset::DND5e,
+        );
+
+        let obs_error = obs_result.unwrap_err();
+        assert_eq!("crit1:1d20+2x", obs_error.notation);
+        assert_eq!(12, obs_error.position);
+    }
+
     // endregion:
 
     // region: resize_vector() tests
@@ -540,26 +1443,64 @@ mod tests {
 
     // endregion:
 
+    // region: resolve_killing_blow() tests
+
+    #[test]
+    fn test_resolve_killing_blow() {
+        let mut remaining_hp = 10;
+        assert_eq!(Some(2), resolve_killing_blow(&mut remaining_hp, &[4, 8, 5]));
+        assert_eq!(-2, remaining_hp);
+    }
+
+    #[test]
+    fn test_resolve_killing_blow_survives() {
+        let mut remaining_hp = 10;
+        assert_eq!(None, resolve_killing_blow(&mut remaining_hp, &[4, 5]));
+        assert_eq!(1, remaining_hp);
+    }
+
+    #[test]
+    fn test_resolve_killing_blow_exact() {
+        // A target reduced to exactly 0 hit points drops with no overkill
+        let mut remaining_hp = 9;
+        assert_eq!(Some(0), resolve_killing_blow(&mut remaining_hp, &[4, 5]));
+    }
+
+    // endregion:
+
     // region: results_to_dataframe() tests
 
     #[test]
     fn test_results_to_dataframe() {
         // Test the behaviour of the results_to_dataframe() function, assuming no errors.
 
-        let input_ac = 5;
+        let input_target = Target::from_ac(5);
         let input_crits = vec![0, 1, 2, 3, 4];
         let input_hits = vec![2, 4, 6, 8, 10];
         let input_damage = vec![10, 12, 14, 16, 18];
+        let input_rider = vec![0, 1, 0, 1, 0];
+        let input_bonus = vec![3, 0, 2, 4, 1];
 
         let exp_df = df![
+            "Build" => &vec!["Fighter"; 5],
             "Iteration" => &vec![1, 2, 3, 4, 5],
             "Target_AC" => &vec![5; 5],
             "Number_hits" => &input_hits,
             "Number_crits" => &input_crits,
-            "Total_damage" => &input_damage
+            "Total_damage" => &input_damage,
+            "Rider_damage" => &input_rider,
+            "Bonus_roll" => &input_bonus
         ]
         .unwrap();
-        let obs_df = results_to_dataframe(input_ac, input_crits, input_hits, input_damage);
+        let obs_df = results_to_dataframe(
+            "Fighter",
+            &input_target,
+            input_crits,
+            input_hits,
+            input_damage,
+            input_rider,
+            input_bonus,
+        );
 
         dataframes_are_equal(exp_df, obs_df);
     }
@@ -618,59 +1559,441 @@ mod tests {
 
     // endregion:
 
-    // region: process_simulation() tests
+    // region: check_separated_damage_types() tests
+
+    #[test]
+    fn test_check_separated_damage_types() {
+        let notations = create_string_vector(vec!["1d8+4slashing,1d6fire", "miss:4", "3d6"]);
+        assert!(check_separated_damage_types("--weapon-details", &notations).is_ok());
+    }
+
+    #[test]
+    fn test_check_separated_damage_types_split() {
+        // "1d8+4 slashing" given on the command line is split into two values
+        let notations = create_string_vector(vec!["1d8+4", "slashing"]);
+
+        let obs_result = check_separated_damage_types("--weapon-details", &notations);
+        assert_eq!(
+            "The damage type 'slashing' was given to --weapon-details on its own. As spaces separate the values of an option, write a damage type directly after its dice (for example 1d8+4slashing)!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    // endregion:
+
+    // region: process_simulation().unwrap() tests
 
     #[test]
     fn test_process_simulation() {
         // Only testing over the success case, as internal behaviours are tested in relevant unit tests.
 
         let exp_df = df![
+            "Build" => vec!["Fighter"; 10],
             "Iteration" => vec![1, 2, 3, 4, 5, 1, 2, 3, 4, 5],
             "Target_AC" => vec![0, 0, 0, 0, 0, 10, 10, 10, 10, 10],
             "Number_hits" => vec![1, 1, 1, 1, 1, 0, 0, 0, 0, 0],
             "Number_crits" => vec![0; 10],
             "Total_damage" => vec![2, 2, 2, 2, 2, 0, 0, 0, 0, 0],
+            "Rider_damage" => vec![0; 10],
+            "Bonus_roll" => vec![0; 10],
         ]
         .unwrap();
 
         let obs_df = process_simulation(
-            vec![0, 10],
+            "Fighter",
+            vec![Target::from_ac(0), Target::from_ac(10)],
             vec!["1d1+1".to_string()],
             vec!["1d1+1".to_string()],
+            vec![],
             Ruleset::DND5e,
             5,
+            1,
             None,
-        );
+        )
+        .unwrap();
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    #[test]
+    fn test_process_simulation_typed() {
+        // Test that the damage is split into a column for each damage type, including untyped damage.
+        let exp_df = df![
+            "Build" => vec!["Fighter"; 2],
+            "Iteration" => vec![1, 2],
+            "Target_AC" => vec![0, 0],
+            "Number_hits" => vec![1, 1],
+            "Number_crits" => vec![0; 2],
+            "Total_damage" => vec![6, 6],
+            "Rider_damage" => vec![2, 2],
+            "Bonus_roll" => vec![0; 2],
+            "Damage_untyped" => vec![2, 2],
+            "Damage_fire" => vec![4, 4],
+        ]
+        .unwrap();
+
+        let obs_df = process_simulation(
+            "Fighter",
+            vec![Target::from_ac(0)],
+            vec!["1d1+1".to_string()],
+            vec!["1d1 fire+3".to_string()],
+            vec!["2d1".to_string()],
+            Ruleset::DND5e,
+            2,
+            1,
+            None,
+        )
+        .unwrap();
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    #[test]
+    fn test_process_simulation_defences() {
+        // Test that the damage before defences is recorded when the target has defences.
+        let mut target = Target::from_ac(0);
+        target.resistances.insert("fire".to_string(), 3);
+
+        let exp_df = df![
+            "Build" => vec!["Fighter"; 2],
+            "Iteration" => vec![1, 2],
+            "Target_AC" => vec![0, 0],
+            "Number_hits" => vec![1, 1],
+            "Number_crits" => vec![0; 2],
+            "Raw_damage" => vec![5, 5],
+            "Total_damage" => vec![2, 2],
+            "Rider_damage" => vec![0; 2],
+            "Bonus_roll" => vec![0; 2],
+            "Damage_fire" => vec![2, 2],
+        ]
+        .unwrap();
+
+        let obs_df = process_simulation(
+            "Fighter",
+            vec![target],
+            vec!["1d1+1".to_string()],
+            vec!["1d1+4 fire".to_string()],
+            vec![],
+            Ruleset::PF2e,
+            2,
+            1,
+            None,
+        )
+        .unwrap();
+        dataframes_are_equal(exp_df, obs_df.clone());
+
+        let summary_df = summarise_results(obs_df);
+        let obs_raw = summary_df
+            .column("Damage before defences per round (mean)")
+            .unwrap();
+        assert_eq!(Some(5.0), obs_raw.f64().unwrap().get(0));
+    }
+
+    #[test]
+    fn test_process_simulation_defences_dnd() {
+        // Test that the defences of a target in a D&D 5e build halve and double damage, rather
+        //  than applying the Pathfinder 2e amounts.
+        let mut target = Target::from_ac(0);
+        target.resistances.insert("fire".to_string(), 3);
+        target.weaknesses.insert("cold".to_string(), 3);
+
+        let exp_df = df![
+            "Build" => vec!["Fighter"; 2],
+            "Iteration" => vec![1, 2],
+            "Target_AC" => vec![0, 0],
+            "Number_hits" => vec![1, 1],
+            "Number_crits" => vec![0; 2],
+            "Raw_damage" => vec![9, 9],
+            "Total_damage" => vec![7, 7],
+            "Rider_damage" => vec![4; 2],
+            "Bonus_roll" => vec![0; 2],
+            "Damage_cold" => vec![4, 4],
+            "Damage_fire" => vec![3, 3],
+        ]
+        .unwrap();
+
+        let obs_df = process_simulation(
+            "Fighter",
+            vec![target],
+            vec!["1d1+1".to_string()],
+            vec!["1d1+6 fire".to_string()],
+            vec!["2d1 cold".to_string()],
+            Ruleset::DND5e,
+            2,
+            1,
+            None,
+        )
+        .unwrap();
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    #[test]
+    fn test_process_simulation_persistent() {
+        // Test that persistent damage is dealt in the round it is inflicted, and that the
+        //  rounds of each sequence are recorded.
+        let obs_df = process_simulation(
+            "Gunslinger",
+            vec![Target::from_ac(0)],
+            vec!["1d1+1".to_string()],
+            vec!["1d1+1".to_string()],
+            vec!["persistent:2d1 fire".to_string()],
+            Ruleset::PF2e,
+            4,
+            2,
+            None,
+        )
+        .unwrap();
+
+        let exp_columns = [
+            "Build",
+            "Iteration",
+            "Round",
+            "Target_AC",
+            "Number_hits",
+            "Number_crits",
+            "Total_damage",
+            "Rider_damage",
+            "Persistent_damage",
+            "Bonus_roll",
+            "Damage_untyped",
+            "Damage_fire",
+        ];
+        assert_eq!(exp_columns.to_vec(), obs_df.get_column_names());
+
+        let exp_rounds = Series::new("Round", [1, 2, 1, 2]);
+        assert!(obs_df.column("Round").unwrap().equals(&exp_rounds));
+
+        // Persistent damage does not stack, so is 2 in every round
+        let exp_persistent = Series::new("Persistent_damage", [2, 2, 2, 2]);
+        assert!(obs_df
+            .column("Persistent_damage")
+            .unwrap()
+            .equals(&exp_persistent));
+
+        let summary_df = summarise_results(obs_df);
+        let obs_persistent = summary_df
+            .column("Persistent damage per round (mean)")
+            .unwrap();
+        assert_eq!(Some(2.0), obs_persistent.f64().unwrap().get(0));
+    }
+
+    #[test]
+    fn test_process_simulation_encounter() {
+        // Test that each encounter lasts until the target drops, with the killing blow
+        //  landing on the first attack of the second round.
+        let target = Target {
+            hp: Some(12),
+            ..Target::from_ac(0)
+        };
+
+        let exp_df = df![
+            "Build" => vec!["Fighter"; 4],
+            "Iteration" => vec![1, 2, 3, 4],
+            "Round" => vec![1, 2, 1, 2],
+            "Target_AC" => vec![0; 4],
+            "Target_HP" => vec![12; 4],
+            "Number_hits" => vec![2; 4],
+            "Number_crits" => vec![0; 4],
+            "Total_damage" => vec![10; 4],
+            "Rider_damage" => vec![0; 4],
+            "Bonus_roll" => vec![0; 4],
+            "Rounds_to_kill" => vec![None, Some(2), None, Some(2)],
+            "Overkill" => vec![None, Some(3), None, Some(3)],
+            "Wasted_damage" => vec![None, Some(8), None, Some(8)],
+        ]
+        .unwrap();
+
+        let obs_df = process_simulation(
+            "Fighter",
+            vec![target],
+            vec!["1d1+1".to_string(), "1d1+1".to_string()],
+            vec!["1d1+4".to_string(), "1d1+4".to_string()],
+            vec![],
+            Ruleset::PF2e,
+            4,
+            1,
+            None,
+        )
+        .unwrap();
         dataframes_are_equal(exp_df, obs_df);
     }
 
+    #[test]
+    fn test_process_simulation_encounter_unfinished() {
+        // Test that the final encounter is dropped if the target has not dropped by the last turn
+        let target = Target {
+            hp: Some(12),
+            ..Target::from_ac(0)
+        };
+
+        let obs_df = process_simulation(
+            "Fighter",
+            vec![target],
+            vec!["1d1+1".to_string(), "1d1+1".to_string()],
+            vec!["1d1+4".to_string(), "1d1+4".to_string()],
+            vec![],
+            Ruleset::PF2e,
+            5,
+            1,
+            None,
+        )
+        .unwrap();
+        assert_eq!(4, obs_df.height());
+
+        assert_eq!(2, obs_df.column("Rounds_to_kill").unwrap().null_count());
+    }
+
+    #[test]
+    fn test_process_simulation_encounter_never_drops() {
+        // Test that the turns are kept if the target never drops, with no rounds to kill
+        let target = Target {
+            hp: Some(100),
+            ..Target::from_ac(0)
+        };
+
+        let obs_df = process_simulation(
+            "Fighter",
+            vec![target],
+            vec!["1d1+1".to_string()],
+            vec!["1d1+4".to_string()],
+            vec![],
+            Ruleset::PF2e,
+            3,
+            1,
+            None,
+        )
+        .unwrap();
+        assert_eq!(3, obs_df.height());
+        assert_eq!(3, obs_df.column("Rounds_to_kill").unwrap().null_count());
+    }
+
+    #[test]
+    fn test_process_simulation_invalid_hp() {
+        let target = Target {
+            hp: Some(0),
+            ..Target::from_ac(10)
+        };
+
+        let obs_result = process_simulation(
+            "Fighter",
+            vec![target],
+            vec!["1d20+5".to_string()],
+            vec!["1d8+4".to_string()],
+            vec![],
+            Ruleset::DND5e,
+            5,
+            1,
+            None,
+        );
+        assert_eq!(
+            "The hit points of a target must be at least 1!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_process_simulation_follow_up() {
+        // Test that the follow-up attack is only made on the turns where the first attack misses
+        let obs_df = process_simulation(
+            "Fighter",
+            vec![Target::from_ac(0), Target::from_ac(10)],
+            vec!["1d1+1".to_string(), "miss:1d1+10".to_string()],
+            vec!["1d1".to_string(), "1d1".to_string()],
+            vec![],
+            Ruleset::PF2e,
+            2,
+            1,
+            None,
+        )
+        .unwrap();
+
+        let exp_triggered = Series::new("Triggered_attacks", [0, 0, 1, 1]);
+        assert!(obs_df
+            .column("Triggered_attacks")
+            .unwrap()
+            .equals(&exp_triggered));
+        let exp_hits = Series::new("Number_hits", [1, 1, 1, 1]);
+        assert!(obs_df.column("Number_hits").unwrap().equals(&exp_hits));
+
+        let summary_df = summarise_results(obs_df);
+        let obs_triggered = summary_df
+            .column("Triggered attacks per round (mean)")
+            .unwrap();
+        assert_eq!(Some(1.0), obs_triggered.f64().unwrap().get(1));
+    }
+
+    #[test]
+    fn test_process_simulation_invalid_rounds() {
+        let obs_result = process_simulation(
+            "Fighter",
+            vec![Target::from_ac(10)],
+            vec!["1d20+5".to_string()],
+            vec!["1d8+4".to_string()],
+            vec![],
+            Ruleset::DND5e,
+            5,
+            0,
+            None,
+        );
+        assert_eq!(
+            "The number of rounds must be at least 1!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
     #[test]
     fn test_process_simulation_multithreaded() {
-        /* Test the complete run of the turnsimulation.process_simulation() function when
+        /* Test the complete run of the turnsimulation.process_simulation().unwrap() function when
             running with multiple threads through rayon. Only testing over the success case,
             as internal behaviours are tested in relevant unit tests.
         */
 
         let exp_df = df![
+            "Build" => vec!["Fighter"; 10],
             "Iteration" => vec![1, 2, 3, 4, 5, 1, 2, 3, 4, 5],
             "Target_AC" => vec![0, 0, 0, 0, 0, 10, 10, 10, 10, 10],
             "Number_hits" => vec![1, 1, 1, 1, 1, 0, 0, 0, 0, 0],
             "Number_crits" => vec![0; 10],
             "Total_damage" => vec![2, 2, 2, 2, 2, 0, 0, 0, 0, 0],
+            "Rider_damage" => vec![0; 10],
+            "Bonus_roll" => vec![0; 10],
         ]
         .unwrap();
 
         let obs_df = process_simulation(
-            vec![0, 10],
+            "Fighter",
+            vec![Target::from_ac(0), Target::from_ac(10)],
             vec!["1d1+1".to_string()],
             vec!["1d1+1".to_string()],
+            vec![],
             Ruleset::DND5e,
             5,
+            1,
             Some(2),
-        );
+        )
+        .unwrap();
         dataframes_are_equal(exp_df, obs_df);
     }
 
+    #[test]
+    fn test_process_simulation_invalid() {
+        let obs_result = process_simulation(
+            "Fighter",
+            vec![Target::from_ac(10)],
+            vec!["1d20+5".to_string()],
+            vec!["d8+4".to_string()],
+            vec![],
+            Ruleset::DND5e,
+            5,
+            1,
+            None,
+        );
+
+        assert!(obs_result.is_err());
+        assert_eq!(
+            "Unable to parse notation 'd8+4': found 'd' at position 0, expected a dice term in the form XdY (for example 1d8) or a constant!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
     // endregion:
 
     // region: summarise_result() tests
@@ -680,22 +2003,649 @@ mod tests {
         // Test the behaviour of the summarise_results() function.
 
         let input_df = df![
+            "Build" => vec!["Fighter"; 9],
             "Target_AC" => vec![0, 0, 0, 1, 1, 1, 2, 2, 2],
             "Number_hits" => vec![0, 1, 2, 4, 6, 8, 1, 2, 3],
             "Number_crits" => vec![0, 1, 2, 4, 6, 8, 1, 2, 3],
             "Total_damage" => vec![0, 1, 2, 4, 6, 8, 1, 2, 3],
+            "Rider_damage" => vec![0, 1, 2, 4, 6, 8, 1, 2, 3],
+            "Bonus_roll" => vec![0, 1, 2, 4, 6, 8, 1, 2, 3],
         ]
         .unwrap();
 
         let exp_df = df![
+            "Build" => vec!["Fighter"; 3],
             "Target AC" => vec![0, 1, 2],
             "Hits per round (mean)" => vec![1, 6, 2],
             "Critical hits per round (mean)" => vec![1, 6, 2],
             "Damage per round (mean)" => vec![1, 6, 2],
+            "Rider damage per round (mean)" => vec![1, 6, 2],
+            "Attack bonus dice per round (mean)" => vec![1, 6, 2],
+        ]
+        .unwrap();
+
+        let obs_df = summarise_results(input_df);
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    #[test]
+    fn test_summarise_results_typed() {
+        let input_df = df![
+            "Build" => vec!["Fighter"; 2],
+            "Iteration" => vec![1, 2],
+            "Target_AC" => vec![10, 10],
+            "Number_hits" => vec![1, 1],
+            "Number_crits" => vec![0, 0],
+            "Total_damage" => vec![6, 4],
+            "Rider_damage" => vec![0, 0],
+            "Bonus_roll" => vec![0, 0],
+            "Damage_fire" => vec![4, 2],
+            "Damage_untyped" => vec![2, 2],
         ]
         .unwrap();
 
         let obs_df = summarise_results(input_df);
+        let obs_fire = obs_df.column("Fire damage per round (mean)").unwrap();
+        assert_eq!(Some(3.0), obs_fire.f64().unwrap().get(0));
+        let obs_untyped = obs_df.column("Untyped damage per round (mean)").unwrap();
+        assert_eq!(Some(2.0), obs_untyped.f64().unwrap().get(0));
+    }
+
+    #[test]
+    fn test_summarise_results_encounter() {
+        let input_df = df![
+            "Build" => vec!["Fighter"; 5],
+            "Iteration" => vec![1, 2, 3, 4, 5],
+            "Round" => vec![1, 2, 1, 1, 2],
+            "Target_AC" => vec![10; 5],
+            "Target_HP" => vec![12; 5],
+            "Number_hits" => vec![1; 5],
+            "Number_crits" => vec![0; 5],
+            "Total_damage" => vec![6, 8, 14, 4, 10],
+            "Rider_damage" => vec![0; 5],
+            "Bonus_roll" => vec![0; 5],
+            "Rounds_to_kill" => vec![None, Some(2), Some(1), None, Some(2)],
+            "Overkill" => vec![None, Some(2), Some(2), None, Some(2)],
+            "Wasted_damage" => vec![None, Some(2), Some(2), None, Some(5)],
+        ]
+        .unwrap();
+
+        let obs_df = summarise_results(input_df);
+        assert!(obs_df.column("Target HP").is_ok());
+
+        let obs_mean = obs_df.column("Rounds to kill (mean)").unwrap();
+        assert_eq!(Some(5.0 / 3.0), obs_mean.f64().unwrap().get(0));
+        let obs_median = obs_df.column("Rounds to kill (median)").unwrap();
+        assert_eq!(Some(2.0), obs_median.f64().unwrap().get(0));
+        let obs_overkill = obs_df.column("Overkill per kill (mean)").unwrap();
+        assert_eq!(Some(2.0), obs_overkill.f64().unwrap().get(0));
+        let obs_wasted = obs_df.column("Wasted damage per kill (mean)").unwrap();
+        assert_eq!(Some(3.0), obs_wasted.f64().unwrap().get(0));
+    }
+
+    #[test]
+    fn test_damage_type_label() {
+        assert_eq!("Damage_fire", damage_type_column(&Some("fire".to_string())));
+        assert_eq!("Damage_untyped", damage_type_column(&None));
+        assert_eq!("Fire damage per round", damage_type_label("Damage_fire"));
+    }
+
+    #[test]
+    fn test_summarise_results_multiple_builds() {
+        // Test that builds are summarised separately, and reported in order of appearance for each AC.
+
+        let input_df = df![
+            "Build" => vec!["Rogue", "Rogue", "Fighter", "Fighter", "Rogue", "Fighter"],
+            "Target_AC" => vec![1, 0, 0, 1, 0, 1],
+            "Number_hits" => vec![0, 1, 2, 4, 3, 6],
+            "Number_crits" => vec![0, 1, 2, 4, 3, 6],
+            "Total_damage" => vec![0, 1, 2, 4, 3, 6],
+            "Rider_damage" => vec![0, 1, 2, 4, 3, 6],
+            "Bonus_roll" => vec![0, 1, 2, 4, 3, 6],
+        ]
+        .unwrap();
+
+        let exp_df = df![
+            "Build" => vec!["Rogue", "Fighter", "Rogue", "Fighter"],
+            "Target AC" => vec![0, 0, 1, 1],
+            "Hits per round (mean)" => vec![2.0, 2.0, 0.0, 5.0],
+            "Critical hits per round (mean)" => vec![2.0, 2.0, 0.0, 5.0],
+            "Damage per round (mean)" => vec![2.0, 2.0, 0.0, 5.0],
+            "Rider damage per round (mean)" => vec![2.0, 2.0, 0.0, 5.0],
+            "Attack bonus dice per round (mean)" => vec![2.0, 2.0, 0.0, 5.0],
+        ]
+        .unwrap();
+
+        let obs_df = summarise_results(input_df);
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    #[test]
+    fn test_summarise_results_levels() {
+        // Test that each level is summarised separately, ordered by level then AC.
+
+        let input_df = df![
+            "Build" => vec!["Druid"; 6],
+            "Level" => vec![2, 2, 1, 1, 1, 1],
+            "Target_AC" => vec![17, 17, 18, 18, 16, 16],
+            "Number_hits" => vec![0, 2, 1, 1, 2, 2],
+            "Number_crits" => vec![0, 2, 1, 1, 2, 2],
+            "Total_damage" => vec![0, 2, 1, 1, 2, 2],
+            "Rider_damage" => vec![0, 2, 1, 1, 2, 2],
+            "Bonus_roll" => vec![0, 2, 1, 1, 2, 2],
+        ]
+        .unwrap();
+
+        let exp_df = df![
+            "Build" => vec!["Druid"; 3],
+            "Level" => vec![1, 1, 2],
+            "Target AC" => vec![16, 18, 17],
+            "Hits per round (mean)" => vec![2.0, 1.0, 1.0],
+            "Critical hits per round (mean)" => vec![2.0, 1.0, 1.0],
+            "Damage per round (mean)" => vec![2.0, 1.0, 1.0],
+            "Rider damage per round (mean)" => vec![2.0, 1.0, 1.0],
+            "Attack bonus dice per round (mean)" => vec![2.0, 1.0, 1.0],
+        ]
+        .unwrap();
+
+        let obs_df = summarise_results(input_df);
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    // endregion:
+
+    // region: process_exact().unwrap() tests
+
+    #[test]
+    fn test_process_exact() {
+        // A guaranteed hit against AC 0 and a guaranteed miss against AC 10, with a rider on hit.
+
+        let exp_df = df![
+            "Build" => vec!["Fighter"; 10],
+            "Target_AC" => vec![0, 0, 0, 0, 0, 10, 10, 10, 10, 10],
+            "Metric" => vec!["Number_hits", "Number_crits", "Total_damage", "Rider_damage", "Bonus_roll", "Number_hits", "Number_crits", "Total_damage", "Rider_damage", "Bonus_roll"],
+            "Value" => vec![1, 0, 5, 3, 0, 0, 0, 0, 0, 0],
+            "Probability" => vec![1.0; 10],
+        ]
+        .unwrap();
+
+        let obs_df = process_exact(
+            "Fighter",
+            vec![Target::from_ac(0), Target::from_ac(10)],
+            vec!["1d1+1".to_string()],
+            vec!["1d1+1".to_string()],
+            vec!["1d1+2".to_string()],
+            Ruleset::DND5e,
+        )
+        .unwrap();
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    #[test]
+    fn test_distributions_to_dataframe() {
+        // Test that zero-probability values are dropped from the output.
+
+        let turn_distribution = TurnDistribution {
+            hits: Distribution::new(0, vec![0.5, 0.5]),
+            crits: Distribution::new(0, vec![1.0, 0.0]),
+            damage: Distribution::new(0, vec![0.5, 0.0, 0.5]),
+            rider_damage: Distribution::point(0),
+            bonus_roll: Distribution::new(1, vec![0.25, 0.75]),
+            damage_by_type: BTreeMap::new(),
+        };
+
+        let exp_df = df![
+            "Build" => vec!["Fighter"; 8],
+            "Target_AC" => vec![12; 8],
+            "Metric" => vec!["Number_hits", "Number_hits", "Number_crits", "Total_damage", "Total_damage", "Rider_damage", "Bonus_roll", "Bonus_roll"],
+            "Value" => vec![0, 1, 0, 0, 2, 0, 1, 2],
+            "Probability" => vec![0.5, 0.5, 1.0, 0.5, 0.5, 1.0, 0.25, 0.75],
+        ]
+        .unwrap();
+
+        let obs_df =
+            distributions_to_dataframe("Fighter", &Target::from_ac(12), &turn_distribution);
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    // endregion:
+
+    // region: label_target() tests
+
+    #[test]
+    fn test_label_target() {
+        let mut target = Target::from_ac(17);
+        target.name = Some("Ogre".to_string());
+
+        let input_df = df!["Build" => vec!["Fighter"; 2], "Target_AC" => vec![17; 2]].unwrap();
+        let exp_df = df![
+            "Build" => vec!["Fighter"; 2],
+            "Target" => vec!["Ogre"; 2],
+            "Target_AC" => vec![17; 2],
+        ]
+        .unwrap();
+
+        let obs_df = label_target(input_df, &target);
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    #[test]
+    fn test_label_target_unnamed() {
+        let input_df = df!["Build" => vec!["Fighter"], "Target_AC" => vec![17]].unwrap();
+
+        let obs_df = label_target(input_df.clone(), &Target::from_ac(17));
+        dataframes_are_equal(input_df, obs_df);
+    }
+
+    #[test]
+    fn test_process_exact_named_targets() {
+        // Test that named targets are summarised in the order provided, and that saves are
+        //  resolved against each target.
+        let mut ogre = Target::from_ac(10);
+        ogre.name = Some("Ogre".to_string());
+        ogre.saves.insert("reflex".to_string(), -20);
+
+        let mut sprite = Target::from_ac(10);
+        sprite.name = Some("Sprite".to_string());
+        sprite.saves.insert("reflex".to_string(), 20);
+
+        let results_df = process_exact(
+            "Wizard",
+            vec![sprite, ogre],
+            vec!["dc15:reflex".to_string()],
+            vec!["1d1+3".to_string()],
+            vec![],
+            Ruleset::DND5e,
+        )
+        .unwrap();
+        let obs_df = summarise_exact_results(results_df);
+
+        let exp_targets = Series::new("Target", ["Sprite", "Ogre"]);
+        assert!(obs_df.column("Target").unwrap().equals(&exp_targets));
+
+        let obs_damage = obs_df
+            .column("Damage per round (mean)")
+            .unwrap()
+            .f64()
+            .unwrap();
+        for (exp, obs) in [2.0, 4.0].iter().zip(obs_damage.into_no_null_iter()) {
+            assert!((exp - obs).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_process_exact_defences() {
+        let mut target = Target::from_ac(10);
+        target.immunities.push("fire".to_string());
+
+        let obs_result = process_exact(
+            "Fighter",
+            vec![target],
+            vec!["1d20+5".to_string()],
+            vec!["1d8 fire".to_string()],
+            vec![],
+            Ruleset::PF2e,
+        );
+        assert_eq!(
+            "Exact mode does not support targets with resistances, weaknesses, or immunities!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_process_exact_hit_points() {
+        let target = Target {
+            hp: Some(30),
+            ..Target::from_ac(10)
+        };
+
+        let obs_result = process_exact(
+            "Fighter",
+            vec![target],
+            vec!["1d20+5".to_string()],
+            vec!["1d8".to_string()],
+            vec![],
+            Ruleset::PF2e,
+        );
+        assert_eq!(
+            "Exact mode does not support targets with hit points!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_process_exact_follow_up() {
+        let obs_result = process_exact(
+            "Fighter",
+            vec![Target::from_ac(10)],
+            vec!["1d20+7".to_string(), "crit:1d20+2".to_string()],
+            vec!["1d8".to_string(), "1d8".to_string()],
+            vec![],
+            Ruleset::DND5e,
+        );
+        assert_eq!(
+            "Exact mode does not support follow-up attacks!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_process_exact_persistent() {
+        let obs_result = process_exact(
+            "Gunslinger",
+            vec![Target::from_ac(10)],
+            vec!["1d20+5".to_string()],
+            vec!["1d8".to_string()],
+            vec!["persistent:2d4 fire".to_string()],
+            Ruleset::PF2e,
+        );
+        assert_eq!(
+            "Exact mode does not support persistent damage!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_process_exact_typed() {
+        // Test that the mean and variance of each damage type is summarised from the exact distributions.
+        let results_df = process_exact(
+            "Fighter",
+            vec![Target::from_ac(0)],
+            vec!["1d1+1".to_string()],
+            vec!["1d2 fire+3, 1d1".to_string()],
+            vec![],
+            Ruleset::DND5e,
+        )
+        .unwrap();
+        let obs_df = summarise_exact_results(results_df);
+
+        for (column, exp) in [
+            ("Fire damage per round (mean)", 4.5),
+            ("Fire damage per round (variance)", 0.25),
+            ("Untyped damage per round (mean)", 1.0),
+            ("Untyped damage per round (variance)", 0.0),
+        ] {
+            let obs = obs_df
+                .column(column)
+                .unwrap()
+                .f64()
+                .unwrap()
+                .get(0)
+                .unwrap();
+            assert!((exp - obs).abs() < 1e-9, "{}: {} != {}", column, exp, obs);
+        }
+    }
+
+    // endregion:
+
+    // region: summarise_exact_results() tests
+
+    #[test]
+    fn test_summarise_exact_results() {
+        let input_df = df![
+            "Build" => vec!["Fighter"; 14],
+            "Target_AC" => vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1],
+            "Metric" => vec!["Number_hits", "Number_hits", "Number_crits", "Total_damage", "Total_damage", "Rider_damage", "Rider_damage", "Bonus_roll", "Bonus_roll", "Number_hits", "Number_crits", "Total_damage", "Rider_damage", "Bonus_roll"],
+            "Value" => vec![0, 1, 0, 0, 4, 0, 2, 1, 3, 1, 1, 6, 2, 0],
+            "Probability" => vec![0.5, 0.5, 1.0, 0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 1.0, 1.0, 1.0, 1.0, 1.0],
+        ]
+        .unwrap();
+
+        let exp_df = df![
+            "Build" => vec!["Fighter"; 2],
+            "Target AC" => vec![0, 1],
+            "Hits per round (mean)" => vec![0.5, 1.0],
+            "Hits per round (variance)" => vec![0.25, 0.0],
+            "Critical hits per round (mean)" => vec![0.0, 1.0],
+            "Critical hits per round (variance)" => vec![0.0, 0.0],
+            "Damage per round (mean)" => vec![2.0, 6.0],
+            "Damage per round (variance)" => vec![4.0, 0.0],
+            "Rider damage per round (mean)" => vec![1.0, 2.0],
+            "Rider damage per round (variance)" => vec![1.0, 0.0],
+            "Attack bonus dice per round (mean)" => vec![2.0, 0.0],
+            "Attack bonus dice per round (variance)" => vec![1.0, 0.0],
+        ]
+        .unwrap();
+
+        let obs_df = summarise_exact_results(input_df);
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    // endregion:
+
+    // region: combine_results() tests
+
+    #[test]
+    fn test_combine_results() {
+        let fighter_df = process_exact(
+            "Fighter",
+            vec![Target::from_ac(0)],
+            vec!["1d1+1".to_string()],
+            vec!["1d1+1".to_string()],
+            vec![],
+            Ruleset::DND5e,
+        )
+        .unwrap();
+        let rogue_df = process_exact(
+            "Rogue",
+            vec![Target::from_ac(0)],
+            vec!["1d1+1".to_string()],
+            vec!["1d1".to_string()],
+            vec![],
+            Ruleset::DND5e,
+        )
+        .unwrap();
+
+        let exp_df = df![
+            "Build" => vec!["Fighter", "Fighter", "Fighter", "Fighter", "Fighter", "Rogue", "Rogue", "Rogue", "Rogue", "Rogue"],
+            "Target_AC" => vec![0; 10],
+            "Metric" => vec!["Number_hits", "Number_crits", "Total_damage", "Rider_damage", "Bonus_roll", "Number_hits", "Number_crits", "Total_damage", "Rider_damage", "Bonus_roll"],
+            "Value" => vec![1, 0, 2, 0, 0, 1, 0, 1, 0, 0],
+            "Probability" => vec![1.0; 10],
+        ]
+        .unwrap();
+
+        let obs_df = combine_results(vec![fighter_df, rogue_df]).unwrap();
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    #[test]
+    fn test_combine_results_levels() {
+        // Test that a build without levels is given a null Level when combined.
+
+        let druid_df = df![
+            "Build" => vec!["Druid"],
+            "Level" => vec![3],
+            "Target_AC" => vec![18],
+        ]
+        .unwrap();
+        let fighter_df = df![
+            "Build" => vec!["Fighter"],
+            "Target_AC" => vec![18],
+        ]
+        .unwrap();
+
+        let exp_df = df![
+            "Build" => vec!["Druid", "Fighter"],
+            "Level" => vec![Some(3), None],
+            "Target_AC" => vec![18, 18],
+        ]
+        .unwrap();
+
+        let obs_df = combine_results(vec![druid_df, fighter_df]).unwrap();
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    #[test]
+    fn test_combine_results_empty() {
+        let obs_result = combine_results(vec![]);
+        assert_eq!(
+            "There are no results to combine, at least one target is required!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    // endregion:
+
+    // region: assign_level() tests
+
+    #[test]
+    fn test_assign_level() {
+        let input_df = df![
+            "Build" => vec!["Druid"; 2],
+            "Target_AC" => vec![16, 18],
+            "Total_damage" => vec![4, 3],
+        ]
+        .unwrap();
+
+        let exp_df = df![
+            "Build" => vec!["Druid"; 2],
+            "Level" => vec![5; 2],
+            "Target_AC" => vec![16, 18],
+            "Total_damage" => vec![4, 3],
+        ]
+        .unwrap();
+
+        let obs_df = assign_level(input_df, 5);
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    // endregion:
+
+    // region: summary_keys() tests
+
+    #[test]
+    fn test_summary_keys() {
+        let input_df = df!["Build" => vec!["Druid"], "Target_AC" => vec![16]].unwrap();
+        assert_eq!(
+            vec!["Build", "Target_AC"],
+            summary_keys(&input_df, "Target_AC", "Target_HP")
+        );
+    }
+
+    #[test]
+    fn test_summary_keys_level() {
+        let input_df = df![
+            "Build" => vec!["Druid"],
+            "Level" => vec![1],
+            "Target AC" => vec![16],
+        ]
+        .unwrap();
+        assert_eq!(
+            vec!["Build", "Level", "Target AC"],
+            summary_keys(&input_df, "Target AC", "Target HP")
+        );
+    }
+
+    #[test]
+    fn test_summary_keys_target() {
+        let input_df = df![
+            "Build" => vec!["Druid"],
+            "Level" => vec![1],
+            "Target" => vec!["Ogre"],
+            "Target AC" => vec![16],
+        ]
+        .unwrap();
+        assert_eq!(
+            vec!["Build", "Level", "Target", "Target AC"],
+            summary_keys(&input_df, "Target AC", "Target HP")
+        );
+    }
+
+    #[test]
+    fn test_summary_keys_hp() {
+        let input_df = df![
+            "Build" => vec!["Druid"],
+            "Target_AC" => vec![16],
+            "Target_HP" => vec![40],
+        ]
+        .unwrap();
+        assert_eq!(
+            vec!["Build", "Target_AC", "Target_HP"],
+            summary_keys(&input_df, "Target_AC", "Target_HP")
+        );
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        assert_eq!(
+            vec!["Level", "Target AC"],
+            sort_keys(&["Build", "Level", "Target", "Target AC"])
+        );
+    }
+
+    // endregion:
+
+    // region: compare_summaries() tests
+
+    #[test]
+    fn test_compare_summaries() {
+        // Test that builds are pivoted into columns, with missing AC values reported as null.
+
+        let input_df = df![
+            "Build" => vec!["Rogue", "Fighter", "Fighter"],
+            "Target AC" => vec![10, 10, 12],
+            "Hits per round (mean)" => vec![0.5, 1.0, 0.75],
+            "Damage per round (mean)" => vec![4.0, 6.0, 4.5],
+        ]
+        .unwrap();
+
+        let exp_df = df![
+            "Target AC" => vec![10, 12],
+            "Rogue: Hits per round (mean)" => vec![Some(0.5), None],
+            "Fighter: Hits per round (mean)" => vec![Some(1.0), Some(0.75)],
+            "Rogue: Damage per round (mean)" => vec![Some(4.0), None],
+            "Fighter: Damage per round (mean)" => vec![Some(6.0), Some(4.5)],
+        ]
+        .unwrap();
+
+        let obs_df = compare_summaries(input_df, &create_string_vector(vec!["Rogue", "Fighter"]));
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    #[test]
+    fn test_compare_summaries_build_order() {
+        // Test that builds keep the order given, rather than the order of the sorted summary.
+
+        let input_df = df![
+            "Build" => vec!["Fighter", "Rogue"],
+            "Target AC" => vec![10, 12],
+            "Damage per round (mean)" => vec![6.0, 4.0],
+        ]
+        .unwrap();
+
+        let exp_df = df![
+            "Target AC" => vec![10, 12],
+            "Rogue: Damage per round (mean)" => vec![None, Some(4.0)],
+            "Fighter: Damage per round (mean)" => vec![Some(6.0), None],
+        ]
+        .unwrap();
+
+        let obs_df = compare_summaries(input_df, &create_string_vector(vec!["Rogue", "Fighter"]));
+        dataframes_are_equal(exp_df, obs_df);
+    }
+
+    #[test]
+    fn test_compare_summaries_levels() {
+        // Test that builds are matched on both level and AC, including builds without a level.
+
+        let input_df = df![
+            "Build" => vec!["Fighter", "Druid", "Druid", "Fighter"],
+            "Level" => vec![None, Some(1), Some(2), Some(2)],
+            "Target AC" => vec![16, 16, 17, 17],
+            "Damage per round (mean)" => vec![9.0, 6.0, 6.5, 8.0],
+        ]
+        .unwrap();
+
+        let exp_df = df![
+            "Level" => vec![None, Some(1), Some(2)],
+            "Target AC" => vec![16, 16, 17],
+            "Fighter: Damage per round (mean)" => vec![Some(9.0), None, Some(8.0)],
+            "Druid: Damage per round (mean)" => vec![None, Some(6.0), Some(6.5)],
+        ]
+        .unwrap();
+
+        let obs_df = compare_summaries(input_df, &create_string_vector(vec!["Fighter", "Druid"]));
         dataframes_are_equal(exp_df, obs_df);
     }
 
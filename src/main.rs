@@ -1,5 +1,5 @@
 use clap::Parser;
-use dpr_simulator::Ruleset;
+use dpr_simulator::{Ruleset, Target, TurnPlan};
 use polars::frame::DataFrame;
 
 fn main() {
@@ -7,39 +7,227 @@ fn main() {
 
     let cli: Cli = Cli::parse();
 
+    // If a build file is provided, simulate each build in turn and exit
+    if let Some(config_path) = &cli.config {
+        let build_file = match dpr_simulator::BuildFile::from_file(config_path) {
+            Ok(b) => b,
+            Err(e) => {
+                println!("ERROR: {}", e);
+                std::process::exit(1);
+            }
+        };
+
+        // The builds are compared in the order they are listed in the file
+        let build_names: Vec<String> = build_file.builds.iter().map(|b| b.name.clone()).collect();
+
+        let mut build_results: Vec<DataFrame> = Vec::new();
+        for build in build_file.builds {
+            let number_turns = build.number_turns.unwrap_or(cli.number_turns);
+            let number_rounds = build.number_rounds.unwrap_or(cli.number_rounds);
+
+            // Builds with a level progression are run once per level, then combined
+            let mut build_df = match &build.levels {
+                None => {
+                    let (hit_vector, dmg_vector) = build.split_attacks();
+                    run_analysis(
+                        &cli,
+                        &build.name,
+                        build.resolve_targets(),
+                        hit_vector,
+                        dmg_vector,
+                        build.riders,
+                        build.ruleset,
+                        number_turns,
+                        number_rounds,
+                    )
+                }
+                Some(levels) => {
+                    let level_results: Vec<DataFrame> = levels
+                        .iter()
+                        .map(|level_details| {
+                            let (hit_vector, dmg_vector) = level_details.split_attacks();
+                            let level_df = run_analysis(
+                                &cli,
+                                &build.name,
+                                level_details.resolve_targets(),
+                                hit_vector,
+                                dmg_vector,
+                                level_details.resolve_riders(&build.riders),
+                                build.ruleset,
+                                number_turns,
+                                number_rounds,
+                            );
+                            dpr_simulator::assign_level(level_df, level_details.level)
+                        })
+                        .collect();
+                    combine_output(level_results)
+                }
+            };
+
+            if let Some(output_path) = build.output {
+                store_output(&output_path, &mut build_df);
+            }
+
+            // Unless comparing the builds, report each summary as it is produced
+            if !cli.compare {
+                println!("{}", summarise_output(&cli, build_df.clone()));
+            }
+            build_results.push(build_df);
+        }
+
+        let mut output_df = combine_output(build_results);
+        if let Some(output_path) = &cli.output {
+            store_output(output_path, &mut output_df);
+        }
+
+        if cli.compare {
+            let summary_df = summarise_output(&cli, output_df);
+            println!(
+                "{}",
+                dpr_simulator::compare_summaries(summary_df, &build_names)
+            );
+        }
+        return;
+    }
+
+    // Catch damage types separated from their dice by a space, which would be read as attacks
+    let separated_values = [
+        ("--weapon-details", &cli.weapon_details),
+        ("--riders", &cli.riders),
+        ("--turn-plan", &cli.turn_plan),
+    ];
+    for (option_name, values) in separated_values {
+        if let Err(e) = dpr_simulator::check_separated_damage_types(option_name, values) {
+            println!("ERROR: {}", e);
+            std::process::exit(1);
+        }
+    }
+
     // Upack the optional parameters
-    let ruleset = match cli.use_pf2e_criticals {
-        true => Ruleset::PF2e,
-        false => Ruleset::DND5e,
+    let ruleset = match (cli.use_pf2e_criticals, cli.no_auto_miss) {
+        (true, _) => Ruleset::PF2e,
+        (false, true) => Ruleset::DND5eNoAutoMiss,
+        (false, false) => Ruleset::DND5e,
     };
 
-    // Confirm that the hit and attack vectors are equal in length
-    let mut hit_vector = cli.to_hit;
-    let mut dmg_vector = cli.weapon_details;
-    dpr_simulator::equalise_input_vectors(&mut hit_vector, &mut dmg_vector);
+    // Either plan the attacks of the turn, or confirm that the hit and attack vectors are equal in length
+    let (hit_vector, dmg_vector) = match cli.attack_bonus {
+        Some(attack_bonus) => {
+            match TurnPlan::new(attack_bonus, cli.turn_plan.clone()).plan_attacks() {
+                Ok(attacks) => attacks,
+                Err(e) => {
+                    println!("ERROR: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        None => {
+            let mut hit_vector = cli.to_hit.clone();
+            let mut dmg_vector = cli.weapon_details.clone();
+            dpr_simulator::equalise_input_vectors(&mut hit_vector, &mut dmg_vector);
+            (hit_vector, dmg_vector)
+        }
+    };
 
-    // Process the information and capture results as a polars DataFrame
-    let mut output_df = dpr_simulator::process_simulation(
-        cli.ac_targets,
+    // Without hit points each AC is a single target, otherwise each AC is fought at every value
+    let targets: Vec<Target> = match cli.hit_points.is_empty() {
+        true => cli
+            .ac_targets
+            .iter()
+            .map(|ac| Target::from_ac(*ac))
+            .collect(),
+        false => cli
+            .ac_targets
+            .iter()
+            .flat_map(|ac| {
+                cli.hit_points.iter().map(|hp| Target {
+                    hp: Some(*hp),
+                    ..Target::from_ac(*ac)
+                })
+            })
+            .collect(),
+    };
+
+    let mut output_df = run_analysis(
+        &cli,
+        &cli.build_name,
+        targets,
         hit_vector,
         dmg_vector,
+        cli.riders.clone(),
         ruleset,
         cli.number_turns,
-        cli.n_threads,
+        cli.number_rounds,
     );
 
     // Store the output if required
-    if let Some(output_path) = cli.output {
-        store_output(&output_path, &mut output_df);
+    if let Some(output_path) = &cli.output {
+        store_output(output_path, &mut output_df);
     }
 
+    println!("{}", summarise_output(&cli, output_df));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_analysis(
+    cli: &Cli,
+    build_name: &str,
+    targets: Vec<Target>,
+    hit_vector: Vec<String>,
+    dmg_vector: Vec<String>,
+    riders: Vec<String>,
+    ruleset: Ruleset,
+    number_turns: i32,
+    number_rounds: i32,
+) -> DataFrame {
+    // Process the information and capture results as a polars DataFrame, either by simulation or
+    //  exact calculation of the outcome distributions
+    let analysis_result = match cli.exact {
+        true => dpr_simulator::process_exact(
+            build_name, targets, hit_vector, dmg_vector, riders, ruleset,
+        ),
+        false => dpr_simulator::process_simulation(
+            build_name,
+            targets,
+            hit_vector,
+            dmg_vector,
+            riders,
+            ruleset,
+            number_turns,
+            number_rounds,
+            cli.n_threads,
+        ),
+    };
+
+    match analysis_result {
+        Ok(df) => df,
+        Err(e) => {
+            println!("ERROR: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn combine_output(results: Vec<DataFrame>) -> DataFrame {
+    match dpr_simulator::combine_results(results) {
+        Ok(df) => df,
+        Err(e) => {
+            println!("ERROR: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn summarise_output(cli: &Cli, output_df: DataFrame) -> DataFrame {
     /* Report the summary results to the user. Currently it appears that the display
         can only be customised using env variables:
             POLARS_FMT_TABLE_HIDE_COLUMN_DATA_TYPES (hide data types)
             POLARS_FMT_TABLE_HIDE_COLUMN_SEPARATOR (hide separator)
     */
-    let summary_df = dpr_simulator::summarise_results(output_df);
-    println!("{}", summary_df);
+    match cli.exact {
+        true => dpr_simulator::summarise_exact_results(output_df),
+        false => dpr_simulator::summarise_results(output_df),
+    }
 }
 
 fn store_output(output_path: &str, output_df: &mut DataFrame) {
@@ -59,14 +247,34 @@ struct Cli {
     #[arg(short, long, value_name = "AC TARGETS", num_args = 1.., value_delimiter = ' ', default_values_t = vec![12, 14, 16, 18, 20])]
     ac_targets: Vec<i32>,
 
-    /// Details of the attack roll in the form 1d20+X
+    /// Space-delimited hit points of the target, fighting encounters against each AC until the target drops (optional)
+    #[arg(long, value_name = "HIT POINTS", num_args = 1.., value_delimiter = ' ')]
+    hit_points: Vec<i32>,
+
+    /// Details of the attack roll in the form 1d20+X, or of the target's saving throw in the form dcX:1d20+Y, optionally only made if an earlier attack hits, crits, or misses (hit:1d20+X, crit1:1d20+X, miss1:1d20+X)
     #[arg(short = 't', long, value_name = "TO HIT", num_args = 1.., value_delimiter = ' ')]
     to_hit: Vec<String>,
 
-    /// Details of each attack to be made in the form 1dX+Y or 1dX,1dY+Z
+    /// Details of each attack to be made in the form 1dX+Y or 1dX,1dY+Z, with miss: for damage only dealt on a miss (1dX+Y,miss:Y)
     #[arg(short = 'w', long, value_name = "WEAPON DETAILS", num_args = 1.., value_delimiter = ' ')]
     weapon_details: Vec<String>,
 
+    /// Base attack bonus of a Pathfinder 2e turn plan, to which the multiple attack penalty is applied (optional)
+    #[arg(long, value_name = "ATTACK BONUS", requires_all = ["turn_plan", "use_pf2e_criticals"], conflicts_with_all = ["to_hit", "weapon_details"])]
+    attack_bonus: Option<i32>,
+
+    /// Strikes and activities of a three-action Pathfinder 2e turn in the form 1dX+Y, with agile: for an agile weapon or 2a: for a two-action activity (optional)
+    #[arg(long, value_name = "TURN PLAN", num_args = 1.., value_delimiter = ' ', requires = "attack_bonus")]
+    turn_plan: Vec<String>,
+
+    /// Once-per-turn damage applied to the first hit (3d6 or hit:3d6) or first critical hit (crit:3d6), or as persistent damage (persistent:2d4fire) (optional)
+    #[arg(short, long, value_name = "RIDERS", num_args = 1.., value_delimiter = ' ')]
+    riders: Vec<String>,
+
+    /// Name of the build, reported in the Build column of the results (optional)
+    #[arg(short, long, value_name = "BUILD NAME", default_value = "Build")]
+    build_name: String,
+
     /// Path to save results in Apache parquet format (optional)
     #[arg(short, long, value_name = "OUTPUT FILE")]
     output: Option<String>,
@@ -75,6 +283,10 @@ struct Cli {
     #[arg(short, long, value_name = "NUMBER TURNS", default_value_t = 1_000_000)]
     number_turns: i32,
 
+    /// Number of consecutive rounds simulated together, over which persistent damage carries
+    #[arg(long = "rounds", value_name = "NUMBER ROUNDS", default_value_t = 1)]
+    number_rounds: i32,
+
     /// Number of threads for running in multi-threaded mode (optional)
     #[arg(long, value_name = "N THREADS")]
     n_threads: Option<usize>,
@@ -82,4 +294,20 @@ struct Cli {
     /// Use Pathfinder 2e rules for critical hits and damage calculation
     #[arg(long, default_value_t = false)]
     use_pf2e_criticals: bool,
+
+    /// Allow a natural 1 to hit if the attack total beats the AC, when using D&D 5e rules
+    #[arg(long, default_value_t = false, conflicts_with = "use_pf2e_criticals")]
+    no_auto_miss: bool,
+
+    /// Path to a TOML file of named builds to simulate, replacing the attack, AC, and ruleset options (optional)
+    #[arg(short, long, value_name = "CONFIG FILE", conflicts_with_all = ["ac_targets", "hit_points", "attack_bonus", "turn_plan", "to_hit", "weapon_details", "riders", "build_name", "use_pf2e_criticals", "no_auto_miss"])]
+    config: Option<String>,
+
+    /// Report the builds of the config file side by side in a single summary table
+    #[arg(long, default_value_t = false, requires = "config")]
+    compare: bool,
+
+    /// Compute exact outcome distributions instead of simulating turns (ignores number of turns, rounds, and threads, and does not support hit points)
+    #[arg(long, default_value_t = false)]
+    exact: bool,
 }
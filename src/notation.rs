@@ -0,0 +1,1365 @@
+use std::error::Error;
+use std::fmt;
+
+/// The prefix of a fragment which is only dealt when the attack misses
+const MISS_PREFIX: &str = "miss";
+
+const EXPECTED_TERM: &str = "a dice term in the form XdY (for example 1d8) or a constant";
+const EXPECTED_OPERATOR: &str = "'+', '-', or ',' followed by a further term";
+const EXPECTED_DICE_COUNT: &str = "a number of dice ≥ 1 before 'd' (for example 1d8)";
+const EXPECTED_DIE_SIZE: &str = "the die size ≥ 1 following 'd' (for example 1d8)";
+const EXPECTED_FLAG: &str =
+    "a dice flag (A, AA, D, khX, klX, !, !!, rX, mX, cX, ~X, or ^X), a damage type (for example fire), or '+', '-', or ',' followed by a further term";
+
+// region: NotationError
+
+/// A description of where and why a dice notation string could not be parsed
+#[derive(Debug, PartialEq)]
+pub struct NotationError {
+    pub notation: String,
+    pub position: usize,
+    pub token: String,
+    pub expected: String,
+}
+
+impl NotationError {
+    /// Creates a new NotationError, recording the position of the offending token in the notation.
+    ///
+    /// # Examples
+    /// ```
+    /// let error = NotationError::new("1d8+4x", 5, "x", "a static modifier in the form +X or -X");
+    /// ```
+    pub fn new(notation: &str, position: usize, token: &str, expected: &str) -> NotationError {
+        NotationError {
+            notation: notation.to_string(),
+            position,
+            token: token.to_string(),
+            expected: expected.to_string(),
+        }
+    }
+
+    /// Report the error against a longer notation string, in which the parsed notation began at the offset.
+    ///
+    /// # Examples
+    /// ```
+    /// // An error in the damage notation of the rider "crit:2d8x"
+    /// let error = NotationError::new("2d8x", 3, "x", "a static modifier in the form +X or -X");
+    /// let error = error.with_offset("crit:2d8x", 5);
+    /// ```
+    pub fn with_offset(mut self, notation: &str, offset: usize) -> NotationError {
+        self.notation = notation.to_string();
+        self.position += offset;
+        self
+    }
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let found = match self.token.is_empty() {
+            true => "nothing".to_string(),
+            false => format!("'{}'", self.token),
+        };
+        write!(
+            f,
+            "Unable to parse notation '{}': found {} at position {}, expected {}!",
+            self.notation, found, self.position, self.expected
+        )
+    }
+}
+
+impl Error for NotationError {}
+
+// endregion:
+
+// region: Abstract syntax tree
+
+/// A parsed dice notation string, made up of comma-separated fragments
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    pub fragments: Vec<Fragment>,
+}
+
+/// A series of terms which are added together, such as the 1d8+4 in 1d8+4,1d6. A fragment
+/// prefixed with miss: is only dealt when the attack misses.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fragment {
+    pub terms: Vec<Term>,
+    pub on_miss: bool,
+}
+
+/// A single element of a fragment, either a group of dice or a signed constant, each with
+/// an optional damage type
+#[derive(Clone, Debug, PartialEq)]
+pub enum Term {
+    Dice(DiceTerm),
+    Constant(i32, Option<String>),
+}
+
+/// A group of identical dice, such as 2d6A, with the flags that alter how they are rolled
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiceTerm {
+    pub count: i32,
+    pub size: i32,
+    pub flags: Vec<DiceFlag>,
+    pub damage_type: Option<String>,
+}
+
+/// A per-term modification to the way a group of dice is rolled
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiceFlag {
+    Advantage,
+    DoubleAdvantage,
+    Disadvantage,
+    KeepHighest(i32),
+    KeepLowest(i32),
+    Exploding,
+    Compounding,
+    Reroll(i32),
+    Floor(i32),
+    CritRange(i32),
+    Fatal(i32),
+    Deadly(i32),
+}
+
+impl Expression {
+    /// Parse a dice notation string into its abstract syntax tree.
+    ///
+    /// The notation is a comma-separated series of fragments, each of which is a series
+    /// of dice terms (such as 1d8, 2d6A, or 1d6~10) and constants joined with '+' or '-'.
+    /// Dice terms cannot be subtracted. Any term may be followed by a damage type, written
+    /// as a lowercase word of at least three letters. A fragment prefixed with miss: is
+    /// dealt only when the attack misses. The first token which does not fit this form is
+    /// reported in the returned error.
+    ///
+    /// # Examples
+    /// ```
+    /// let expression = Expression::parse("1d8+1d6+4,2d6~10")?;
+    ///
+    /// // A longsword with a flaming rune
+    /// let expression = Expression::parse("1d8 slashing + 1d6 fire + 4")?;
+    ///
+    /// // A greatsword with the Graze mastery, dealing the ability modifier on a miss
+    /// let expression = Expression::parse("2d6+4,miss:4")?;
+    ///
+    /// // Typos are reported, rather than silently ignored
+    /// assert!(Expression::parse("1d8+4x").is_err());
+    /// ```
+    pub fn parse(notation: &str) -> Result<Expression, NotationError> {
+        let tokens = tokenize(notation)?;
+        Parser::new(notation, tokens).parse_expression()
+    }
+}
+
+impl Term {
+    /// Return the damage type of the term, if one was given.
+    ///
+    /// # Examples
+    /// ```
+    /// let term = Term::Constant(4, Some(String::from("fire")));
+    /// assert_eq!(Some(&String::from("fire")), term.damage_type());
+    /// ```
+    pub fn damage_type(&self) -> Option<&String> {
+        match self {
+            Term::Dice(dice_term) => dice_term.damage_type.as_ref(),
+            Term::Constant(_, damage_type) => damage_type.as_ref(),
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fragments: Vec<String> = self.fragments.iter().map(|x| x.to_string()).collect();
+        write!(f, "{}", fragments.join(","))
+    }
+}
+
+impl fmt::Display for Fragment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.on_miss {
+            write!(f, "{}:", MISS_PREFIX)?;
+        }
+        for (i, term) in self.terms.iter().enumerate() {
+            match (i, term) {
+                (0, Term::Constant(value, _)) => write!(f, "{}", value)?,
+                (_, Term::Constant(value, _)) => write!(f, "{:+}", value)?,
+                (0, Term::Dice(dice_term)) => write!(f, "{}", dice_term)?,
+                (_, Term::Dice(dice_term)) => write!(f, "+{}", dice_term)?,
+            }
+            if let Term::Constant(_, Some(damage_type)) = term {
+                write!(f, " {}", damage_type)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DiceTerm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}d{}", self.count, self.size)?;
+        for flag in &self.flags {
+            write!(f, "{}", flag)?;
+        }
+        if let Some(damage_type) = &self.damage_type {
+            write!(f, " {}", damage_type)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DiceFlag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiceFlag::Advantage => write!(f, "A"),
+            DiceFlag::DoubleAdvantage => write!(f, "AA"),
+            DiceFlag::Disadvantage => write!(f, "D"),
+            DiceFlag::KeepHighest(keep) => write!(f, "kh{}", keep),
+            DiceFlag::KeepLowest(keep) => write!(f, "kl{}", keep),
+            DiceFlag::Exploding => write!(f, "!"),
+            DiceFlag::Compounding => write!(f, "!!"),
+            DiceFlag::Reroll(threshold) => write!(f, "r{}", threshold),
+            DiceFlag::Floor(floor) => write!(f, "m{}", floor),
+            DiceFlag::CritRange(threshold) => write!(f, "c{}", threshold),
+            DiceFlag::Fatal(size) => write!(f, "~{}", size),
+            DiceFlag::Deadly(size) => write!(f, "^{}", size),
+        }
+    }
+}
+
+// endregion:
+
+// region: Tokenizer
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Number(i32),
+    Word,
+    Plus,
+    Minus,
+    Comma,
+    Tilde,
+    Caret,
+    Bang,
+    Colon,
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+    text: String,
+}
+
+/// Break a dice notation string into tokens, recording the position of each.
+///
+/// Digits are grouped into numbers and letters into words, so that 1d20AA is read as
+/// the tokens 1, d, 20, and AA. A word ends when the letters change case, so that flags
+/// can be written together, such as 1d20Ac19. Whitespace is skipped.
+///
+/// # Examples
+/// ```
+/// let tokens = tokenize("1d8+4")?;
+/// ```
+fn tokenize(notation: &str) -> Result<Vec<Token>, NotationError> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let chars: Vec<(usize, char)> = notation.char_indices().collect();
+
+    let mut i = 0;
+    while i < chars.len() {
+        let (position, c) = chars[i];
+
+        // Identify the extent of the token, grouping runs of digits or letters of the same case
+        let mut end = i + 1;
+        if c.is_ascii_digit() || c.is_ascii_alphabetic() {
+            while end < chars.len()
+                && chars[end].1.is_ascii_digit() == c.is_ascii_digit()
+                && chars[end].1.is_ascii_uppercase() == c.is_ascii_uppercase()
+                && (chars[end].1.is_ascii_digit() || chars[end].1.is_ascii_alphabetic())
+            {
+                end += 1;
+            }
+        }
+        let end_position = chars.get(end).map_or(notation.len(), |(p, _)| *p);
+        let text = &notation[position..end_position];
+
+        let kind = match c {
+            _ if c.is_whitespace() => {
+                i = end;
+                continue;
+            }
+            _ if c.is_ascii_digit() => match text.parse::<i32>() {
+                Ok(value) => TokenKind::Number(value),
+                _ => {
+                    return Err(NotationError::new(
+                        notation,
+                        position,
+                        text,
+                        "a number small enough to be represented",
+                    ))
+                }
+            },
+            _ if c.is_ascii_alphabetic() => TokenKind::Word,
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            ',' => TokenKind::Comma,
+            '~' => TokenKind::Tilde,
+            '^' => TokenKind::Caret,
+            '!' => TokenKind::Bang,
+            ':' => TokenKind::Colon,
+            _ => TokenKind::Unknown,
+        };
+
+        tokens.push(Token {
+            kind,
+            position,
+            text: text.to_string(),
+        });
+        i = end;
+    }
+
+    Ok(tokens)
+}
+
+// endregion:
+
+// region: Parser
+
+/// Return a description of the flags expected, if two dice flags cannot be given on the same term.
+///
+/// Advantage, disadvantage, and keep rules each choose which dice of a pool are kept, so only one
+/// may be given. Exploding dice add each additional roll as a new die, which cannot be kept from
+/// a pool, whereas compounding dice keep a single result per die. Fatal dice are rolled at a
+/// different size on a critical hit, so cannot also explode.
+fn incompatible_flags(first: &DiceFlag, second: &DiceFlag) -> Option<&'static str> {
+    let is_explosion = |f: &DiceFlag| matches!(f, DiceFlag::Exploding | DiceFlag::Compounding);
+    let is_pool = |f: &DiceFlag| {
+        matches!(
+            f,
+            DiceFlag::Advantage
+                | DiceFlag::DoubleAdvantage
+                | DiceFlag::Disadvantage
+                | DiceFlag::KeepHighest(_)
+                | DiceFlag::KeepLowest(_)
+        )
+    };
+
+    match (first, second) {
+        (a, b) if is_pool(a) && is_pool(b) => {
+            Some("only one of A, AA, D, khX, or klX per dice term")
+        }
+        (DiceFlag::Exploding, f) | (f, DiceFlag::Exploding) if is_pool(f) => {
+            Some("compounding '!!' rather than exploding '!' for dice kept from a pool")
+        }
+        (DiceFlag::Fatal(_), f) | (f, DiceFlag::Fatal(_)) if is_explosion(f) => {
+            Some("fatal dice which do not also explode or compound")
+        }
+        (_, _) => None,
+    }
+}
+
+/// A recursive descent parser over the tokens of a dice notation string
+struct Parser<'a> {
+    notation: &'a str,
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(notation: &'a str, tokens: Vec<Token>) -> Parser<'a> {
+        Parser {
+            notation,
+            tokens,
+            index: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    /// Report an error at the current token, or at the end of the notation if there are no tokens left.
+    fn error(&self, expected: &str) -> NotationError {
+        match self.peek() {
+            Some(token) => NotationError::new(self.notation, token.position, &token.text, expected),
+            None => NotationError::new(self.notation, self.notation.len(), "", expected),
+        }
+    }
+
+    /// expression := fragment (',' fragment)*
+    fn parse_expression(&mut self) -> Result<Expression, NotationError> {
+        let mut fragments = vec![self.parse_fragment()?];
+
+        while let Some(token) = self.advance() {
+            match token.kind {
+                TokenKind::Comma => fragments.push(self.parse_fragment()?),
+                _ => {
+                    self.index -= 1;
+                    return Err(self.error(EXPECTED_OPERATOR));
+                }
+            }
+        }
+
+        Ok(Expression { fragments })
+    }
+
+    /// fragment := ['miss' ':'] [sign] term (sign term)*
+    fn parse_fragment(&mut self) -> Result<Fragment, NotationError> {
+        let mut terms: Vec<Term> = Vec::new();
+
+        let on_miss = match (self.peek(), self.tokens.get(self.index + 1)) {
+            (Some(word), Some(colon))
+                if word.text == MISS_PREFIX && colon.kind == TokenKind::Colon =>
+            {
+                self.index += 2;
+                true
+            }
+            _ => false,
+        };
+
+        loop {
+            let sign = match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::Plus) => {
+                    self.advance();
+                    1
+                }
+                Some(TokenKind::Minus) => {
+                    self.advance();
+                    -1
+                }
+                _ if terms.is_empty() => 1,
+                _ => break,
+            };
+
+            let term = self.parse_term()?;
+            match (sign, term) {
+                (-1, Term::Dice(_)) => {
+                    // Step back to the minus sign, which is the source of the error
+                    self.index -= 1;
+                    while self.tokens[self.index].kind != TokenKind::Minus {
+                        self.index -= 1;
+                    }
+                    return Err(self.error("a constant after '-', as dice cannot be subtracted"));
+                }
+                (_, Term::Constant(value, damage_type)) => {
+                    terms.push(Term::Constant(sign * value, damage_type))
+                }
+                (_, dice_term) => terms.push(dice_term),
+            }
+        }
+
+        Ok(Fragment { terms, on_miss })
+    }
+
+    /// term := number ['d' number flag*] [damage_type]
+    fn parse_term(&mut self) -> Result<Term, NotationError> {
+        let count = match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Number(value)) => *value,
+            _ => return Err(self.error(EXPECTED_TERM)),
+        };
+        self.advance();
+
+        match self.peek() {
+            Some(token) if token.kind == TokenKind::Word && token.text == "d" => {
+                if count < 1 {
+                    self.index -= 1;
+                    return Err(self.error(EXPECTED_DICE_COUNT));
+                }
+                self.advance();
+            }
+            Some(token) if is_damage_type(token) => {
+                return Ok(Term::Constant(count, self.parse_damage_type()))
+            }
+            Some(token) if token.kind == TokenKind::Word => {
+                return Err(self
+                    .error("'d' followed by the die size (for example 1d8), or '+', '-', or ','"))
+            }
+            _ => return Ok(Term::Constant(count, None)),
+        }
+
+        let size = self.parse_die_size(EXPECTED_DIE_SIZE)?;
+        let flags = self.parse_flags(count, size)?;
+        let damage_type = self.parse_damage_type();
+
+        Ok(Term::Dice(DiceTerm {
+            count,
+            size,
+            flags,
+            damage_type,
+        }))
+    }
+
+    /// damage_type := lowercase word of at least three letters
+    fn parse_damage_type(&mut self) -> Option<String> {
+        match self.peek() {
+            Some(token) if is_damage_type(token) => self.advance().map(|t| t.text),
+            _ => None,
+        }
+    }
+
+    /// flag := 'A' | 'AA' | 'D' | 'kh' number | 'kl' number | '!' | '!!' | 'r' number | 'm' number
+    ///     | 'c' number | '~' number | '^' number
+    fn parse_flags(&mut self, count: i32, size: i32) -> Result<Vec<DiceFlag>, NotationError> {
+        let mut flags: Vec<DiceFlag> = Vec::new();
+
+        while let Some(token) = self.peek().cloned() {
+            let flag = match (&token.kind, token.text.as_str()) {
+                (TokenKind::Word, "A") => DiceFlag::Advantage,
+                (TokenKind::Word, "AA") => DiceFlag::DoubleAdvantage,
+                (TokenKind::Word, "D") => DiceFlag::Disadvantage,
+                (TokenKind::Word, "kh") => {
+                    self.advance();
+                    DiceFlag::KeepHighest(self.parse_keep_count(count)?)
+                }
+                (TokenKind::Word, "kl") => {
+                    self.advance();
+                    DiceFlag::KeepLowest(self.parse_keep_count(count)?)
+                }
+                (TokenKind::Word, "r") => {
+                    self.advance();
+                    DiceFlag::Reroll(self.parse_reroll_threshold(size)?)
+                }
+                (TokenKind::Word, "m") => {
+                    self.advance();
+                    DiceFlag::Floor(self.parse_face_floor(size)?)
+                }
+                (TokenKind::Word, "c") => {
+                    self.advance();
+                    let expected =
+                        "the lowest natural roll which is a critical, from 2 to 20 (for example 1d20c19)";
+                    let threshold = self.parse_number(expected)?;
+                    if !(2..=20).contains(&threshold) {
+                        self.index -= 1;
+                        return Err(self.error(expected));
+                    }
+                    DiceFlag::CritRange(threshold)
+                }
+                (TokenKind::Bang, _) => {
+                    self.advance();
+                    // A second, adjacent '!' marks the dice as compounding rather than exploding
+                    match self.peek() {
+                        Some(next)
+                            if next.kind == TokenKind::Bang
+                                && next.position == token.position + 1 =>
+                        {
+                            self.advance();
+                            DiceFlag::Compounding
+                        }
+                        _ => DiceFlag::Exploding,
+                    }
+                }
+                (TokenKind::Tilde, _) => {
+                    self.advance();
+                    DiceFlag::Fatal(self.parse_die_size(
+                        "the die size ≥ 1 of the fatal trait (for example 1d6~10)",
+                    )?)
+                }
+                (TokenKind::Caret, _) => {
+                    self.advance();
+                    DiceFlag::Deadly(self.parse_die_size(
+                        "the die size ≥ 1 of the deadly trait (for example 1d8^10)",
+                    )?)
+                }
+                (TokenKind::Word, _) if is_damage_type(&token) => break,
+                (TokenKind::Word, _) => return Err(self.error(EXPECTED_FLAG)),
+                (_, _) => break,
+            };
+
+            // Each kind of flag may only be given once per term, and dice cannot both explode and compound
+            let is_explosion =
+                |f: &DiceFlag| matches!(f, DiceFlag::Exploding | DiceFlag::Compounding);
+            if flags.iter().any(|f| {
+                std::mem::discriminant(f) == std::mem::discriminant(&flag)
+                    || (is_explosion(f) && is_explosion(&flag))
+            }) {
+                return Err(NotationError::new(
+                    self.notation,
+                    token.position,
+                    &flag.to_string(),
+                    "each dice flag to be given only once per dice term",
+                ));
+            }
+
+            if let Some(expected) = flags.iter().find_map(|f| incompatible_flags(f, &flag)) {
+                return Err(NotationError::new(
+                    self.notation,
+                    token.position,
+                    &flag.to_string(),
+                    expected,
+                ));
+            }
+
+            if matches!(
+                flag,
+                DiceFlag::Advantage | DiceFlag::DoubleAdvantage | DiceFlag::Disadvantage
+            ) {
+                self.advance();
+            }
+            flags.push(flag);
+        }
+
+        Ok(flags)
+    }
+
+    /// Read the number of dice kept from a pool, which must be between one and the number rolled.
+    fn parse_keep_count(&mut self, count: i32) -> Result<i32, NotationError> {
+        let expected = format!(
+            "the number of dice to keep, from 1 to {} (for example 4d6kh3)",
+            count
+        );
+        let keep = self.parse_number(&expected)?;
+
+        if keep < 1 || keep > count {
+            self.index -= 1;
+            return Err(self.error(&expected));
+        }
+        Ok(keep)
+    }
+
+    /// Read the highest face to reroll, which must leave at least the top face of the die.
+    fn parse_reroll_threshold(&mut self, size: i32) -> Result<i32, NotationError> {
+        let expected = format!(
+            "the highest face to reroll, from 1 to {} (for example 2d6r2)",
+            size - 1
+        );
+        let threshold = self.parse_number(&expected)?;
+
+        if threshold < 1 || threshold >= size {
+            self.index -= 1;
+            return Err(self.error(&expected));
+        }
+        Ok(threshold)
+    }
+
+    /// Read the lowest value of each die, which must be one of the faces of the die.
+    fn parse_face_floor(&mut self, size: i32) -> Result<i32, NotationError> {
+        let expected = format!(
+            "the lowest value of each die, from 1 to {} (for example 2d6m3)",
+            size
+        );
+        let floor = self.parse_number(&expected)?;
+
+        if floor < 1 || floor > size {
+            self.index -= 1;
+            return Err(self.error(&expected));
+        }
+        Ok(floor)
+    }
+
+    /// Read the size of a die, which must have at least one face.
+    fn parse_die_size(&mut self, expected: &str) -> Result<i32, NotationError> {
+        let size = self.parse_number(expected)?;
+
+        if size < 1 {
+            self.index -= 1;
+            return Err(self.error(expected));
+        }
+        Ok(size)
+    }
+
+    fn parse_number(&mut self, expected: &str) -> Result<i32, NotationError> {
+        match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::Number(value)) => {
+                let value = *value;
+                self.advance();
+                Ok(value)
+            }
+            _ => Err(self.error(expected)),
+        }
+    }
+}
+
+/// Test whether a token names a damage type, rather than a dice flag.
+///
+/// Damage types are lowercase words of at least three letters, so that they cannot be
+/// confused with the lowercase dice flags (d, kh, kl, r, m, and c).
+fn is_damage_type(token: &Token) -> bool {
+    token.kind == TokenKind::Word
+        && token.text.len() >= 3
+        && token.text.chars().all(|c| c.is_ascii_lowercase())
+}
+
+// endregion:
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dice(count: i32, size: i32, flags: Vec<DiceFlag>) -> Term {
+        Term::Dice(DiceTerm {
+            count,
+            size,
+            flags,
+            damage_type: None,
+        })
+    }
+
+    fn typed_dice(count: i32, size: i32, damage_type: &str) -> Term {
+        Term::Dice(DiceTerm {
+            count,
+            size,
+            flags: vec![],
+            damage_type: Some(damage_type.to_string()),
+        })
+    }
+
+    fn single_fragment(terms: Vec<Term>) -> Expression {
+        Expression {
+            fragments: vec![Fragment {
+                terms,
+                on_miss: false,
+            }],
+        }
+    }
+
+    // region: NotationError tests
+
+    #[test]
+    fn test_notation_error_display() {
+        let error = NotationError::new("1d8+4x", 5, "x", "a static modifier");
+        assert_eq!(
+            "Unable to parse notation '1d8+4x': found 'x' at position 5, expected a static modifier!",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn test_notation_error_display_empty() {
+        let error = NotationError::new("1d8,", 4, "", "a dice term");
+        assert_eq!(
+            "Unable to parse notation '1d8,': found nothing at position 4, expected a dice term!",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn test_notation_error_with_offset() {
+        let exp_error = NotationError::new("crit:2d8x", 8, "x", "a static modifier");
+
+        let obs_error =
+            NotationError::new("2d8x", 3, "x", "a static modifier").with_offset("crit:2d8x", 5);
+        assert_eq!(exp_error, obs_error);
+    }
+
+    // endregion:
+
+    // region: tokenize() tests
+
+    #[test]
+    fn test_tokenize() {
+        let exp_kinds = vec![
+            TokenKind::Number(1),
+            TokenKind::Word,
+            TokenKind::Number(20),
+            TokenKind::Word,
+            TokenKind::Plus,
+            TokenKind::Number(5),
+            TokenKind::Comma,
+            TokenKind::Number(2),
+            TokenKind::Word,
+            TokenKind::Number(6),
+            TokenKind::Tilde,
+            TokenKind::Number(10),
+        ];
+
+        let obs_tokens = tokenize("1d20AA+5,2d6~10").unwrap();
+        let obs_kinds: Vec<TokenKind> = obs_tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(exp_kinds, obs_kinds);
+
+        assert_eq!("AA", obs_tokens[3].text);
+        assert_eq!(4, obs_tokens[3].position);
+    }
+
+    #[test]
+    fn test_tokenize_whitespace() {
+        let obs_tokens = tokenize("1d8 + 4").unwrap();
+
+        assert_eq!(5, obs_tokens.len());
+        assert_eq!(4, obs_tokens[3].position);
+        assert_eq!(6, obs_tokens[4].position);
+    }
+
+    #[test]
+    fn test_tokenize_case_change() {
+        // Uppercase and lowercase flags written together are read as separate words
+        let obs_tokens = tokenize("1d20AAc19").unwrap();
+        let obs_text: Vec<&str> = obs_tokens.iter().map(|t| t.text.as_str()).collect();
+
+        assert_eq!(vec!["1", "d", "20", "AA", "c", "19"], obs_text);
+    }
+
+    #[test]
+    fn test_tokenize_overflow() {
+        let obs_result = tokenize("1d99999999999");
+        assert_eq!(2, obs_result.unwrap_err().position);
+    }
+
+    // endregion:
+
+    // region: Expression::parse() tests
+
+    #[test]
+    fn test_parse_dice() {
+        let exp_expr = single_fragment(vec![dice(1, 8, vec![])]);
+        assert_eq!(exp_expr, Expression::parse("1d8").unwrap());
+    }
+
+    #[test]
+    fn test_parse_modifiers() {
+        let exp_expr = single_fragment(vec![
+            dice(1, 20, vec![]),
+            Term::Constant(15, None),
+            Term::Constant(-5, None),
+            Term::Constant(2, None),
+        ]);
+        assert_eq!(exp_expr, Expression::parse("1d20+15-5+2").unwrap());
+    }
+
+    #[test]
+    fn test_parse_fragments() {
+        // Modifiers remain attached to the fragment in which they appear
+        let exp_expr = Expression {
+            fragments: vec![
+                Fragment {
+                    terms: vec![dice(1, 20, vec![]), Term::Constant(5, None)],
+                    on_miss: false,
+                },
+                Fragment {
+                    terms: vec![dice(1, 6, vec![]), Term::Constant(-1, None)],
+                    on_miss: false,
+                },
+            ],
+        };
+        assert_eq!(exp_expr, Expression::parse("1d20+5,1d6-1").unwrap());
+    }
+
+    #[test]
+    fn test_parse_added_dice() {
+        let exp_expr = single_fragment(vec![
+            dice(1, 8, vec![]),
+            dice(1, 6, vec![]),
+            Term::Constant(4, None),
+        ]);
+        assert_eq!(exp_expr, Expression::parse("1d8+1d6+4").unwrap());
+    }
+
+    #[test]
+    fn test_parse_constant() {
+        let exp_expr = single_fragment(vec![Term::Constant(-2, None)]);
+        assert_eq!(exp_expr, Expression::parse("-2").unwrap());
+    }
+
+    #[test]
+    fn test_parse_flags() {
+        let exp_expr = Expression {
+            fragments: vec![
+                Fragment {
+                    terms: vec![dice(1, 20, vec![DiceFlag::Advantage])],
+                    on_miss: false,
+                },
+                Fragment {
+                    terms: vec![dice(1, 20, vec![DiceFlag::DoubleAdvantage])],
+                    on_miss: false,
+                },
+                Fragment {
+                    terms: vec![dice(1, 20, vec![DiceFlag::Disadvantage])],
+                    on_miss: false,
+                },
+                Fragment {
+                    terms: vec![dice(1, 6, vec![DiceFlag::Advantage, DiceFlag::Fatal(10)])],
+                    on_miss: false,
+                },
+                Fragment {
+                    terms: vec![
+                        dice(2, 8, vec![DiceFlag::Deadly(10)]),
+                        Term::Constant(4, None),
+                    ],
+                    on_miss: false,
+                },
+            ],
+        };
+        assert_eq!(
+            exp_expr,
+            Expression::parse("1d20A,1d20AA,1d20D,1d6A~10,2d8^10+4").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_exploding() {
+        let exp_expr = Expression {
+            fragments: vec![
+                Fragment {
+                    terms: vec![
+                        dice(1, 6, vec![DiceFlag::Exploding]),
+                        Term::Constant(2, None),
+                    ],
+                    on_miss: false,
+                },
+                Fragment {
+                    terms: vec![dice(2, 10, vec![DiceFlag::Compounding])],
+                    on_miss: false,
+                },
+                Fragment {
+                    terms: vec![
+                        dice(1, 6, vec![DiceFlag::Exploding]),
+                        Term::Constant(2, None),
+                    ],
+                    on_miss: false,
+                },
+            ],
+        };
+        assert_eq!(
+            exp_expr,
+            Expression::parse("1d6!+2,2d10!!,1d6 ! + 2").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_reroll() {
+        let exp_expr = Expression {
+            fragments: vec![
+                Fragment {
+                    terms: vec![
+                        dice(2, 6, vec![DiceFlag::Reroll(2)]),
+                        Term::Constant(5, None),
+                    ],
+                    on_miss: false,
+                },
+                Fragment {
+                    terms: vec![dice(1, 20, vec![DiceFlag::Reroll(1), DiceFlag::Advantage])],
+                    on_miss: false,
+                },
+            ],
+        };
+        assert_eq!(exp_expr, Expression::parse("2d6r2+5,1d20r1A").unwrap());
+    }
+
+    #[test]
+    fn test_parse_missing_reroll_threshold() {
+        let exp_error = NotationError::new(
+            "2d6r+5",
+            4,
+            "+",
+            "the highest face to reroll, from 1 to 5 (for example 2d6r2)",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("2d6r+5"));
+    }
+
+    #[test]
+    fn test_parse_reroll_out_of_range() {
+        let exp_error = NotationError::new(
+            "1d20r0",
+            5,
+            "0",
+            "the highest face to reroll, from 1 to 19 (for example 2d6r2)",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("1d20r0"));
+
+        // A threshold of the die size would reroll every face
+        let exp_error = NotationError::new(
+            "1d6r6",
+            4,
+            "6",
+            "the highest face to reroll, from 1 to 5 (for example 2d6r2)",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("1d6r6"));
+    }
+
+    #[test]
+    fn test_parse_floor() {
+        let exp_expr = single_fragment(vec![dice(
+            2,
+            6,
+            vec![DiceFlag::Reroll(1), DiceFlag::Floor(3)],
+        )]);
+        assert_eq!(exp_expr, Expression::parse("2d6r1m3").unwrap());
+    }
+
+    #[test]
+    fn test_parse_floor_out_of_range() {
+        let exp_error = NotationError::new(
+            "1d6m9",
+            4,
+            "9",
+            "the lowest value of each die, from 1 to 6 (for example 2d6m3)",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("1d6m9"));
+
+        let exp_error = NotationError::new(
+            "1d6m0",
+            4,
+            "0",
+            "the lowest value of each die, from 1 to 6 (for example 2d6m3)",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("1d6m0"));
+
+        // A floor of the die size is allowed, as it fixes every roll at the maximum
+        assert!(Expression::parse("1d6m6").is_ok());
+    }
+
+    #[test]
+    fn test_parse_keep() {
+        let exp_expr = Expression {
+            fragments: vec![
+                Fragment {
+                    terms: vec![dice(4, 6, vec![DiceFlag::KeepHighest(3)])],
+                    on_miss: false,
+                },
+                Fragment {
+                    terms: vec![
+                        dice(2, 20, vec![DiceFlag::KeepLowest(1)]),
+                        Term::Constant(5, None),
+                    ],
+                    on_miss: false,
+                },
+            ],
+        };
+        assert_eq!(exp_expr, Expression::parse("4d6kh3,2d20kl1+5").unwrap());
+    }
+
+    #[test]
+    fn test_parse_keep_too_many() {
+        let exp_error = NotationError::new(
+            "2d20kh3+5",
+            6,
+            "3",
+            "the number of dice to keep, from 1 to 2 (for example 4d6kh3)",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("2d20kh3+5"));
+    }
+
+    #[test]
+    fn test_parse_keep_missing_count() {
+        let exp_error = NotationError::new(
+            "4d6kh",
+            5,
+            "",
+            "the number of dice to keep, from 1 to 4 (for example 4d6kh3)",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("4d6kh"));
+    }
+
+    #[test]
+    fn test_parse_crit_range() {
+        let exp_expr = single_fragment(vec![
+            dice(1, 20, vec![DiceFlag::Advantage, DiceFlag::CritRange(19)]),
+            Term::Constant(8, None),
+        ]);
+        assert_eq!(exp_expr, Expression::parse("1d20Ac19+8").unwrap());
+    }
+
+    #[test]
+    fn test_parse_crit_range_invalid() {
+        let exp_error = NotationError::new(
+            "1d20c21+8",
+            5,
+            "21",
+            "the lowest natural roll which is a critical, from 2 to 20 (for example 1d20c19)",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("1d20c21+8"));
+    }
+
+    #[test]
+    fn test_parse_repeated_explosion() {
+        // Separated '!' characters are read as two exploding flags, rather than compounding
+        let exp_error = NotationError::new(
+            "1d6! !",
+            5,
+            "!",
+            "each dice flag to be given only once per dice term",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("1d6! !"));
+
+        let exp_error = NotationError::new(
+            "1d6!!!",
+            5,
+            "!",
+            "each dice flag to be given only once per dice term",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("1d6!!!"));
+    }
+
+    #[test]
+    fn test_parse_whitespace() {
+        let exp_expr = single_fragment(vec![dice(1, 8, vec![]), Term::Constant(4, None)]);
+        assert_eq!(exp_expr, Expression::parse("1d8 + 4").unwrap());
+    }
+
+    #[test]
+    fn test_parse_damage_types() {
+        let exp_expr = single_fragment(vec![
+            typed_dice(1, 8, "slashing"),
+            typed_dice(1, 6, "fire"),
+            Term::Constant(4, None),
+        ]);
+        assert_eq!(
+            exp_expr,
+            Expression::parse("1d8 slashing + 1d6 fire + 4").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_damage_type_flags() {
+        // Damage types follow the dice flags, and may be given on constants
+        let exp_expr = single_fragment(vec![
+            Term::Dice(DiceTerm {
+                count: 2,
+                size: 8,
+                flags: vec![DiceFlag::Deadly(10), DiceFlag::Reroll(1)],
+                damage_type: Some("piercing".to_string()),
+            }),
+            Term::Constant(-1, Some("piercing".to_string())),
+        ]);
+        assert_eq!(
+            exp_expr,
+            Expression::parse("2d8^10r1piercing-1 piercing").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_short_damage_type() {
+        // Words of fewer than three letters are read as dice flags
+        let exp_error = NotationError::new("1d8 fi", 4, "fi", EXPECTED_FLAG);
+        assert_eq!(Err(exp_error), Expression::parse("1d8 fi"));
+    }
+
+    #[test]
+    fn test_parse_miss_fragment() {
+        let exp_expr = Expression {
+            fragments: vec![
+                Fragment {
+                    terms: vec![dice(2, 6, vec![]), Term::Constant(4, None)],
+                    on_miss: false,
+                },
+                Fragment {
+                    terms: vec![Term::Constant(4, None)],
+                    on_miss: true,
+                },
+            ],
+        };
+        assert_eq!(exp_expr, Expression::parse("2d6+4,miss:4").unwrap());
+    }
+
+    #[test]
+    fn test_parse_miss_prefix_mid_fragment() {
+        // The miss prefix applies to a whole fragment, so cannot follow a term
+        let exp_error = NotationError::new("1d8+miss:4", 4, "miss", EXPECTED_TERM);
+        assert_eq!(Err(exp_error), Expression::parse("1d8+miss:4"));
+    }
+
+    #[test]
+    fn test_parse_trailing_text() {
+        let exp_error = NotationError::new(
+            "1d8+4x",
+            5,
+            "x",
+            "'d' followed by the die size (for example 1d8), or '+', '-', or ','",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("1d8+4x"));
+    }
+
+    #[test]
+    fn test_parse_unknown_flag() {
+        let exp_error = NotationError::new("1d8x+4", 3, "x", EXPECTED_FLAG);
+        assert_eq!(Err(exp_error), Expression::parse("1d8x+4"));
+    }
+
+    #[test]
+    fn test_parse_missing_dice_count() {
+        let exp_error = NotationError::new("d8+4", 0, "d", EXPECTED_TERM);
+        assert_eq!(Err(exp_error), Expression::parse("d8+4"));
+    }
+
+    #[test]
+    fn test_parse_invalid_die() {
+        let exp_error = NotationError::new(
+            "2f6~10+4",
+            1,
+            "f",
+            "'d' followed by the die size (for example 1d8), or '+', '-', or ','",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("2f6~10+4"));
+    }
+
+    #[test]
+    fn test_parse_missing_die_size() {
+        let exp_error = NotationError::new("1d+4", 2, "+", EXPECTED_DIE_SIZE);
+        assert_eq!(Err(exp_error), Expression::parse("1d+4"));
+    }
+
+    #[test]
+    fn test_parse_zero_die_size() {
+        let exp_error = NotationError::new("1d0+4", 2, "0", EXPECTED_DIE_SIZE);
+        assert_eq!(Err(exp_error), Expression::parse("1d0+4"));
+
+        let exp_error = NotationError::new(
+            "1d6~0",
+            4,
+            "0",
+            "the die size ≥ 1 of the fatal trait (for example 1d6~10)",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("1d6~0"));
+    }
+
+    #[test]
+    fn test_parse_zero_dice_count() {
+        let exp_error = NotationError::new("0d6+3", 0, "0", EXPECTED_DICE_COUNT);
+        assert_eq!(Err(exp_error), Expression::parse("0d6+3"));
+
+        // A constant of zero is not a dice count
+        assert!(Expression::parse("1d6+0").is_ok());
+    }
+
+    #[test]
+    fn test_parse_missing_fatal_size() {
+        let exp_error = NotationError::new(
+            "1d6~",
+            4,
+            "",
+            "the die size ≥ 1 of the fatal trait (for example 1d6~10)",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("1d6~"));
+    }
+
+    #[test]
+    fn test_parse_fatal_exploding() {
+        let expected = "fatal dice which do not also explode or compound";
+
+        let exp_error = NotationError::new("1d6~10!", 6, "!", expected);
+        assert_eq!(Err(exp_error), Expression::parse("1d6~10!"));
+
+        let exp_error = NotationError::new("1d6!!~10", 5, "~10", expected);
+        assert_eq!(Err(exp_error), Expression::parse("1d6!!~10"));
+    }
+
+    #[test]
+    fn test_parse_conflicting_pools() {
+        let expected = "only one of A, AA, D, khX, or klX per dice term";
+
+        let exp_error = NotationError::new("1d20A kh1", 6, "kh1", expected);
+        assert_eq!(Err(exp_error), Expression::parse("1d20A kh1"));
+
+        let exp_error = NotationError::new("1d20kh1A", 7, "A", expected);
+        assert_eq!(Err(exp_error), Expression::parse("1d20kh1A"));
+    }
+
+    #[test]
+    fn test_parse_exploding_pool() {
+        let expected = "compounding '!!' rather than exploding '!' for dice kept from a pool";
+
+        let exp_error = NotationError::new("4d6kh3!", 6, "!", expected);
+        assert_eq!(Err(exp_error), Expression::parse("4d6kh3!"));
+
+        let exp_error = NotationError::new("4d6!kh3", 4, "kh3", expected);
+        assert_eq!(Err(exp_error), Expression::parse("4d6!kh3"));
+    }
+
+    #[test]
+    fn test_parse_compounding_pool() {
+        // Compounding dice may be kept from a pool, with the flags given in either order
+        let exp_expr = single_fragment(vec![dice(
+            4,
+            6,
+            vec![DiceFlag::KeepHighest(3), DiceFlag::Compounding],
+        )]);
+        assert_eq!(exp_expr, Expression::parse("4d6kh3!!").unwrap());
+
+        let exp_expr = single_fragment(vec![dice(
+            4,
+            6,
+            vec![DiceFlag::Compounding, DiceFlag::KeepHighest(3)],
+        )]);
+        assert_eq!(exp_expr, Expression::parse("4d6!!kh3").unwrap());
+    }
+
+    #[test]
+    fn test_parse_repeated_flag() {
+        let exp_error = NotationError::new(
+            "1d6~10~12",
+            6,
+            "~12",
+            "each dice flag to be given only once per dice term",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("1d6~10~12"));
+    }
+
+    #[test]
+    fn test_parse_subtracted_dice() {
+        let exp_error = NotationError::new(
+            "1d8-1d4",
+            3,
+            "-",
+            "a constant after '-', as dice cannot be subtracted",
+        );
+        assert_eq!(Err(exp_error), Expression::parse("1d8-1d4"));
+    }
+
+    #[test]
+    fn test_parse_incomplete_modifier() {
+        let exp_error = NotationError::new("1d8+", 4, "", EXPECTED_TERM);
+        assert_eq!(Err(exp_error), Expression::parse("1d8+"));
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        let exp_error = NotationError::new("", 0, "", EXPECTED_TERM);
+        assert_eq!(Err(exp_error), Expression::parse(""));
+    }
+
+    #[test]
+    fn test_parse_empty_fragment() {
+        let exp_error = NotationError::new("1d8,,1d6", 4, ",", EXPECTED_TERM);
+        assert_eq!(Err(exp_error), Expression::parse("1d8,,1d6"));
+    }
+
+    // endregion:
+
+    // region: Display tests
+
+    #[test]
+    fn test_display_round_trip() {
+        let notations = [
+            "1d8",
+            "1d20+15-5+2",
+            "1d4,2d6D+7,1d6~12",
+            "1d20AA-1",
+            "1d6A~10,2d4+4",
+            "2d8^10+4",
+            "1d8+1d6+4",
+            "1d6!+2,2d10!!",
+            "2d6r2+5,1d20r1A",
+            "2d6m3+4,1d8r1m2",
+            "4d6kh3,2d20kl1+5,3d20kh1r1",
+            "1d20c19+8,2d20kh1c18-2",
+            "2d6r2+5,1d20r1A",
+            "-2",
+            "1d8 slashing+1d6 fire+4",
+            "2d6! cold-1 cold,3 force",
+            "1d8+4,miss:4",
+            "2d6 fire,miss:1d6 fire",
+        ];
+
+        for notation in notations {
+            let expression = Expression::parse(notation).unwrap();
+            assert_eq!(notation, expression.to_string());
+            assert_eq!(
+                expression,
+                Expression::parse(&expression.to_string()).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_display_normalised() {
+        // Redundant signs and whitespace are not preserved
+        let expression = Expression::parse("+1d8 + 4").unwrap();
+        assert_eq!("1d8+4", expression.to_string());
+    }
+
+    // endregion:
+}
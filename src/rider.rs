@@ -0,0 +1,339 @@
+use std::collections::BTreeSet;
+
+use crate::damage::TypedDamage;
+use crate::distribution::Distribution;
+use crate::notation::NotationError;
+use crate::roll_collection::RollCollection;
+use crate::{HitResult, RiderTrigger, Ruleset};
+
+/// A representation of damage applied at most once per turn, such as Sneak Attack
+///
+/// A persistent rider, such as a bleed effect, is not dealt with the attack which
+/// triggers it. Instead the target takes the damage at the end of each round, until the
+/// effect ends.
+#[derive(Debug, PartialEq)]
+pub struct Rider {
+    damage_collection: RollCollection,
+    trigger: RiderTrigger,
+    persistent: bool,
+}
+
+impl Rider {
+    /// Creates a new Rider representation of once-per-turn damage.
+    ///
+    /// # Examples
+    /// ```
+    /// let damage_collection = RollCollection::parse_user_input("2d6", Ruleset::DND5e)?;
+    /// let rider = Rider::new(damage_collection, RiderTrigger::FirstHit);
+    /// ```
+    pub fn new(damage_collection: RollCollection, trigger: RiderTrigger) -> Rider {
+        Rider {
+            damage_collection,
+            trigger,
+            persistent: false,
+        }
+    }
+
+    /// Set whether the rider deals persistent damage, rather than damage on the attack.
+    ///
+    /// # Examples
+    /// ```
+    /// let damage_collection = RollCollection::parse_user_input("1d6 bleed", Ruleset::PF2e)?;
+    /// let rider = Rider::new(damage_collection, RiderTrigger::FirstCrit).set_persistent(true);
+    /// ```
+    pub fn set_persistent(mut self, persistent: bool) -> Self {
+        self.persistent = persistent;
+        self
+    }
+
+    /// Take an input string from the user and parse into a Rider.
+    ///
+    /// Accepts a damage notation string, optionally prefixed with the trigger for the
+    /// rider. Riders prefixed with `crit:` apply to the first critical hit in a turn,
+    /// while those with the `hit:` prefix (or no prefix) apply to the first hit of any
+    /// kind. The `persistent:` prefix, which can be combined with a trigger, makes the
+    /// rider deal persistent damage. Errors in the damage notation are reported against
+    /// the full input string.
+    ///
+    /// # Examples
+    /// ```
+    /// // Sneak Attack, on the first hit in a turn
+    /// let rider = Rider::parse_user_input("3d6", Ruleset::DND5e)?;
+    /// let rider = Rider::parse_user_input("hit:3d6", Ruleset::DND5e)?;
+    ///
+    /// // Divine Smite, saved for the first critical hit in a turn
+    /// let rider = Rider::parse_user_input("crit:2d8", Ruleset::DND5e)?;
+    ///
+    /// // Persistent bleed damage from a critical hit
+    /// let rider = Rider::parse_user_input("crit:persistent:1d6 bleed", Ruleset::PF2e)?;
+    /// ```
+    pub fn parse_user_input(notation: &str, rule_mode: Ruleset) -> Result<Rider, NotationError> {
+        let mut trigger = RiderTrigger::FirstHit;
+        let mut persistent = false;
+        let mut damage_notation = notation;
+        while let Some((prefix, remainder)) = damage_notation.split_once(':') {
+            match prefix {
+                "crit" => trigger = RiderTrigger::FirstCrit,
+                "hit" => trigger = RiderTrigger::FirstHit,
+                "persistent" => persistent = true,
+                _ => break,
+            }
+            damage_notation = remainder;
+        }
+
+        let prefix_length = notation.len() - damage_notation.len();
+        let damage_collection = RollCollection::parse_user_input(damage_notation, rule_mode)
+            .map_err(|e| e.with_offset(notation, prefix_length))?;
+
+        Ok(Rider::new(damage_collection, trigger).set_persistent(persistent))
+    }
+
+    /// Test whether the rider deals persistent damage.
+    ///
+    /// # Examples
+    /// ```
+    /// let rider = Rider::parse_user_input("persistent:1d6 fire", Ruleset::PF2e)?;
+    /// assert!(rider.is_persistent());
+    /// ```
+    pub fn is_persistent(&self) -> bool {
+        self.persistent
+    }
+
+    /// Return the damage type by which persistent damage from the rider is tracked.
+    ///
+    /// This is the first damage type dealt by the rider, or None if all of its damage
+    /// is untyped.
+    ///
+    /// # Examples
+    /// ```
+    /// let rider = Rider::parse_user_input("persistent:1d6 fire", Ruleset::PF2e)?;
+    /// assert_eq!(Some(String::from("fire")), rider.persistent_type());
+    /// ```
+    pub fn persistent_type(&self) -> Option<String> {
+        self.damage_types().into_iter().flatten().next()
+    }
+
+    /// Test whether the rider is applied by an attack with the specified hit result.
+    ///
+    /// # Examples
+    /// ```
+    /// let rider = Rider::parse_user_input("crit:2d8", Ruleset::DND5e)?;
+    ///
+    /// assert!(rider.triggers_on(&HitResult::CriticalHit));
+    /// assert!(!rider.triggers_on(&HitResult::Hit));
+    /// ```
+    pub fn triggers_on(&self, hit_result: &HitResult) -> bool {
+        match (&self.trigger, hit_result) {
+            (RiderTrigger::FirstHit, HitResult::Hit | HitResult::CriticalHit) => true,
+            (RiderTrigger::FirstCrit, HitResult::CriticalHit) => true,
+            (_, _) => false,
+        }
+    }
+
+    /// Roll the rider damage for the attack which triggered it.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut rider = Rider::parse_user_input("2d6", Ruleset::DND5e)?;
+    /// let result = rider.roll_damage_result(&HitResult::CriticalHit);
+    /// ```
+    pub fn roll_damage_result(&mut self, hit_result: &HitResult) -> TypedDamage {
+        self.damage_collection.roll_damage_result(hit_result)
+    }
+
+    /// Return every damage type dealt by the rider, including None for untyped damage.
+    ///
+    /// # Examples
+    /// ```
+    /// let rider = Rider::parse_user_input("2d6 precision", Ruleset::PF2e)?;
+    /// let damage_types = rider.damage_types();
+    /// ```
+    pub fn damage_types(&self) -> BTreeSet<Option<String>> {
+        self.damage_collection.damage_types()
+    }
+
+    /// Return the exact damage distribution of the rider for the attack which triggered it.
+    ///
+    /// # Examples
+    /// ```
+    /// let rider = Rider::parse_user_input("2d6", Ruleset::DND5e)?;
+    /// let dist = rider.damage_distribution(&HitResult::Hit);
+    /// ```
+    pub fn damage_distribution(&self, hit_result: &HitResult) -> Distribution {
+        self.damage_collection.damage_distribution(hit_result)
+    }
+
+    /// Return the exact distribution of the damage of a single type dealt by the rider.
+    ///
+    /// # Examples
+    /// ```
+    /// let rider = Rider::parse_user_input("2d6 precision", Ruleset::PF2e)?;
+    /// let dist = rider.type_damage_distribution(&HitResult::Hit, &Some(String::from("precision")));
+    /// ```
+    pub fn type_damage_distribution(
+        &self,
+        hit_result: &HitResult,
+        damage_type: &Option<String>,
+    ) -> Distribution {
+        self.damage_collection
+            .type_damage_distribution(hit_result, damage_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dice::DiceBuilder;
+
+    #[test]
+    fn test_constructor() {
+        let exp_rider = Rider {
+            damage_collection: RollCollection::parse_user_input("2d6", Ruleset::DND5e).unwrap(),
+            trigger: RiderTrigger::FirstCrit,
+            persistent: false,
+        };
+
+        let obs_rider = Rider::new(
+            RollCollection::parse_user_input("2d6", Ruleset::DND5e).unwrap(),
+            RiderTrigger::FirstCrit,
+        );
+        assert_eq!(exp_rider, obs_rider);
+    }
+
+    // region: parse_user_input() tests
+
+    #[test]
+    fn test_parse_user_input_default() {
+        let exp_rider = Rider::new(
+            RollCollection::parse_user_input("3d6", Ruleset::DND5e).unwrap(),
+            RiderTrigger::FirstHit,
+        );
+
+        let obs_rider = Rider::parse_user_input("3d6", Ruleset::DND5e).unwrap();
+        assert_eq!(exp_rider, obs_rider);
+    }
+
+    #[test]
+    fn test_parse_user_input_hit() {
+        let exp_rider = Rider::new(
+            RollCollection::parse_user_input("3d6", Ruleset::DND5e).unwrap(),
+            RiderTrigger::FirstHit,
+        );
+
+        let obs_rider = Rider::parse_user_input("hit:3d6", Ruleset::DND5e).unwrap();
+        assert_eq!(exp_rider, obs_rider);
+    }
+
+    #[test]
+    fn test_parse_user_input_crit() {
+        let exp_rider = Rider::new(
+            RollCollection::parse_user_input("2d8+1", Ruleset::PF2e).unwrap(),
+            RiderTrigger::FirstCrit,
+        );
+
+        let obs_rider = Rider::parse_user_input("crit:2d8+1", Ruleset::PF2e).unwrap();
+        assert_eq!(exp_rider, obs_rider);
+    }
+
+    #[test]
+    fn test_parse_user_input_persistent() {
+        let exp_rider = Rider::new(
+            RollCollection::parse_user_input("1d6 bleed", Ruleset::PF2e).unwrap(),
+            RiderTrigger::FirstCrit,
+        )
+        .set_persistent(true);
+
+        let obs_rider =
+            Rider::parse_user_input("crit:persistent:1d6 bleed", Ruleset::PF2e).unwrap();
+        assert_eq!(exp_rider, obs_rider);
+
+        let obs_rider =
+            Rider::parse_user_input("persistent:crit:1d6 bleed", Ruleset::PF2e).unwrap();
+        assert_eq!(exp_rider, obs_rider);
+    }
+
+    #[test]
+    fn test_parse_user_input_invalid() {
+        // Test that the error position accounts for the trigger prefix
+        let obs_result = Rider::parse_user_input("crit:2d8x", Ruleset::DND5e);
+
+        let obs_error = obs_result.unwrap_err();
+        assert_eq!("crit:2d8x", obs_error.notation);
+        assert_eq!(8, obs_error.position);
+        assert_eq!("x", obs_error.token);
+    }
+
+    #[test]
+    fn test_parse_user_input_invalid_trigger() {
+        // An unknown trigger is not silently treated as damage notation
+        let obs_result = Rider::parse_user_input("crti:2d8", Ruleset::DND5e);
+
+        let obs_error = obs_result.unwrap_err();
+        assert_eq!(0, obs_error.position);
+        assert_eq!("crti", obs_error.token);
+    }
+
+    // endregion:
+
+    // region: triggers_on() tests
+
+    #[test]
+    fn test_triggers_on_first_hit() {
+        let rider = Rider::parse_user_input("1d6", Ruleset::DND5e).unwrap();
+
+        assert!(rider.triggers_on(&HitResult::CriticalHit));
+        assert!(rider.triggers_on(&HitResult::Hit));
+        assert!(!rider.triggers_on(&HitResult::Miss));
+    }
+
+    #[test]
+    fn test_triggers_on_first_crit() {
+        let rider = Rider::parse_user_input("crit:1d6", Ruleset::DND5e).unwrap();
+
+        assert!(rider.triggers_on(&HitResult::CriticalHit));
+        assert!(!rider.triggers_on(&HitResult::Hit));
+        assert!(!rider.triggers_on(&HitResult::Miss));
+    }
+
+    // endregion:
+
+    // region: persistent_type() tests
+
+    #[test]
+    fn test_persistent_type() {
+        let rider = Rider::parse_user_input("persistent:1d6+1d4 fire", Ruleset::PF2e).unwrap();
+        assert_eq!(Some("fire".to_string()), rider.persistent_type());
+
+        let rider = Rider::parse_user_input("persistent:1d6", Ruleset::PF2e).unwrap();
+        assert_eq!(None, rider.persistent_type());
+    }
+
+    // endregion:
+
+    // region: roll_damage_result() tests
+
+    #[test]
+    fn test_roll_damage_result() {
+        // Test that rider dice are doubled on a critical hit
+        let die = DiceBuilder::new().set_roll_min(3).set_roll_max(3).build();
+        let mut rider = Rider::new(
+            RollCollection::new(vec![die], vec![], Ruleset::DND5e),
+            RiderTrigger::FirstHit,
+        );
+
+        assert_eq!(3, rider.roll_damage_result(&HitResult::Hit).total());
+        assert_eq!(6, rider.roll_damage_result(&HitResult::CriticalHit).total());
+    }
+
+    #[test]
+    fn test_damage_distribution() {
+        let rider = Rider::parse_user_input("1d1+1", Ruleset::PF2e).unwrap();
+
+        assert_eq!(
+            Distribution::point(4),
+            rider.damage_distribution(&HitResult::CriticalHit)
+        );
+    }
+
+    // endregion:
+}
@@ -1,11 +1,15 @@
-use std::cmp::Ordering;
-
-use once_cell::sync::Lazy;
-use regex::Regex;
+use std::cmp::{max, max_by_key, min, min_by_key, Ordering};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
+use crate::damage::TypedDamage;
 use crate::dice::{Dice, DiceBuilder};
+use crate::distribution::Distribution;
+use crate::notation::{DiceFlag, DiceTerm, Expression, NotationError, Term};
 use crate::static_modifier::StaticModifier;
-use crate::{HitResult, ModifierBehaviour, RollBehaviour, Ruleset};
+use crate::{
+    DiceBehaviour, FortuneBehaviour, HitResult, ModifierBehaviour, RollBehaviour, Ruleset,
+    SaveResult,
+};
 
 /// A representation of a collection of dice and modifiers, defining a roll event
 #[derive(Debug, PartialEq)]
@@ -13,6 +17,9 @@ pub struct RollCollection {
     dice: Vec<Dice>,
     modifiers: Vec<StaticModifier>,
     rule_mode: Ruleset,
+    crit_threshold: i32,
+    fortune: FortuneBehaviour,
+    primary_die: Option<usize>,
 }
 
 impl RollCollection {
@@ -20,56 +27,134 @@ impl RollCollection {
     ///
     /// This collection contains an arbitrary number of dice and static modifier
     /// elements, and contains a reference to the rule set used for rolling when
-    /// evaluating hit results.
+    /// evaluating hit results. Only a natural 20 is treated as a critical roll, which
+    /// can be widened with RollCollection::set_crit_threshold().
+    ///
+    /// When rolled as an attack, the first d20 in the collection (or the first die, if
+    /// there is no d20) is the primary die, on which natural results are read. All other
+    /// dice are bonus dice, such as the d4 added by Bless.
     ///
     /// # Examples
     /// ```
-    /// let my_die = Dice::new();
+    /// // Rolling 1d20+5
+    /// let roll_collection = RollCollection::new(
+    ///     vec![DiceBuilder::new().set_roll_max(20).build()],
+    ///     vec![StaticModifier::new(5, ModifierBehaviour::OnHit)],
+    ///     Ruleset::DND5e
+    /// );
     /// ```
     pub fn new(
         dice_vector: Vec<Dice>,
         mod_vector: Vec<StaticModifier>,
         rule_set: Ruleset,
     ) -> RollCollection {
+        let primary_die = match dice_vector.iter().position(|d| d.max == 20) {
+            Some(i) => Some(i),
+            None if !dice_vector.is_empty() => Some(0),
+            None => None,
+        };
+
         RollCollection {
+            primary_die,
             dice: dice_vector,
             modifiers: mod_vector,
             rule_mode: rule_set,
+            crit_threshold: 20,
+            fortune: FortuneBehaviour::Standard,
         }
     }
 
-    /// Assess a roll event against a target armour class under D&D 5e rules
+    /// Set the lowest natural d20 roll which is treated as a critical roll, such as 19 for a
+    /// Champion Fighter.
+    ///
+    /// # Examples
+    /// ```
+    /// let roll_collection = RollCollection::new(
+    ///     vec![DiceBuilder::new().set_roll_max(20).build()],
+    ///     vec![StaticModifier::new(5, ModifierBehaviour::OnHit)],
+    ///     Ruleset::DND5e
+    /// ).set_crit_threshold(19);
+    /// ```
+    pub fn set_crit_threshold(mut self, crit_threshold: i32) -> Self {
+        self.crit_threshold = crit_threshold;
+        self
+    }
+
+    /// Set whether the whole attack roll is made twice, keeping the better (fortune) or
+    /// worse (misfortune) result.
+    ///
+    /// # Examples
+    /// ```
+    /// // Rolling 1d20+1d4+5 with advantage, such as an attack under Bless
+    /// let roll_collection = RollCollection::parse_user_input("1d20+1d4+5", Ruleset::DND5e)?
+    ///     .set_fortune(FortuneBehaviour::Fortune);
+    /// ```
+    pub fn set_fortune(mut self, fortune: FortuneBehaviour) -> Self {
+        self.fortune = fortune;
+        self
+    }
+
+    /// Roll every die in the collection as an attack roll, recording natural results on the primary die.
     ///
     /// # Examples
     /// ```
     /// // Rolling 1d20+5
     /// let mut roll_collection = RollCollection::new(
     ///     vec![DiceBuilder::new().set_roll_max(20).build()],
-    ///     vec![StaticModifier::new(5, EvalBehaviour::OnHit)],
+    ///     vec![StaticModifier::new(5, ModifierBehaviour::OnHit)],
     ///     Ruleset::DND5e
     /// );
-    /// let target_ac = 15;
-    /// let result = roll_collection.eval_ac_roll_dnd(target_ac);
+    /// let attack_roll = roll_collection.roll_attack();
     /// ```
-    fn eval_ac_roll_dnd(&mut self, target_ac: i32) -> HitResult {
-        let mut roll_total: i32 = self.modifiers.iter().map(|x| x.evaluate_result(None)).sum();
+    fn roll_attack(&mut self) -> AttackRoll {
+        let mut attack_roll = self.base_attack_roll();
 
-        // Roll the dice and record if there is a modifier
-        for die in &mut self.dice {
+        for (i, die) in self.dice.iter_mut().enumerate() {
             let result = die.evaluate_result(None);
+            attack_roll = attack_roll.add_die_result(
+                result,
+                die.max,
+                self.primary_die == Some(i),
+                self.crit_threshold,
+            );
+        }
 
-            if (result, die.max) == (20, 20) {
-                return HitResult::CriticalHit;
-            }
+        attack_roll
+    }
 
-            roll_total += result;
+    /// Return the attack roll before any dice are rolled, holding the static modifiers.
+    fn base_attack_roll(&self) -> AttackRoll {
+        AttackRoll {
+            total: self.modifiers.iter().map(|x| x.evaluate_result(None)).sum(),
+            natural_crits: 0,
+            natural_ones: 0,
+            bonus: 0,
         }
+    }
 
-        if roll_total >= target_ac {
-            HitResult::Hit
-        } else {
-            HitResult::Miss
-        }
+    /// Assess a roll event against a target armour class under D&D 5e rules
+    ///
+    /// A natural 1 always misses, unless the collection uses the Ruleset::DND5eNoAutoMiss
+    /// variant of the rules.
+    ///
+    /// # Examples
+    /// ```
+    /// // Rolling 1d20+5
+    /// let mut roll_collection = RollCollection::new(
+    ///     vec![DiceBuilder::new().set_roll_max(20).build()],
+    ///     vec![StaticModifier::new(5, ModifierBehaviour::OnHit)],
+    ///     Ruleset::DND5e
+    /// );
+    /// let target_ac = 15;
+    /// let (result, bonus_roll) = roll_collection.eval_ac_roll_dnd(target_ac);
+    /// ```
+    fn eval_ac_roll_dnd(&mut self, target_ac: i32) -> (HitResult, i32) {
+        let attack_roll = self.roll_attack();
+        let natural_one_misses = self.rule_mode != Ruleset::DND5eNoAutoMiss;
+        (
+            RollCollection::resolve_attack_dnd(&attack_roll, target_ac, natural_one_misses),
+            attack_roll.bonus,
+        )
     }
 
     /// Assess a roll event against a target armour class under Pathfinder 2e rules
@@ -79,43 +164,68 @@ impl RollCollection {
     /// // Rolling 1d20+5
     /// let mut roll_collection = RollCollection::new(
     ///     vec![DiceBuilder::new().set_roll_max(20).build()],
-    ///     vec![StaticModifier::new(5, EvalBehaviour::OnHit)],
+    ///     vec![StaticModifier::new(5, ModifierBehaviour::OnHit)],
     ///     Ruleset::PF2e
     /// );
     /// let target_ac = 15;
-    /// let result = roll_collection.eval_ac_roll_pathfinder(target_ac);
+    /// let (result, bonus_roll) = roll_collection.eval_ac_roll_pathfinder(target_ac);
     /// ```
-    fn eval_ac_roll_pathfinder(&mut self, target_ac: i32) -> HitResult {
-        // Using a numeric value to represent the success state of the roll, so that it can be increased or decreased
-        // in light of nat20 or nat1 rolls.
-
-        let mut roll_total: i32 = self.modifiers.iter().map(|x| x.evaluate_result(None)).sum();
-        let mut success_modifier = 0;
-        let success_state: i32;
-
-        // Roll the dice and record if there is a modifier
-        for die in &mut self.dice {
-            let result = die.evaluate_result(None);
-
-            match (result, die.max) {
-                (20, 20) => success_modifier += 1,
-                (1, 20) => success_modifier -= 1,
-                (_, _) => (),
-            }
+    fn eval_ac_roll_pathfinder(&mut self, target_ac: i32) -> (HitResult, i32) {
+        let attack_roll = self.roll_attack();
+        (
+            RollCollection::resolve_attack_pathfinder(&attack_roll, target_ac),
+            attack_roll.bonus,
+        )
+    }
 
-            roll_total += result;
+    /// Determine the hit result of a completed attack roll under D&D 5e rules
+    ///
+    /// A natural critical roll always hits, and a natural 1 always misses when
+    /// natural_one_misses is set, regardless of the roll total.
+    ///
+    /// # Examples
+    /// ```
+    /// let attack_roll = AttackRoll { total: 17, natural_crits: 0, natural_ones: 0, bonus: 0 };
+    /// let result = RollCollection::resolve_attack_dnd(&attack_roll, 15, true);
+    /// ```
+    fn resolve_attack_dnd(
+        attack_roll: &AttackRoll,
+        target_ac: i32,
+        natural_one_misses: bool,
+    ) -> HitResult {
+        if attack_roll.natural_crits > 0 {
+            HitResult::CriticalHit
+        } else if natural_one_misses && attack_roll.natural_ones > 0 {
+            HitResult::Miss
+        } else if attack_roll.total >= target_ac {
+            HitResult::Hit
+        } else {
+            HitResult::Miss
         }
+    }
+
+    /// Determine the hit result of a completed attack roll under Pathfinder 2e rules
+    ///
+    /// # Examples
+    /// ```
+    /// let attack_roll = AttackRoll { total: 17, natural_crits: 0, natural_ones: 1, bonus: 0 };
+    /// let result = RollCollection::resolve_attack_pathfinder(&attack_roll, 15);
+    /// ```
+    fn resolve_attack_pathfinder(attack_roll: &AttackRoll, target_ac: i32) -> HitResult {
+        // Using a numeric value to represent the success state of the roll, so that it can be increased or decreased
+        // in light of nat20 or nat1 rolls.
+        let success_modifier = attack_roll.natural_crits - attack_roll.natural_ones;
 
         // Evaluate the flat roll
-        let roll_difference: i32 = roll_total - target_ac;
+        let roll_difference: i32 = attack_roll.total - target_ac;
 
-        if roll_difference >= 10 {
-            success_state = 2;
+        let success_state = if roll_difference >= 10 {
+            2
         } else if roll_difference >= 0 {
-            success_state = 1;
+            1
         } else {
-            success_state = 0;
-        }
+            0
+        };
 
         // Evaluate and return
         match (success_modifier + success_state).cmp(&1) {
@@ -125,84 +235,121 @@ impl RollCollection {
         }
     }
 
-    /// Parse a dice notation string to extract the elements required for rolling.
+    /// Build the dice described by a single dice term of a parsed notation string.
     ///
-    /// Extracts user information of dice to be represented in the roll and adds them
-    /// to a borrowed vector of Dice. Accepts string in the form "NdS" where N is the
-    /// number of dice to roll in the collection, and S is the size of the dice. There
-    /// are also two optional modifiers accepted, modulating one or both of the following:
+    /// Adds the dice represented by the term to a borrowed vector of Dice. A term in
+    /// the form "NdS" represents N dice of size S. There are also three optional flags
+    /// accepted, modulating any of the following:
     ///
     /// 1. Adding reroll mechanics - Advantage, Disadvantage, or 'double advantage',
-    ///    which is effectively the Elven Accuracy mechanic from D&D 5E.
+    ///    which is effectively the Elven Accuracy mechanic from D&D 5E. These are
+    ///    special cases of keep-highest and keep-lowest pools, which are built as a
+    ///    single Dice totalling the kept dice.
     /// 2. Adding Fatal rolling mechanics, from Pathfinder.
+    /// 3. Adding Deadly rolling mechanics, from Pathfinder. The number of extra dice
+    ///    rolled on a critical hit scales with the number of weapon dice, so that a
+    ///    weapon with a greater striking rune (3 dice) adds two Deadly dice, and a
+    ///    major striking rune (4 dice) adds three.
+    /// 4. Adding per-die modifications - exploding or compounding dice, rerolling low
+    ///    faces, and minimum face values. Compounding dice keep one result per die, so
+    ///    are built alongside any keep rule.
+    ///
+    /// Every die built, including any Deadly dice, deals the damage type of the term, and is
+    /// rolled only when the attack misses if the term is dealt on a miss.
     ///
     /// # Examples
     /// ```
     /// // Regular roll for 2d6
     /// let mut dice_collection: Vec<Dice> = Vec::new();
-    /// parse_die_element(&mut dice_collection, "2d6");
-    ///
-    /// // A d20 attack roll with advantage or disadvantage
-    /// parse_die_element(&mut dice_collection, "1d20A");
-    /// parse_die_element(&mut dice_collection, "1d20D");
-    ///
-    /// // Rolling with Elven Advantage mechanics
-    /// parse_die_element(&mut dice_collection, "1d20AA");
-    ///
-    /// // Rolling a Pathfinder Dueling Pistol, standard or with Advantage
-    /// parse_die_element(&mut dice_collection, "1d6~10");
-    /// parse_die_element(&mut dice_collection, "1d6A~10");
-    /// ```
-    fn parse_die_elements(dice_vector: &mut Vec<Dice>, notation: &str) {
-        // Use a lazy wrapper so that the expression is only compiled a single time.
-        static RE_DICE: Lazy<Regex> = Lazy::new(|| {
-            Regex::new(
-                r"(?P<n_dice>\d+)d(?P<die_size>\d+)(?P<behaviour>AA|A|D)?(?:~(?P<fatal>\d+))?",
-            )
-            .unwrap()
-        });
-
-        if let Some(capture) = RE_DICE.captures(notation) {
-            // X in XdY~Z
-            let n_dice: i32 = capture
-                .name("n_dice")
-                .and_then(|m| m.as_str().parse::<i32>().ok())
-                .unwrap();
+    /// let dice_term = DiceTerm { count: 2, size: 6, flags: vec![], damage_type: None };
+    /// build_dice_elements(&mut dice_collection, &dice_term, false);
+    ///
+    /// // A d20 attack roll with advantage (1d20A)
+    /// let dice_term = DiceTerm { count: 1, size: 20, flags: vec![DiceFlag::Advantage], damage_type: None };
+    /// build_dice_elements(&mut dice_collection, &dice_term, false);
+    ///
+    /// // Rolling a Pathfinder weapon with Deadly d10 and a striking rune (2d8^10 piercing)
+    /// let dice_term = DiceTerm { count: 2, size: 8, flags: vec![DiceFlag::Deadly(10)], damage_type: Some(String::from("piercing")) };
+    /// build_dice_elements(&mut dice_collection, &dice_term, false);
+    ///
+    /// // Graze damage rolled only on a miss (miss:1d6)
+    /// let dice_term = DiceTerm { count: 1, size: 6, flags: vec![], damage_type: None };
+    /// build_dice_elements(&mut dice_collection, &dice_term, true);
+    /// ```
+    fn build_dice_elements(dice_vector: &mut Vec<Dice>, dice_term: &DiceTerm, on_miss: bool) {
+        let dice_behaviour = match on_miss {
+            true => DiceBehaviour::OnMiss,
+            false => DiceBehaviour::Standard,
+        };
+        let mut roll_behaviour = RollBehaviour::Standard;
+        let mut fatal_mod: Option<i32> = None;
+        let mut deadly_mod: Option<i32> = None;
+        let mut compounding = false;
+        let mut reroll_threshold = 0;
+        let mut face_floor = 0;
+
+        for flag in &dice_term.flags {
+            match flag {
+                DiceFlag::Advantage => roll_behaviour = RollBehaviour::Advantage,
+                DiceFlag::DoubleAdvantage => roll_behaviour = RollBehaviour::DoubleAdvantage,
+                DiceFlag::Disadvantage => roll_behaviour = RollBehaviour::Disadvantage,
+                DiceFlag::KeepHighest(x) => {
+                    roll_behaviour = RollBehaviour::KeepHighest(dice_term.count, *x)
+                }
+                DiceFlag::KeepLowest(x) => {
+                    roll_behaviour = RollBehaviour::KeepLowest(dice_term.count, *x)
+                }
+                DiceFlag::Exploding => roll_behaviour = RollBehaviour::Exploding,
+                DiceFlag::Compounding => compounding = true,
+                DiceFlag::Reroll(x) => reroll_threshold = *x,
+                DiceFlag::Floor(x) => face_floor = *x,
+                DiceFlag::Fatal(x) => fatal_mod = Some(*x),
+                DiceFlag::Deadly(x) => deadly_mod = Some(*x),
+                // The critical range applies to the whole collection, so is read by parse_user_input()
+                DiceFlag::CritRange(_) => {}
+            }
+        }
 
-            // Y in XdY~Z
-            let die_size: i32 = capture
-                .name("die_size")
-                .and_then(|m| m.as_str().parse::<i32>().ok())
-                .unwrap();
+        // Fatal and reroll mechanics are mutually exclusive, so if a fatal case is found use that...
+        let modifier = fatal_mod;
+        if fatal_mod.is_some() {
+            roll_behaviour = RollBehaviour::Fatal;
+        }
 
-            // Optional behaviour modifier in XdYmod~Z
-            let adv_mod = capture.name("behaviour").map_or("", |m| m.as_str());
-            let fatal_mod = capture
-                .name("fatal")
-                .and_then(|m| m.as_str().parse::<i32>().ok());
-
-            let roll_behaviour: RollBehaviour;
-            let modifier: Option<i32>;
-
-            if let Some(x) = fatal_mod {
-                // Fatal and reroll mechanics are mutually exclusive, so if a fatal case is found parse that...
-                roll_behaviour = RollBehaviour::Fatal;
-                modifier = Some(x);
-            } else {
-                // Otherwise test for reroll mechanics
-                roll_behaviour = match adv_mod {
-                    "AA" => RollBehaviour::DoubleAdvantage,
-                    "A" => RollBehaviour::Advantage,
-                    "D" => RollBehaviour::Disadvantage,
-                    _ => RollBehaviour::Standard,
-                };
-                modifier = None;
-            }
+        // A pool of dice is rolled as a single element, which totals only the kept dice
+        let n_elements = match roll_behaviour {
+            RollBehaviour::KeepHighest(..) | RollBehaviour::KeepLowest(..) => 1,
+            _ => dice_term.count,
+        };
+
+        for _ in 0..n_elements {
+            let dice_collection = DiceBuilder::new()
+                .set_roll_max(dice_term.size)
+                .set_roll_behaviour(roll_behaviour, modifier)
+                .set_compounding(compounding)
+                .set_dice_behaviour(dice_behaviour)
+                .set_reroll_threshold(reroll_threshold)
+                .set_face_floor(face_floor)
+                .set_damage_type(dice_term.damage_type.clone())
+                .build();
+
+            dice_vector.push(dice_collection);
+        }
 
-            for _ in 0..n_dice {
+        // Deadly dice are rolled in addition to the weapon dice, so are added as separate elements.
+        if let Some(deadly_size) = deadly_mod {
+            let n_deadly = match dice_term.count {
+                4.. => 3,
+                3 => 2,
+                _ => 1,
+            };
+
+            for _ in 0..n_deadly {
                 let dice_collection = DiceBuilder::new()
-                    .set_roll_max(die_size)
-                    .set_roll_behaviour(roll_behaviour, modifier)
+                    .set_roll_max(deadly_size)
+                    .set_roll_behaviour(RollBehaviour::Deadly, None)
+                    .set_dice_behaviour(dice_behaviour)
+                    .set_damage_type(dice_term.damage_type.clone())
                     .build();
 
                 dice_vector.push(dice_collection);
@@ -210,124 +357,600 @@ impl RollCollection {
         }
     }
 
-    /// Extract tokens representing static damage modifiers from an input string.
+    /// Build the static modifier described by a constant term of a parsed notation string.
     ///
-    /// Extracts user information of static modifiers to be represented in the roll
-    /// and adds them to a borrowed vector of StaticModifiers. Standard notation will
-    /// be the +X modifier on an attack or damage roll, but negative values are also
-    /// accepted (for example, MAP in Pathfinder). Also allows for multiple inputs in
-    /// case of differing behaviours or just to simplify notation when multiple
-    /// modifiers are being considered.
+    /// Standard notation will be the +X modifier on an attack or damage roll, but negative
+    /// values are also accepted (for example, MAP in Pathfinder). The behaviour of the
+    /// modifier on a critical hit is determined by the rule set, unless the modifier is
+    /// only dealt on a miss.
     ///
     /// # Examples
     /// ```
-    /// // Typical D&D dice notation
-    /// let mut mod_collection: Vec<StaticModifier> = Vec::new();
-    /// parse_static_elements(&mut mod_collection, "1d8+5");
+    /// // Typical D&D dice notation, the +5 in 1d8+5
+    /// let modifier = build_static_element(5, &Ruleset::DND5e, false);
     ///
-    /// // D&D damage roll from the above, with a +1 weapon
-    /// parse_static_elements(&mut mod_collection, "1d8+6");
-    /// // or
-    /// parse_static_elements(&mut mod_collection, "1d8+5+1");
+    /// // Graze damage, the miss:4 in 2d6+4,miss:4
+    /// let modifier = build_static_element(4, &Ruleset::DND5e, true);
     /// ```
-    fn parse_static_elements(
-        mod_vector: &mut Vec<StaticModifier>,
-        notation: &str,
-        rule_set: &Ruleset,
-    ) {
-        // Use a lazy wrapper so that the expression is only compiled a single time.
-        // This expression should only be called once, but this is easy future proofing.
-        static RE_STATIC: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\+\d+)|(-\d+)").unwrap());
-
-        for capture in RE_STATIC.find_iter(notation) {
-            let modifier = capture.as_str().parse::<i32>().unwrap();
-            let behaviour = match rule_set {
-                Ruleset::PF2e => ModifierBehaviour::CanCritical,
-                Ruleset::DND5e => ModifierBehaviour::OnHit,
-            };
+    fn build_static_element(value: i32, rule_set: &Ruleset, on_miss: bool) -> StaticModifier {
+        let behaviour = match (on_miss, rule_set) {
+            (true, _) => ModifierBehaviour::OnMiss,
+            (false, Ruleset::PF2e) => ModifierBehaviour::CanCritical,
+            (false, Ruleset::DND5e | Ruleset::DND5eNoAutoMiss) => ModifierBehaviour::OnHit,
+        };
 
-            mod_vector.push(StaticModifier::new(modifier, behaviour));
-        }
+        StaticModifier::new(value, behaviour)
     }
 
-    /// Take a pair of input strings from the user and parse into the elements representing the roll
+    /// Take an input string from the user and parse into the elements representing the roll
+    ///
+    /// Parses the notation into its dice terms and constants, then builds a Dice for each
+    /// die rolled and a StaticModifier for each constant. A critical range given on any
+    /// dice term (such as 1d20c19) applies to the whole collection. Returns an error
+    /// describing the first unrecognised token if the notation cannot be parsed.
     ///
-    /// Breaks and iterates over comma separation for dice elements, then identifiers and
-    /// captures all static modifiers, identified through their +/- prefix.
+    /// The notation may be prefixed with `fortune:` or `misfortune:` to roll the whole
+    /// collection twice when attacking. If both are given they cancel out, and the roll
+    /// is made normally.
+    ///
+    /// Terms without a damage type deal the damage type of the first typed term in their
+    /// fragment, so that the modifier in 1d8 slashing + 1d6 fire + 4 deals slashing damage.
+    /// A fragment prefixed with `miss:` is dealt only when the attack misses, such as the
+    /// Graze mastery of D&D 5e (2d6+4,miss:4).
     ///
     /// # Examples
     /// ```
-    /// let roll_collection = parse_user_input("1d6,2d4+5", Ruleset::DND5e);
+    /// let roll_collection = parse_user_input("1d6,2d4+5", Ruleset::DND5e)?;
+    ///
+    /// // A longsword with a flaming rune
+    /// let roll_collection = parse_user_input("1d8 slashing + 1d6 fire + 4", Ruleset::PF2e)?;
+    ///
+    /// // Rolling the whole attack twice, keeping the better result
+    /// let roll_collection = parse_user_input("fortune:1d20+1d4+5", Ruleset::DND5e)?;
     /// ```
-    pub fn parse_user_input(notation: &str, rule_mode: Ruleset) -> RollCollection {
-        let mut dice_vector: Vec<Dice> = Vec::new();
-
-        for notation_fragment in notation.split(",") {
-            RollCollection::parse_die_elements(&mut dice_vector, notation_fragment);
+    pub fn parse_user_input(
+        notation: &str,
+        rule_mode: Ruleset,
+    ) -> Result<RollCollection, NotationError> {
+        let mut has_fortune = false;
+        let mut has_misfortune = false;
+        let mut roll_notation = notation;
+        while let Some((prefix, remainder)) = roll_notation.split_once(':') {
+            match prefix {
+                "fortune" => has_fortune = true,
+                "misfortune" => has_misfortune = true,
+                _ => break,
+            }
+            roll_notation = remainder;
         }
 
+        let prefix_length = notation.len() - roll_notation.len();
+        let expression =
+            Expression::parse(roll_notation).map_err(|e| e.with_offset(notation, prefix_length))?;
+
+        let mut dice_vector: Vec<Dice> = Vec::new();
         let mut mod_vector: Vec<StaticModifier> = Vec::new();
-        RollCollection::parse_static_elements(&mut mod_vector, notation, &rule_mode);
+        let mut crit_threshold = 20;
+
+        for fragment in &expression.fragments {
+            let fragment_type = fragment.terms.iter().find_map(|t| t.damage_type());
+
+            for term in &fragment.terms {
+                let damage_type = term.damage_type().or(fragment_type).cloned();
+                match term {
+                    Term::Dice(dice_term) => {
+                        let typed_term = DiceTerm {
+                            damage_type,
+                            ..dice_term.clone()
+                        };
+                        RollCollection::build_dice_elements(
+                            &mut dice_vector,
+                            &typed_term,
+                            fragment.on_miss,
+                        );
+                        for flag in &dice_term.flags {
+                            if let DiceFlag::CritRange(x) = flag {
+                                crit_threshold = crit_threshold.min(*x);
+                            }
+                        }
+                    }
+                    Term::Constant(value, _) => mod_vector.push(
+                        RollCollection::build_static_element(*value, &rule_mode, fragment.on_miss)
+                            .set_damage_type(damage_type),
+                    ),
+                }
+            }
+        }
+
+        let fortune = match (has_fortune, has_misfortune) {
+            (true, false) => FortuneBehaviour::Fortune,
+            (false, true) => FortuneBehaviour::Misfortune,
+            _ => FortuneBehaviour::Standard,
+        };
 
-        RollCollection::new(dice_vector, mod_vector, rule_mode)
+        Ok(RollCollection::new(dice_vector, mod_vector, rule_mode)
+            .set_crit_threshold(crit_threshold)
+            .set_fortune(fortune))
     }
 
     /// Perform a turn roll against a specified armour class
     ///
-    /// Returns the hit result as either miss, hit, or critical hit. Critical hits are
+    /// Returns the hit result as either miss, hit, or critical hit, along with the total
+    /// rolled on the bonus dice of the attack. Critical hits are
     /// determined using the internal rule specified by the struct instance. Internally
     /// this is just a call to either the RollCollection::eval_ac_roll_dnd() or
-    /// RollCollection::eval_ac_roll_pathfinder() function.
+    /// RollCollection::eval_ac_roll_pathfinder() function, made twice with the better or
+    /// worse result kept if the collection has fortune or misfortune.
     ///
     /// # Examples
     /// ```
     /// // Rolling 1d20+5
     /// let mut roll_collection = RollCollection::new(
     ///     vec![DiceBuilder::new().set_roll_max(20).build()],
-    ///     vec![StaticModifier::new(5, EvalBehaviour::OnHit)],
+    ///     vec![StaticModifier::new(5, ModifierBehaviour::OnHit)],
     ///     Ruleset::PF2e
     /// );
     /// let target_ac = 15;
-    /// let result = roll_collection.roll_against_armour_class(target_ac);
+    /// let (result, bonus_roll) = roll_collection.roll_against_armour_class(target_ac);
     /// ```
-    pub fn roll_against_armour_class(&mut self, target_ac: i32) -> HitResult {
+    pub fn roll_against_armour_class(&mut self, target_ac: i32) -> (HitResult, i32) {
+        let outcome_degree = |outcome: &(HitResult, i32)| success_degree(&outcome.0);
+
+        match self.fortune {
+            FortuneBehaviour::Standard => self.roll_once_against_armour_class(target_ac),
+            FortuneBehaviour::Fortune => max_by_key(
+                self.roll_once_against_armour_class(target_ac),
+                self.roll_once_against_armour_class(target_ac),
+                outcome_degree,
+            ),
+            FortuneBehaviour::Misfortune => min_by_key(
+                self.roll_once_against_armour_class(target_ac),
+                self.roll_once_against_armour_class(target_ac),
+                outcome_degree,
+            ),
+        }
+    }
+
+    fn roll_once_against_armour_class(&mut self, target_ac: i32) -> (HitResult, i32) {
         match self.rule_mode {
-            Ruleset::DND5e => self.eval_ac_roll_dnd(target_ac),
+            Ruleset::DND5e | Ruleset::DND5eNoAutoMiss => self.eval_ac_roll_dnd(target_ac),
             Ruleset::PF2e => self.eval_ac_roll_pathfinder(target_ac),
         }
     }
 
-    /// Roll the rollection as a damage roll with a specified hit outcome.
+    /// Perform a saving throw against a specified difficulty class
     ///
-    /// Modules the damage according to the hit type (miss, hit, critical hit),
-    /// the rule set used, and the behaviour of each element in the roll collection.
+    /// Returns the degree of success of the creature making the save. Under D&D 5e rules
+    /// the save either succeeds or fails, and natural rolls are not special. Under
+    /// Pathfinder 2e rules, there are four degrees of success, which are adjusted by
+    /// natural rolls in the same way as an attack roll. The save is made twice, keeping
+    /// the better or worse result, if the collection has fortune or misfortune.
+    ///
+    /// # Examples
+    /// ```
+    /// // A target with a +8 Reflex save against a DC 25 spell
+    /// let mut roll_collection = RollCollection::parse_user_input("1d20+8", Ruleset::PF2e)?;
+    /// let save_result = roll_collection.roll_against_difficulty_class(25);
+    /// ```
+    pub fn roll_against_difficulty_class(&mut self, dc: i32) -> SaveResult {
+        match self.fortune {
+            FortuneBehaviour::Standard => self.roll_once_against_difficulty_class(dc),
+            FortuneBehaviour::Fortune => max(
+                self.roll_once_against_difficulty_class(dc),
+                self.roll_once_against_difficulty_class(dc),
+            ),
+            FortuneBehaviour::Misfortune => min(
+                self.roll_once_against_difficulty_class(dc),
+                self.roll_once_against_difficulty_class(dc),
+            ),
+        }
+    }
+
+    fn roll_once_against_difficulty_class(&mut self, dc: i32) -> SaveResult {
+        let save_roll = self.roll_attack();
+        self.resolve_save(&save_roll, dc)
+    }
+
+    /// Determine the degree of success of a completed saving throw, using the rules of the collection
+    ///
+    /// # Examples
+    /// ```
+    /// let save_roll = AttackRoll { total: 17, natural_crits: 0, natural_ones: 0, bonus: 0 };
+    /// let result = roll_collection.resolve_save(&save_roll, 15);
+    /// ```
+    fn resolve_save(&self, save_roll: &AttackRoll, dc: i32) -> SaveResult {
+        let roll_difference = save_roll.total - dc;
+
+        match self.rule_mode {
+            Ruleset::DND5e | Ruleset::DND5eNoAutoMiss => match roll_difference >= 0 {
+                true => SaveResult::Success,
+                false => SaveResult::Failure,
+            },
+            Ruleset::PF2e => {
+                let success_state = if roll_difference >= 10 {
+                    3
+                } else if roll_difference >= 0 {
+                    2
+                } else if roll_difference > -10 {
+                    1
+                } else {
+                    0
+                };
+
+                match success_state + save_roll.natural_crits - save_roll.natural_ones {
+                    x if x >= 3 => SaveResult::CriticalSuccess,
+                    2 => SaveResult::Success,
+                    1 => SaveResult::Failure,
+                    _ => SaveResult::CriticalFailure,
+                }
+            }
+        }
+    }
+
+    /// Roll the collection as a damage roll with a specified hit outcome.
     ///
-    /// CURRENTLY UNDER DEVELOPMENT, REQUIRE MORE IMPLEMENTATION IN THE DICE AND
-    /// STATIC_MODIFIER STRUCTS TO ENABLE ALL BEHAVIOURS.
+    /// Modulates the damage according to the hit type (miss, hit, critical hit),
+    /// the rule set used, and the behaviour of each element in the roll collection.
+    /// Each element deals its own damage type, and elements dealt only on a miss
+    /// contribute nothing to a hit or critical hit.
     ///
     /// # Examples
     /// ```
     /// // Roll damage for a 1d8+3 attack (hit)
     /// let mut roll_collection = RollCollection::new(
     ///     vec![DiceBuilder::new().set_roll_max(8).build()],
-    ///     vec![StaticModifier::new(3, EvalBehaviour::OnHit)],
+    ///     vec![StaticModifier::new(3, ModifierBehaviour::OnHit)],
     ///     Ruleset::DND5e
     /// );
     /// let result = roll_collection.roll_damage_result(&HitResult::Hit);
+    /// let total_damage = result.total();
+    /// ```
+    pub fn roll_damage_result(&mut self, hit_result: &HitResult) -> TypedDamage {
+        let mut typed_damage = TypedDamage::new();
+        for die in self.dice.iter_mut() {
+            let value = die.evaluate_result(Some(hit_result));
+            typed_damage.add(&die.damage_type, value);
+        }
+        for modifier in &self.modifiers {
+            typed_damage.add(
+                &modifier.damage_type,
+                modifier.evaluate_result(Some(hit_result)),
+            );
+        }
+
+        typed_damage
+    }
+
+    /// Return every damage type dealt by the collection, including None for untyped damage.
+    ///
+    /// # Examples
     /// ```
-    pub fn roll_damage_result(&mut self, hit_result: &HitResult) -> i32 {
-        let dice_roll: i32 = self
-            .dice
-            .iter_mut()
-            .map(|d| d.evaluate_result(Some(hit_result)))
+    /// let roll_collection = RollCollection::parse_user_input("1d8 slashing + 1d6 fire + 4", Ruleset::PF2e)?;
+    /// assert_eq!(2, roll_collection.damage_types().len());
+    /// ```
+    pub fn damage_types(&self) -> BTreeSet<Option<String>> {
+        self.dice
+            .iter()
+            .map(|d| d.damage_type.clone())
+            .chain(self.modifiers.iter().map(|m| m.damage_type.clone()))
+            .collect()
+    }
+
+    /// Return the exact probability of each hit result against a specified armour class
+    ///
+    /// Sums the probability of each outcome returned by RollCollection::attack_outcomes()
+    /// over the totals of the bonus dice.
+    ///
+    /// # Examples
+    /// ```
+    /// let roll_collection = RollCollection::parse_user_input("1d20+5", Ruleset::DND5e)?;
+    /// let target_ac = 15;
+    ///
+    /// for (hit_result, probability) in roll_collection.hit_probabilities(target_ac) {
+    ///     println!("{:?}: {}", hit_result, probability);
+    /// }
+    /// ```
+    #[allow(dead_code)]
+    pub fn hit_probabilities(&self, target_ac: i32) -> Vec<(HitResult, f64)> {
+        let mut probabilities = vec![
+            (HitResult::CriticalHit, 0.0),
+            (HitResult::Hit, 0.0),
+            (HitResult::Miss, 0.0),
+        ];
+        for (hit_result, _, p_outcome) in self.attack_outcomes(target_ac) {
+            for (result, probability) in probabilities.iter_mut() {
+                if *result == hit_result {
+                    *probability += p_outcome;
+                }
+            }
+        }
+
+        probabilities
+    }
+
+    /// Return the exact probability of each pairing of hit result and bonus dice total
+    /// against a specified armour class
+    ///
+    /// Builds the joint distribution of the roll total, the natural results on the primary
+    /// die, and the total of the bonus dice, then resolves each possible roll using the same
+    /// rules as RollCollection::roll_against_armour_class(). Outcomes are ordered from the
+    /// best to the worst, by hit result and then by bonus dice total.
+    ///
+    /// # Examples
+    /// ```
+    /// let roll_collection = RollCollection::parse_user_input("1d20+1d4+5", Ruleset::DND5e)?;
+    ///
+    /// for (hit_result, bonus_roll, probability) in roll_collection.attack_outcomes(15) {
+    ///     println!("{:?} ({}): {}", hit_result, bonus_roll, probability);
+    /// }
+    /// ```
+    pub fn attack_outcomes(&self, target_ac: i32) -> Vec<(HitResult, i32, f64)> {
+        // Keyed by the degree of success of the hit result, and the total of the bonus dice
+        let mut outcomes: BTreeMap<(i32, i32), f64> = BTreeMap::new();
+        for (attack_roll, p_state) in self.attack_roll_distribution() {
+            let hit_result = match self.rule_mode {
+                Ruleset::DND5e => RollCollection::resolve_attack_dnd(&attack_roll, target_ac, true),
+                Ruleset::DND5eNoAutoMiss => {
+                    RollCollection::resolve_attack_dnd(&attack_roll, target_ac, false)
+                }
+                Ruleset::PF2e => RollCollection::resolve_attack_pathfinder(&attack_roll, target_ac),
+            };
+            *outcomes
+                .entry((success_degree(&hit_result), attack_roll.bonus))
+                .or_insert(0.0) += p_state;
+        }
+
+        keep_fortune_outcomes(outcomes, self.fortune, |x| x.0)
+            .into_iter()
+            .rev()
+            .map(|((degree, bonus), p)| (hit_result_from_degree(degree), bonus, p))
+            .collect()
+    }
+
+    /// Return the exact probability of each degree of success when rolling the collection
+    /// as a saving throw against a specified difficulty class
+    ///
+    /// The exact counterpart to RollCollection::roll_against_difficulty_class(). Results
+    /// are ordered from a critical failure to a critical success.
+    ///
+    /// # Examples
+    /// ```
+    /// let roll_collection = RollCollection::parse_user_input("1d20+5", Ruleset::PF2e)?;
+    ///
+    /// for (save_result, probability) in roll_collection.save_probabilities(18) {
+    ///     println!("{:?}: {}", save_result, probability);
+    /// }
+    /// ```
+    pub fn save_probabilities(&self, dc: i32) -> Vec<(SaveResult, f64)> {
+        let mut outcomes: BTreeMap<SaveResult, f64> = BTreeMap::new();
+        for (save_roll, p_state) in self.attack_roll_distribution() {
+            *outcomes
+                .entry(self.resolve_save(&save_roll, dc))
+                .or_insert(0.0) += p_state;
+        }
+
+        keep_fortune_outcomes(outcomes, self.fortune, |x| *x)
+            .into_iter()
+            .collect()
+    }
+
+    /// Return the exact probability of every total and natural result of a roll of the collection.
+    ///
+    /// Builds the joint distribution by adding one die of the collection at a time, in the
+    /// same way as RollCollection::roll_attack().
+    fn attack_roll_distribution(&self) -> HashMap<AttackRoll, f64> {
+        let mut roll_states: HashMap<AttackRoll, f64> =
+            HashMap::from([(self.base_attack_roll(), 1.0)]);
+
+        for (i, die) in self.dice.iter().enumerate() {
+            let die_distribution = die.distribution(None);
+            let mut next_states: HashMap<AttackRoll, f64> = HashMap::new();
+
+            for (attack_roll, p_state) in &roll_states {
+                for (result, p_result) in die_distribution.iter() {
+                    let next_roll = attack_roll.add_die_result(
+                        result,
+                        die.max,
+                        self.primary_die == Some(i),
+                        self.crit_threshold,
+                    );
+                    *next_states.entry(next_roll).or_insert(0.0) += p_state * p_result;
+                }
+            }
+            roll_states = next_states;
+        }
+
+        roll_states
+    }
+
+    /// Return the exact damage distribution of the collection for a specified hit outcome.
+    ///
+    /// The exact counterpart to RollCollection::roll_damage_result().
+    ///
+    /// # Examples
+    /// ```
+    /// let roll_collection = RollCollection::parse_user_input("1d8+3", Ruleset::DND5e)?;
+    /// let dist = roll_collection.damage_distribution(&HitResult::Hit);
+    /// ```
+    pub fn damage_distribution(&self, hit_result: &HitResult) -> Distribution {
+        let static_mods: i32 = self
+            .modifiers
+            .iter()
+            .map(|s| s.evaluate_result(Some(hit_result)))
             .sum();
+
+        self.dice
+            .iter()
+            .fold(Distribution::point(static_mods), |dist, d| {
+                dist.convolve(&d.distribution(Some(hit_result)))
+            })
+    }
+
+    /// Return the exact distribution of the damage of a single type dealt by the collection.
+    ///
+    /// # Examples
+    /// ```
+    /// let roll_collection = RollCollection::parse_user_input("1d8 slashing + 1d6 fire + 4", Ruleset::PF2e)?;
+    /// let fire_dist = roll_collection.type_damage_distribution(&HitResult::Hit, &Some(String::from("fire")));
+    /// ```
+    pub fn type_damage_distribution(
+        &self,
+        hit_result: &HitResult,
+        damage_type: &Option<String>,
+    ) -> Distribution {
         let static_mods: i32 = self
             .modifiers
             .iter()
+            .filter(|s| s.damage_type == *damage_type)
             .map(|s| s.evaluate_result(Some(hit_result)))
             .sum();
 
-        dice_roll + static_mods
+        self.dice
+            .iter()
+            .filter(|d| d.damage_type == *damage_type)
+            .fold(Distribution::point(static_mods), |dist, d| {
+                dist.convolve(&d.distribution(Some(hit_result)))
+            })
+    }
+
+    /// Return the exact distribution of the damage of a single type dealt by the collection on a
+    /// hit, when the total damage is halved.
+    ///
+    /// The exact counterpart to TypedDamage::halve(). The damage of each type is independent, so
+    /// a type dealt an odd amount regains the point lost to rounding with the probability that
+    /// fewer of the types before it are dealt an odd amount than the points returned in total.
+    ///
+    /// # Examples
+    /// ```
+    /// let roll_collection = RollCollection::parse_user_input("1d8 fire + 1d6 cold", Ruleset::DND5e)?;
+    /// let fire_dist = roll_collection.halved_type_damage_distribution(&Some(String::from("fire")));
+    /// ```
+    pub fn halved_type_damage_distribution(&self, damage_type: &Option<String>) -> Distribution {
+        let odd_types = |ordering: Ordering| {
+            self.damage_types()
+                .iter()
+                .filter(|t| (*t).cmp(damage_type) == ordering)
+                .fold(Distribution::point(0), |dist, t| {
+                    let p_odd: f64 = self
+                        .type_damage_distribution(&HitResult::Hit, t)
+                        .iter()
+                        .filter(|(value, _)| value % 2 != 0)
+                        .map(|(_, p)| p)
+                        .sum();
+                    dist.convolve(&Distribution::from_pairs(vec![
+                        (0, 1.0 - p_odd),
+                        (1, p_odd),
+                    ]))
+                })
+        };
+        let odd_before = odd_types(Ordering::Less);
+        let odd_after = odd_types(Ordering::Greater);
+
+        // Half of the types dealt an odd amount, rounded down, regain a point
+        let p_regain: f64 = odd_before
+            .iter()
+            .flat_map(|(n_before, p_before)| {
+                odd_after
+                    .iter()
+                    .filter(move |(n_after, _)| n_before < (n_before + 1 + n_after) / 2)
+                    .map(move |(_, p_after)| p_before * p_after)
+            })
+            .sum();
+
+        Distribution::from_pairs(
+            self.type_damage_distribution(&HitResult::Hit, damage_type)
+                .iter()
+                .flat_map(|(value, p)| match value % 2 {
+                    0 => vec![(value / 2, p)],
+                    _ => vec![
+                        (value / 2 + 1, p * p_regain),
+                        (value / 2, p * (1.0 - p_regain)),
+                    ],
+                })
+                .filter(|(_, p)| *p > 0.0)
+                .collect(),
+        )
+    }
+}
+
+/// Rank a hit result by its degree of success, for keeping the better or worse of two rolls.
+fn success_degree(hit_result: &HitResult) -> i32 {
+    match hit_result {
+        HitResult::CriticalHit => 2,
+        HitResult::Hit => 1,
+        HitResult::Miss => 0,
+    }
+}
+
+/// Combine each pairing of outcomes from two rolls, keeping the better (or worse) under fortune
+/// (or misfortune), using the same choice as the Monte Carlo rolls. Outcomes are ranked by the
+/// key function, and standard rolls are returned unchanged.
+fn keep_fortune_outcomes<K: Copy + Ord, R: Ord>(
+    outcomes: BTreeMap<K, f64>,
+    fortune: FortuneBehaviour,
+    rank: impl Fn(&K) -> R,
+) -> BTreeMap<K, f64> {
+    if fortune == FortuneBehaviour::Standard {
+        return outcomes;
+    }
+
+    let mut kept_outcomes: BTreeMap<K, f64> = BTreeMap::new();
+    for (first, p_first) in &outcomes {
+        for (second, p_second) in &outcomes {
+            let kept = match fortune {
+                FortuneBehaviour::Fortune => max_by_key(first, second, |x| rank(x)),
+                _ => min_by_key(first, second, |x| rank(x)),
+            };
+            *kept_outcomes.entry(*kept).or_insert(0.0) += p_first * p_second;
+        }
+    }
+    kept_outcomes
+}
+
+/// Return the hit result with the specified degree of success, the inverse of success_degree().
+fn hit_result_from_degree(degree: i32) -> HitResult {
+    match degree {
+        2 => HitResult::CriticalHit,
+        1 => HitResult::Hit,
+        _ => HitResult::Miss,
+    }
+}
+
+/// The outcome of rolling a collection as an attack, prior to comparison against a target
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct AttackRoll {
+    total: i32,
+    natural_crits: i32,
+    natural_ones: i32,
+    bonus: i32,
+}
+
+impl AttackRoll {
+    /// Return a new AttackRoll with the result of a single die added to the running tally.
+    ///
+    /// Natural critical rolls (those at or above the crit threshold) and 1s are only recorded
+    /// on the primary die, when it is a d20. The results of all other dice are also tallied
+    /// as the bonus dice total.
+    ///
+    /// # Examples
+    /// ```
+    /// let attack_roll = AttackRoll { total: 5, natural_crits: 0, natural_ones: 0, bonus: 0 };
+    /// let attack_roll = attack_roll.add_die_result(20, 20, true, 20);
+    /// ```
+    fn add_die_result(
+        &self,
+        result: i32,
+        die_max: i32,
+        is_primary: bool,
+        crit_threshold: i32,
+    ) -> AttackRoll {
+        let is_natural = is_primary && die_max == 20;
+        AttackRoll {
+            total: self.total + result,
+            natural_crits: self.natural_crits + i32::from(is_natural && result >= crit_threshold),
+            natural_ones: self.natural_ones + i32::from(is_natural && result == 1),
+            bonus: self.bonus + if is_primary { 0 } else { result },
+        }
     }
 }
 
@@ -341,12 +964,25 @@ mod tests {
             dice: Vec::new(),
             modifiers: Vec::new(),
             rule_mode: Ruleset::DND5e,
+            crit_threshold: 20,
+            fortune: FortuneBehaviour::Standard,
+            primary_die: None,
         };
 
         let obs_rc = RollCollection::new(Vec::new(), Vec::new(), Ruleset::DND5e);
         assert_eq!(exp_rc, obs_rc);
     }
 
+    #[test]
+    fn test_constructor_primary_die() {
+        // The first d20 is the primary die wherever it appears, otherwise the first die of any size
+        let test_roll = RollCollection::parse_user_input("1d4+1d20+1d20", Ruleset::DND5e).unwrap();
+        assert_eq!(Some(1), test_roll.primary_die);
+
+        let test_roll = RollCollection::parse_user_input("2d6+3", Ruleset::DND5e).unwrap();
+        assert_eq!(Some(0), test_roll.primary_die);
+    }
+
     // region: eval_ac_roll_dnd() tests
 
     #[test]
@@ -355,10 +991,91 @@ mod tests {
         let dice_vector = vec![DiceBuilder::new().set_roll_min(20).set_roll_max(20).build()];
         let mut test_roll = RollCollection::new(dice_vector, vec![], Ruleset::DND5e);
 
-        let obs_result = test_roll.eval_ac_roll_dnd(20);
+        let (obs_result, _) = test_roll.eval_ac_roll_dnd(20);
         assert_eq!(HitResult::CriticalHit, obs_result);
     }
 
+    #[test]
+    fn test_eval_ac_roll_dnd_crit_range() {
+        // Test the critical condition when rolling a natural 19 with an expanded critical range.
+        let dice_vector = vec![DiceBuilder::new().set_roll_min(19).set_roll_max(20).build()];
+        let mut test_roll =
+            RollCollection::new(dice_vector, vec![], Ruleset::DND5e).set_crit_threshold(19);
+
+        let obs_results: Vec<HitResult> =
+            (0..100).map(|_| test_roll.eval_ac_roll_dnd(30).0).collect();
+        assert!(obs_results.iter().all(|x| *x == HitResult::CriticalHit));
+    }
+
+    #[test]
+    fn test_eval_ac_roll_dnd_bonus_die() {
+        // Test that the bonus die counts towards the total, and is reported separately.
+        let dice_vector = vec![
+            DiceBuilder::new().set_roll_min(20).set_roll_max(20).build(),
+            DiceBuilder::new().set_roll_min(3).set_roll_max(4).build(),
+        ];
+        let mut test_roll = RollCollection::new(dice_vector, vec![], Ruleset::DND5e);
+
+        let (obs_result, obs_bonus) = test_roll.eval_ac_roll_dnd(100);
+        assert_eq!(HitResult::CriticalHit, obs_result);
+        assert!((3..=4).contains(&obs_bonus));
+    }
+
+    #[test]
+    fn test_eval_ac_roll_dnd_bonus_d20() {
+        // Test that a second d20 added to the roll is a bonus die, rather than a second primary die.
+        let dice_vector = vec![
+            DiceBuilder::new().set_roll_min(20).set_roll_max(20).build(),
+            DiceBuilder::new().set_roll_min(20).set_roll_max(20).build(),
+        ];
+        let mut test_roll = RollCollection::new(dice_vector, vec![], Ruleset::DND5e);
+
+        let (obs_result, obs_bonus) = test_roll.eval_ac_roll_dnd(100);
+        assert_eq!(HitResult::CriticalHit, obs_result);
+        assert_eq!(20, obs_bonus);
+    }
+
+    #[test]
+    fn test_eval_ac_roll_dnd_natural_one() {
+        // Test that a natural 1 misses, even when the total would beat the target AC.
+        let dice_vector = vec![DiceBuilder::new().set_roll_max(20).build()];
+        let mod_vector = vec![StaticModifier::new(100, ModifierBehaviour::OnHit)];
+        let mut test_roll = RollCollection::new(dice_vector, mod_vector, Ruleset::DND5e);
+
+        let n_misses = (0..2_000)
+            .filter(|_| test_roll.eval_ac_roll_dnd(1).0 == HitResult::Miss)
+            .count();
+        assert!(n_misses > 0);
+        assert!(n_misses < 200);
+    }
+
+    #[test]
+    fn test_eval_ac_roll_dnd_natural_one_no_auto_miss() {
+        // Test that a natural 1 hits when the total beats the target AC, if opted out of the auto-miss.
+        let dice_vector = vec![DiceBuilder::new().set_roll_max(20).build()];
+        let mod_vector = vec![StaticModifier::new(100, ModifierBehaviour::OnHit)];
+        let mut test_roll = RollCollection::new(dice_vector, mod_vector, Ruleset::DND5eNoAutoMiss);
+
+        let n_misses = (0..2_000)
+            .filter(|_| test_roll.eval_ac_roll_dnd(1).0 == HitResult::Miss)
+            .count();
+        assert_eq!(0, n_misses);
+    }
+
+    #[test]
+    fn test_eval_ac_roll_dnd_natural_one_bonus_die() {
+        // Test that a 1 on a bonus die is not a natural 1.
+        let dice_vector = vec![
+            DiceBuilder::new().set_roll_min(2).set_roll_max(20).build(),
+            DiceBuilder::new().set_roll_max(1).build(),
+        ];
+        let mut test_roll = RollCollection::new(dice_vector, vec![], Ruleset::DND5e);
+
+        let obs_results: Vec<HitResult> =
+            (0..100).map(|_| test_roll.eval_ac_roll_dnd(1).0).collect();
+        assert!(obs_results.iter().all(|x| *x != HitResult::Miss));
+    }
+
     #[test]
     fn test_eval_ac_roll_dnd_hit() {
         // Test the hit condition when beating target AC.
@@ -366,7 +1083,7 @@ mod tests {
         let mod_vector = vec![StaticModifier::new(10, ModifierBehaviour::OnHit)];
         let mut test_roll = RollCollection::new(dice_vector, mod_vector, Ruleset::DND5e);
 
-        let obs_result = test_roll.eval_ac_roll_dnd(1);
+        let (obs_result, _) = test_roll.eval_ac_roll_dnd(1);
         assert_eq!(HitResult::Hit, obs_result);
     }
 
@@ -377,7 +1094,7 @@ mod tests {
         let mod_vector = vec![StaticModifier::new(10, ModifierBehaviour::OnHit)];
         let mut test_roll = RollCollection::new(dice_vector, mod_vector, Ruleset::DND5e);
 
-        let obs_result = test_roll.eval_ac_roll_dnd(20);
+        let (obs_result, _) = test_roll.eval_ac_roll_dnd(20);
         assert_eq!(HitResult::Miss, obs_result);
     }
 
@@ -392,7 +1109,7 @@ mod tests {
         let mod_vector = vec![StaticModifier::new(10, ModifierBehaviour::OnHit)];
         let mut test_roll = RollCollection::new(dice_vector, mod_vector, Ruleset::PF2e);
 
-        let obs_result = test_roll.eval_ac_roll_pathfinder(1);
+        let (obs_result, _) = test_roll.eval_ac_roll_pathfinder(1);
         assert_eq!(HitResult::CriticalHit, obs_result);
     }
 
@@ -402,10 +1119,23 @@ mod tests {
         let dice_vector = vec![DiceBuilder::new().set_roll_min(20).set_roll_max(20).build()];
         let mut test_roll = RollCollection::new(dice_vector, vec![], Ruleset::PF2e);
 
-        let obs_result = test_roll.eval_ac_roll_pathfinder(19);
+        let (obs_result, _) = test_roll.eval_ac_roll_pathfinder(19);
         assert_eq!(HitResult::CriticalHit, obs_result);
     }
 
+    #[test]
+    fn test_eval_ac_roll_pathfinder_crit_range() {
+        // Test the degree of success bump when rolling a natural 19 with an expanded critical range.
+        let dice_vector = vec![DiceBuilder::new().set_roll_min(19).set_roll_max(20).build()];
+        let mut test_roll =
+            RollCollection::new(dice_vector, vec![], Ruleset::PF2e).set_crit_threshold(19);
+
+        let obs_results: Vec<HitResult> = (0..100)
+            .map(|_| test_roll.eval_ac_roll_pathfinder(25).0)
+            .collect();
+        assert!(obs_results.iter().all(|x| *x == HitResult::Hit));
+    }
+
     #[test]
     fn test_eval_ac_roll_pathfinder_hit_ac() {
         // Test the hit condition when achieved by beating AC.
@@ -413,7 +1143,7 @@ mod tests {
         let mod_vector = vec![StaticModifier::new(5, ModifierBehaviour::OnHit)];
         let mut test_roll = RollCollection::new(dice_vector, mod_vector, Ruleset::PF2e);
 
-        let obs_result = test_roll.eval_ac_roll_pathfinder(5);
+        let (obs_result, _) = test_roll.eval_ac_roll_pathfinder(5);
         assert_eq!(HitResult::Hit, obs_result);
     }
 
@@ -423,7 +1153,7 @@ mod tests {
         let dice_vector = vec![DiceBuilder::new().set_roll_min(20).set_roll_max(20).build()];
         let mut test_roll = RollCollection::new(dice_vector, vec![], Ruleset::PF2e);
 
-        let obs_result = test_roll.eval_ac_roll_pathfinder(21);
+        let (obs_result, _) = test_roll.eval_ac_roll_pathfinder(21);
         assert_eq!(HitResult::Hit, obs_result);
     }
 
@@ -433,148 +1163,244 @@ mod tests {
         let dice_vector = vec![DiceBuilder::new().set_roll_max(10).build()];
         let mut test_roll = RollCollection::new(dice_vector, vec![], Ruleset::PF2e);
 
-        let obs_result = test_roll.eval_ac_roll_pathfinder(15);
+        let (obs_result, _) = test_roll.eval_ac_roll_pathfinder(15);
         assert_eq!(HitResult::Miss, obs_result);
     }
 
-    #[ignore]
     #[test]
     fn test_eval_ac_roll_pathfinder_miss_1() {
-        // Test the miss condition when hitting AC but rolling a natural 1
-
-        /* Currently this cannot be tested as the test for assessing a natural one requires the rolling die to be a d20
-            Will need to revised this code in the future to make this work.
-            Current idea is to sort the dice in the RollCollection by descending size and reading the 1/20 results from
-            the first only.
-        */
-        let dice_vector = vec![DiceBuilder::new().set_roll_max(1).build()];
+        // Test the miss condition when hitting AC but rolling a natural 1. Every roll of 1d20+5
+        //  hits AC 2, so only a natural 1 on the d20 can downgrade the hit to a miss.
+        let dice_vector = vec![DiceBuilder::new().set_roll_max(20).build()];
         let mod_vector = vec![StaticModifier::new(5, ModifierBehaviour::OnHit)];
         let mut test_roll = RollCollection::new(dice_vector, mod_vector, Ruleset::PF2e);
 
-        let obs_result = test_roll.eval_ac_roll_pathfinder(2);
-        assert_eq!(HitResult::Miss, obs_result);
+        let obs_results: Vec<HitResult> = (0..1_000)
+            .map(|_| test_roll.eval_ac_roll_pathfinder(2).0)
+            .collect();
+        assert!(obs_results.contains(&HitResult::Miss));
     }
 
-    #[ignore]
     #[test]
     fn test_eval_ac_roll_pathfinder_hit_1() {
         // Test the hit condition when scoring a critical hit by beating AC by 10, but then downgrading to
-        // a regular hit due to natural 1.
-        let dice_vector = vec![DiceBuilder::new().set_roll_max(1).build()];
+        //  a regular hit due to natural 1. Every roll of 1d20+20 beats AC 10 by 10.
+        let dice_vector = vec![DiceBuilder::new().set_roll_max(20).build()];
         let mod_vector = vec![StaticModifier::new(20, ModifierBehaviour::OnHit)];
         let mut test_roll = RollCollection::new(dice_vector, mod_vector, Ruleset::PF2e);
 
-        let obs_result = test_roll.eval_ac_roll_pathfinder(10);
-        assert_eq!(HitResult::Miss, obs_result);
+        let obs_results: Vec<HitResult> = (0..1_000)
+            .map(|_| test_roll.eval_ac_roll_pathfinder(10).0)
+            .collect();
+        assert!(obs_results.contains(&HitResult::Hit));
+        assert!(!obs_results.contains(&HitResult::Miss));
     }
 
     // endregion:
 
-    // region: parse_die_elements() tests
+    // region: build_dice_elements() tests
+
+    fn parse_dice_term(notation: &str) -> DiceTerm {
+        // Extract the first dice term from a notation string
+        let expression = Expression::parse(notation).unwrap();
+        match &expression.fragments[0].terms[0] {
+            Term::Dice(dice_term) => dice_term.clone(),
+            _ => panic!("No dice term found in '{}'", notation),
+        }
+    }
 
     #[test]
-    fn test_parse_die_elements_none() {
+    fn test_build_dice_elements_none() {
         let exp_result: Vec<Dice> = Vec::new();
         let mut obs_result: Vec<Dice> = Vec::new();
 
-        RollCollection::parse_die_elements(&mut obs_result, "+5");
+        let dice_term = DiceTerm {
+            count: 0,
+            size: 6,
+            flags: vec![],
+            damage_type: None,
+        };
+        RollCollection::build_dice_elements(&mut obs_result, &dice_term, false);
         assert_eq!(exp_result, obs_result);
     }
 
     #[test]
-    fn test_parse_die_elements_single() {
+    fn test_build_dice_elements_single() {
         let exp_result = vec![DiceBuilder::new().set_roll_max(8).build()];
         let mut obs_result: Vec<Dice> = Vec::new();
 
-        RollCollection::parse_die_elements(&mut obs_result, "1d8+5");
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("1d8+5"), false);
         assert_eq!(exp_result, obs_result);
     }
 
     #[test]
-    fn test_parse_die_elements_multiple() {
+    fn test_build_dice_elements_multiple() {
         let exp_result = vec![
             DiceBuilder::new().set_roll_max(8).build(),
             DiceBuilder::new().set_roll_max(8).build(),
         ];
         let mut obs_result: Vec<Dice> = Vec::new();
 
-        RollCollection::parse_die_elements(&mut obs_result, "2d8+5");
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("2d8+5"), false);
         assert_eq!(exp_result, obs_result);
     }
 
     #[test]
-    fn test_parse_die_elements_multidigit() {
-        // Ensuring that the regex does not prematurely terminate dice values with 10s or 100s sizes.
+    fn test_build_dice_elements_multidigit() {
+        // Ensuring that the parser does not prematurely terminate dice values with 10s or 100s sizes.
         let exp_result = vec![
             DiceBuilder::new().set_roll_max(20).build(),
             DiceBuilder::new().set_roll_max(100).build(),
         ];
         let mut obs_result: Vec<Dice> = Vec::new();
 
-        RollCollection::parse_die_elements(&mut obs_result, "1d20");
-        RollCollection::parse_die_elements(&mut obs_result, "1d100+5");
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("1d20"), false);
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("1d100+5"), false);
+        assert_eq!(exp_result, obs_result);
+    }
+
+    #[test]
+    fn test_build_dice_elements_exploding() {
+        let exp_result = vec![
+            DiceBuilder::new()
+                .set_roll_max(6)
+                .set_roll_behaviour(RollBehaviour::Exploding, None)
+                .build(),
+            DiceBuilder::new()
+                .set_roll_max(10)
+                .set_compounding(true)
+                .build(),
+        ];
+        let mut obs_result: Vec<Dice> = Vec::new();
+
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("1d6!+2"), false);
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("1d10!!"), false);
         assert_eq!(exp_result, obs_result);
     }
 
     #[test]
-    fn test_parse_die_string_single_a() {
+    fn test_build_dice_elements_reroll() {
+        let exp_result = vec![
+            DiceBuilder::new()
+                .set_roll_max(6)
+                .set_reroll_threshold(2)
+                .build(),
+            DiceBuilder::new()
+                .set_roll_max(6)
+                .set_reroll_threshold(2)
+                .build(),
+        ];
+        let mut obs_result: Vec<Dice> = Vec::new();
+
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("2d6r2+5"), false);
+        assert_eq!(exp_result, obs_result);
+    }
+
+    #[test]
+    fn test_build_dice_elements_floor() {
+        let exp_result = vec![DiceBuilder::new().set_roll_max(8).set_face_floor(2).build()];
+        let mut obs_result: Vec<Dice> = Vec::new();
+
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("1d8m2+4"), false);
+        assert_eq!(exp_result, obs_result);
+    }
+
+    #[test]
+    fn test_build_dice_elements_keep() {
+        let exp_result = vec![
+            DiceBuilder::new()
+                .set_roll_max(6)
+                .set_roll_behaviour(RollBehaviour::KeepHighest(4, 3), None)
+                .build(),
+            DiceBuilder::new()
+                .set_roll_max(20)
+                .set_roll_behaviour(RollBehaviour::KeepLowest(2, 1), None)
+                .build(),
+        ];
+        let mut obs_result: Vec<Dice> = Vec::new();
+
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("4d6kh3"), false);
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("2d20kl1+5"), false);
+        assert_eq!(exp_result, obs_result);
+    }
+
+    #[test]
+    fn test_build_dice_elements_keep_compounding() {
+        // Compounding dice are built alongside the keep rule, rather than replacing it
         let exp_result = vec![DiceBuilder::new()
-            .set_roll_max(8)
-            .set_roll_behaviour(RollBehaviour::Advantage, None)
+            .set_roll_max(6)
+            .set_roll_behaviour(RollBehaviour::KeepHighest(4, 3), None)
+            .set_compounding(true)
             .build()];
         let mut obs_result: Vec<Dice> = Vec::new();
 
-        RollCollection::parse_die_elements(&mut obs_result, "1d8A+5");
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("4d6!!kh3"), false);
+        assert_eq!(exp_result, obs_result);
+
+        // The order of the flags does not change the dice built
+        let mut obs_result: Vec<Dice> = Vec::new();
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("4d6kh3!!"), false);
         assert_eq!(exp_result, obs_result);
+
+        let keep_roll = RollCollection::parse_user_input("4d6kh3", Ruleset::DND5e).unwrap();
+        let compounding_roll =
+            RollCollection::parse_user_input("4d6!!kh3", Ruleset::DND5e).unwrap();
+        assert!(
+            compounding_roll.damage_distribution(&HitResult::Hit).mean()
+                > keep_roll.damage_distribution(&HitResult::Hit).mean()
+        );
     }
 
     #[test]
-    fn test_parse_die_string_double_a() {
+    fn test_build_dice_elements_single_a() {
         let exp_result = vec![DiceBuilder::new()
             .set_roll_max(8)
-            .set_roll_behaviour(RollBehaviour::DoubleAdvantage, None)
+            .set_roll_behaviour(RollBehaviour::Advantage, None)
             .build()];
         let mut obs_result: Vec<Dice> = Vec::new();
 
-        RollCollection::parse_die_elements(&mut obs_result, "1d8AA+5");
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("1d8A+5"), false);
         assert_eq!(exp_result, obs_result);
     }
 
     #[test]
-    fn test_parse_die_string_single_d() {
+    fn test_build_dice_elements_double_a() {
         let exp_result = vec![DiceBuilder::new()
             .set_roll_max(8)
-            .set_roll_behaviour(RollBehaviour::Disadvantage, None)
+            .set_roll_behaviour(RollBehaviour::DoubleAdvantage, None)
             .build()];
         let mut obs_result: Vec<Dice> = Vec::new();
 
-        RollCollection::parse_die_elements(&mut obs_result, "1d8D+5");
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("1d8AA+5"), false);
         assert_eq!(exp_result, obs_result);
     }
 
     #[test]
-    fn test_parse_die_string_single_invalid() {
-        let exp_result = vec![DiceBuilder::new().set_roll_max(8).build()];
+    fn test_build_dice_elements_single_d() {
+        let exp_result = vec![DiceBuilder::new()
+            .set_roll_max(8)
+            .set_roll_behaviour(RollBehaviour::Disadvantage, None)
+            .build()];
         let mut obs_result: Vec<Dice> = Vec::new();
 
-        RollCollection::parse_die_elements(&mut obs_result, "1d8z+5");
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("1d8D+5"), false);
         assert_eq!(exp_result, obs_result);
     }
 
     #[test]
-    fn test_parse_die_string_fatal() {
+    fn test_build_dice_elements_fatal() {
         let exp_result = vec![DiceBuilder::new()
             .set_roll_max(6)
             .set_roll_behaviour(RollBehaviour::Fatal, Some(10))
             .build()];
         let mut obs_result: Vec<Dice> = Vec::new();
 
-        RollCollection::parse_die_elements(&mut obs_result, "1d6~10");
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("1d6~10"), false);
         assert_eq!(exp_result, obs_result);
     }
 
     #[test]
-    fn test_parse_die_string_compete() {
+    fn test_build_dice_elements_compete() {
         // Test a string with both advantage/disadvantage and fatal modifiers, to confirm the resolution priority
         let exp_result = vec![DiceBuilder::new()
             .set_roll_max(6)
@@ -582,51 +1408,104 @@ mod tests {
             .build()];
         let mut obs_result: Vec<Dice> = Vec::new();
 
-        RollCollection::parse_die_elements(&mut obs_result, "1d6AA~10");
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("1d6AA~10"), false);
         assert_eq!(exp_result, obs_result);
     }
 
-    // endregion:
-
-    // region: parse_static_elements() tests
-
     #[test]
-    fn test_parse_static_elements_single_pos() {
-        let exp_result = vec![StaticModifier::new(5, ModifierBehaviour::OnHit)];
-        let mut obs_result: Vec<StaticModifier> = Vec::new();
+    fn test_build_dice_elements_deadly() {
+        let exp_result = vec![
+            DiceBuilder::new().set_roll_max(8).build(),
+            DiceBuilder::new()
+                .set_roll_max(10)
+                .set_roll_behaviour(RollBehaviour::Deadly, None)
+                .build(),
+        ];
+        let mut obs_result: Vec<Dice> = Vec::new();
 
-        RollCollection::parse_static_elements(&mut obs_result, "1d8+5", &Ruleset::DND5e);
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("1d8^10+4"), false);
         assert_eq!(exp_result, obs_result);
     }
 
     #[test]
-    fn test_parse_static_elements_single_neg() {
-        let exp_result = vec![StaticModifier::new(-5, ModifierBehaviour::OnHit)];
-        let mut obs_result: Vec<StaticModifier> = Vec::new();
-
-        RollCollection::parse_static_elements(&mut obs_result, "1d8-5", &Ruleset::DND5e);
-        assert_eq!(exp_result, obs_result);
+    fn test_build_dice_elements_deadly_scaling() {
+        // Test that the number of Deadly dice scales with the striking runes, represented by the
+        //  number of weapon dice.
+        for (notation, exp_deadly) in [("2d8^10", 1), ("3d8^10", 2), ("4d8^10", 3)] {
+            let mut obs_result: Vec<Dice> = Vec::new();
+            RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term(notation), false);
+
+            let deadly_die = DiceBuilder::new()
+                .set_roll_max(10)
+                .set_roll_behaviour(RollBehaviour::Deadly, None)
+                .build();
+            let obs_deadly = obs_result.iter().filter(|d| **d == deadly_die).count();
+            assert_eq!(exp_deadly, obs_deadly);
+        }
     }
 
     #[test]
-    fn test_parse_static_elements_multiple() {
+    fn test_build_dice_elements_fatal_deadly() {
+        // Test a string with both fatal and deadly modifiers, which are not mutually exclusive
         let exp_result = vec![
-            StaticModifier::new(5, ModifierBehaviour::OnHit),
-            StaticModifier::new(-3, ModifierBehaviour::OnHit),
+            DiceBuilder::new()
+                .set_roll_max(6)
+                .set_roll_behaviour(RollBehaviour::Fatal, Some(10))
+                .build(),
+            DiceBuilder::new()
+                .set_roll_max(8)
+                .set_roll_behaviour(RollBehaviour::Deadly, None)
+                .build(),
         ];
-        let mut obs_result: Vec<StaticModifier> = Vec::new();
+        let mut obs_result: Vec<Dice> = Vec::new();
 
-        RollCollection::parse_static_elements(&mut obs_result, "1d8+5-3", &Ruleset::DND5e);
+        RollCollection::build_dice_elements(&mut obs_result, &parse_dice_term("1d6~10^8"), false);
         assert_eq!(exp_result, obs_result);
     }
 
+    // endregion:
+
+    // region: build_static_element() tests
+
+    #[test]
+    fn test_build_static_element_pos() {
+        let exp_result = StaticModifier::new(5, ModifierBehaviour::OnHit);
+        assert_eq!(
+            exp_result,
+            RollCollection::build_static_element(5, &Ruleset::DND5e, false)
+        );
+    }
+
     #[test]
-    fn test_parse_static_elements_pf() {
-        let exp_result = vec![StaticModifier::new(5, ModifierBehaviour::CanCritical)];
-        let mut obs_result: Vec<StaticModifier> = Vec::new();
+    fn test_build_static_element_neg() {
+        let exp_result = StaticModifier::new(-5, ModifierBehaviour::OnHit);
+        assert_eq!(
+            exp_result,
+            RollCollection::build_static_element(-5, &Ruleset::DND5e, false)
+        );
+    }
 
-        RollCollection::parse_static_elements(&mut obs_result, "1d8+5", &Ruleset::PF2e);
-        assert_eq!(exp_result, obs_result);
+    #[test]
+    fn test_build_static_element_pf() {
+        let exp_result = StaticModifier::new(5, ModifierBehaviour::CanCritical);
+        assert_eq!(
+            exp_result,
+            RollCollection::build_static_element(5, &Ruleset::PF2e, false)
+        );
+    }
+
+    #[test]
+    fn test_build_static_element_on_miss() {
+        // A modifier dealt on a miss behaves the same under either rule set
+        let exp_result = StaticModifier::new(4, ModifierBehaviour::OnMiss);
+        assert_eq!(
+            exp_result,
+            RollCollection::build_static_element(4, &Ruleset::DND5e, true)
+        );
+        assert_eq!(
+            exp_result,
+            RollCollection::build_static_element(4, &Ruleset::PF2e, true)
+        );
     }
 
     // endregion:
@@ -660,10 +1539,131 @@ mod tests {
         let s_modfier = StaticModifier::new(7, ModifierBehaviour::OnHit);
 
         let exp_rc = RollCollection::new(vec![d1, d2, d3, d4], vec![s_modfier], Ruleset::DND5e);
-        let obs_rc = RollCollection::parse_user_input("1d4,2d6D+7,1d6~12", Ruleset::DND5e);
+        let obs_rc = RollCollection::parse_user_input("1d4,2d6D+7,1d6~12", Ruleset::DND5e).unwrap();
+        assert_eq!(exp_rc, obs_rc);
+    }
+
+    #[test]
+    fn test_parse_user_input_multiple_modifiers() {
+        // Modifiers in each fragment are all applied to the roll
+        let exp_rc = RollCollection::new(
+            vec![
+                DiceBuilder::new().set_roll_max(20).build(),
+                DiceBuilder::new().set_roll_max(6).build(),
+            ],
+            vec![
+                StaticModifier::new(5, ModifierBehaviour::OnHit),
+                StaticModifier::new(-1, ModifierBehaviour::OnHit),
+            ],
+            Ruleset::DND5e,
+        );
+
+        let obs_rc = RollCollection::parse_user_input("1d20+5,1d6-1", Ruleset::DND5e).unwrap();
         assert_eq!(exp_rc, obs_rc);
     }
 
+    #[test]
+    fn test_parse_user_input_added_dice() {
+        let exp_rc = RollCollection::new(
+            vec![
+                DiceBuilder::new().set_roll_max(8).build(),
+                DiceBuilder::new().set_roll_max(6).build(),
+            ],
+            vec![StaticModifier::new(4, ModifierBehaviour::CanCritical)],
+            Ruleset::PF2e,
+        );
+
+        let obs_rc = RollCollection::parse_user_input("1d8+1d6+4", Ruleset::PF2e).unwrap();
+        assert_eq!(exp_rc, obs_rc);
+    }
+
+    #[test]
+    fn test_parse_user_input_on_miss() {
+        let exp_rc = RollCollection::new(
+            vec![
+                DiceBuilder::new().set_roll_max(6).build(),
+                DiceBuilder::new().set_roll_max(6).build(),
+                DiceBuilder::new()
+                    .set_roll_max(4)
+                    .set_dice_behaviour(DiceBehaviour::OnMiss)
+                    .build(),
+            ],
+            vec![
+                StaticModifier::new(4, ModifierBehaviour::OnHit),
+                StaticModifier::new(2, ModifierBehaviour::OnMiss),
+            ],
+            Ruleset::DND5e,
+        );
+
+        let obs_rc = RollCollection::parse_user_input("2d6+4,miss:1d4+2", Ruleset::DND5e).unwrap();
+        assert_eq!(exp_rc, obs_rc);
+    }
+
+    #[test]
+    fn test_parse_user_input_crit_range() {
+        let exp_rc = RollCollection::new(
+            vec![DiceBuilder::new().set_roll_max(20).build()],
+            vec![StaticModifier::new(8, ModifierBehaviour::OnHit)],
+            Ruleset::DND5e,
+        )
+        .set_crit_threshold(19);
+
+        let obs_rc = RollCollection::parse_user_input("1d20c19+8", Ruleset::DND5e).unwrap();
+        assert_eq!(exp_rc, obs_rc);
+    }
+
+    #[test]
+    fn test_parse_user_input_fortune() {
+        let exp_rc = RollCollection::new(
+            vec![
+                DiceBuilder::new().set_roll_max(20).build(),
+                DiceBuilder::new().set_roll_max(4).build(),
+            ],
+            vec![StaticModifier::new(5, ModifierBehaviour::OnHit)],
+            Ruleset::DND5e,
+        )
+        .set_fortune(FortuneBehaviour::Fortune);
+
+        let obs_rc =
+            RollCollection::parse_user_input("fortune:1d20+1d4+5", Ruleset::DND5e).unwrap();
+        assert_eq!(exp_rc, obs_rc);
+
+        let obs_rc = RollCollection::parse_user_input("misfortune:1d20", Ruleset::PF2e).unwrap();
+        assert_eq!(FortuneBehaviour::Misfortune, obs_rc.fortune);
+    }
+
+    #[test]
+    fn test_parse_user_input_fortune_cancelled() {
+        // Fortune and misfortune cancel out, regardless of the number of each applied
+        let notations = [
+            "fortune:misfortune:1d20+5",
+            "misfortune:fortune:fortune:1d20+5",
+        ];
+
+        for notation in notations {
+            let obs_rc = RollCollection::parse_user_input(notation, Ruleset::PF2e).unwrap();
+            assert_eq!(FortuneBehaviour::Standard, obs_rc.fortune);
+        }
+    }
+
+    #[test]
+    fn test_parse_user_input_fortune_invalid() {
+        // Errors are reported against the full notation, including the prefix
+        let obs_result = RollCollection::parse_user_input("fortune:1d20+5x", Ruleset::DND5e);
+        assert_eq!(14, obs_result.unwrap_err().position);
+
+        let obs_result = RollCollection::parse_user_input("fortnue:1d20+5", Ruleset::DND5e);
+        assert_eq!("fortnue", obs_result.unwrap_err().token);
+    }
+
+    #[test]
+    fn test_parse_user_input_invalid() {
+        let obs_result = RollCollection::parse_user_input("1d8+4x", Ruleset::DND5e);
+
+        assert!(obs_result.is_err());
+        assert_eq!(5, obs_result.unwrap_err().position);
+    }
+
     // endregion:
 
     // region: roll_against_armour_class()
@@ -676,10 +1676,33 @@ mod tests {
         let dice_vector = vec![DiceBuilder::new().set_roll_min(20).set_roll_max(20).build()];
         let mut test_roll = RollCollection::new(dice_vector, vec![], Ruleset::DND5e);
 
-        let obs_result = test_roll.roll_against_armour_class(100);
+        let (obs_result, _) = test_roll.roll_against_armour_class(100);
         assert_eq!(HitResult::CriticalHit, obs_result);
     }
 
+    #[test]
+    fn test_roll_against_armour_class_fortune() {
+        // A d20 rolling only 19 or 20 against AC 20 crits unless both rolls are a 19 with fortune,
+        // and misses unless both rolls are a 20 with misfortune
+        let dice_vector = vec![DiceBuilder::new().set_roll_min(19).set_roll_max(20).build()];
+        let mut test_roll = RollCollection::new(dice_vector, vec![], Ruleset::DND5e)
+            .set_fortune(FortuneBehaviour::Fortune);
+
+        let n_crits = (0..10_000)
+            .filter(|_| test_roll.roll_against_armour_class(20).0 == HitResult::CriticalHit)
+            .count();
+        assert!(n_crits > 7_000);
+
+        let dice_vector = vec![DiceBuilder::new().set_roll_min(19).set_roll_max(20).build()];
+        let mut test_roll = RollCollection::new(dice_vector, vec![], Ruleset::DND5e)
+            .set_fortune(FortuneBehaviour::Misfortune);
+
+        let n_crits = (0..10_000)
+            .filter(|_| test_roll.roll_against_armour_class(20).0 == HitResult::CriticalHit)
+            .count();
+        assert!(n_crits < 3_000);
+    }
+
     #[test]
     fn test_roll_against_armour_class_pf() {
         // Test for a roll condition which could only happen in the PF2e rules.
@@ -688,7 +1711,7 @@ mod tests {
         let dice_vector = vec![DiceBuilder::new().set_roll_min(14).set_roll_max(15).build()];
         let mut test_roll = RollCollection::new(dice_vector, vec![], Ruleset::PF2e);
 
-        let obs_result = test_roll.roll_against_armour_class(2);
+        let (obs_result, _) = test_roll.roll_against_armour_class(2);
         assert_eq!(HitResult::CriticalHit, obs_result);
     }
 
@@ -701,7 +1724,7 @@ mod tests {
         let mut test_roll = RollCollection::new(vec![], vec![], Ruleset::DND5e);
 
         let obs_result = test_roll.roll_damage_result(&HitResult::Miss);
-        assert_eq!(0, obs_result);
+        assert_eq!(0, obs_result.total());
     }
 
     #[test]
@@ -717,7 +1740,7 @@ mod tests {
         let mut test_roll = RollCollection::new(dice_vector, mod_vector, Ruleset::DND5e);
 
         let obs_result = test_roll.roll_damage_result(&HitResult::Hit);
-        assert_eq!(8, obs_result);
+        assert_eq!(8, obs_result.total());
     }
 
     #[test]
@@ -733,7 +1756,7 @@ mod tests {
         let mut test_roll = RollCollection::new(dice_vector, mod_vector, Ruleset::DND5e);
 
         let obs_result = test_roll.roll_damage_result(&HitResult::CriticalHit);
-        assert_eq!(13, obs_result);
+        assert_eq!(13, obs_result.total());
     }
 
     #[test]
@@ -749,7 +1772,328 @@ mod tests {
         let mut test_roll = RollCollection::new(dice_vector, mod_vector, Ruleset::PF2e);
 
         let obs_result = test_roll.roll_damage_result(&HitResult::CriticalHit);
-        assert_eq!(16, obs_result);
+        assert_eq!(16, obs_result.total());
+    }
+
+    #[test]
+    fn test_roll_damage_result_typed() {
+        // Test that untyped terms take the first damage type of their fragment.
+        let mut test_roll =
+            RollCollection::parse_user_input("1d1 slashing + 1d1 fire + 4, 2d1", Ruleset::PF2e)
+                .unwrap();
+
+        let obs_result = test_roll.roll_damage_result(&HitResult::Hit);
+        assert_eq!(5, obs_result.get(&Some("slashing".to_string())));
+        assert_eq!(1, obs_result.get(&Some("fire".to_string())));
+        assert_eq!(2, obs_result.get(&None));
+        assert_eq!(8, obs_result.total());
+    }
+
+    #[test]
+    fn test_roll_damage_result_on_miss() {
+        // Graze damage is only dealt when the attack misses
+        let mut test_roll =
+            RollCollection::parse_user_input("1d1+3,miss:1d1+2", Ruleset::DND5e).unwrap();
+
+        assert_eq!(3, test_roll.roll_damage_result(&HitResult::Miss).total());
+        assert_eq!(4, test_roll.roll_damage_result(&HitResult::Hit).total());
+        assert_eq!(
+            5,
+            test_roll
+                .roll_damage_result(&HitResult::CriticalHit)
+                .total()
+        );
+    }
+
+    // endregion:
+
+    // region: damage_types() tests
+
+    #[test]
+    fn test_damage_types() {
+        let test_roll =
+            RollCollection::parse_user_input("2d8^10 piercing, 4 fire, 1d4", Ruleset::PF2e)
+                .unwrap();
+
+        let exp_types =
+            BTreeSet::from([None, Some("fire".to_string()), Some("piercing".to_string())]);
+        assert_eq!(exp_types, test_roll.damage_types());
+    }
+
+    // endregion:
+
+    // region: hit_probabilities() tests
+
+    fn unpack_probabilities(probabilities: Vec<(HitResult, f64)>) -> (f64, f64, f64) {
+        let find = |target: HitResult| {
+            probabilities
+                .iter()
+                .find(|(r, _)| *r == target)
+                .map(|(_, p)| *p)
+                .unwrap()
+        };
+        (
+            find(HitResult::CriticalHit),
+            find(HitResult::Hit),
+            find(HitResult::Miss),
+        )
+    }
+
+    fn assert_close(left: f64, right: f64) {
+        assert!((left - right).abs() < 1e-9, "{} != {}", left, right);
+    }
+
+    #[test]
+    fn test_hit_probabilities_dnd() {
+        // 1d20+5 against AC 15 hits on a 10-19 and crits on a 20
+        let test_roll = RollCollection::parse_user_input("1d20+5", Ruleset::DND5e).unwrap();
+        let (p_crit, p_hit, p_miss) = unpack_probabilities(test_roll.hit_probabilities(15));
+
+        assert_close(0.05, p_crit);
+        assert_close(0.50, p_hit);
+        assert_close(0.45, p_miss);
+    }
+
+    #[test]
+    fn test_hit_probabilities_dnd_natural_one() {
+        // 1d20+20 against AC 10 misses only on a natural 1, unless opted out of the auto-miss
+        let test_roll = RollCollection::parse_user_input("1d20+20", Ruleset::DND5e).unwrap();
+        let (_, _, p_miss) = unpack_probabilities(test_roll.hit_probabilities(10));
+        assert_close(0.05, p_miss);
+
+        let test_roll =
+            RollCollection::parse_user_input("1d20+20", Ruleset::DND5eNoAutoMiss).unwrap();
+        let (_, _, p_miss) = unpack_probabilities(test_roll.hit_probabilities(10));
+        assert_close(0.0, p_miss);
+    }
+
+    #[test]
+    fn test_hit_probabilities_dnd_advantage() {
+        // With advantage, a crit occurs unless both dice miss the 20
+        let test_roll = RollCollection::parse_user_input("1d20A", Ruleset::DND5e).unwrap();
+        let (p_crit, _, _) = unpack_probabilities(test_roll.hit_probabilities(100));
+
+        assert_close(1.0 - 0.95 * 0.95, p_crit);
+    }
+
+    #[test]
+    fn test_hit_probabilities_dnd_crit_range() {
+        // A Champion Fighter crits on an 18-20, even when 18 would not otherwise hit
+        let test_roll = RollCollection::parse_user_input("1d20c18", Ruleset::DND5e).unwrap();
+        let (p_crit, p_hit, p_miss) = unpack_probabilities(test_roll.hit_probabilities(19));
+
+        assert_close(0.15, p_crit);
+        assert_close(0.0, p_hit);
+        assert_close(0.85, p_miss);
+    }
+
+    #[test]
+    fn test_hit_probabilities_pathfinder_crit_range() {
+        // 1d20+10 against AC 25 hits on a 15-18, and a natural 19 or 20 bumps the hit to a crit
+        let test_roll = RollCollection::parse_user_input("1d20c19+10", Ruleset::PF2e).unwrap();
+        let (p_crit, p_hit, p_miss) = unpack_probabilities(test_roll.hit_probabilities(25));
+
+        assert_close(0.10, p_crit);
+        assert_close(0.20, p_hit);
+        assert_close(0.70, p_miss);
+    }
+
+    #[test]
+    fn test_hit_probabilities_fortune() {
+        // 1d20+1d4 against AC 13 rolls the whole check twice, rather than just the d20
+        let test_roll = RollCollection::parse_user_input("1d20+1d4", Ruleset::DND5e).unwrap();
+        let (p_crit, p_hit, p_miss) = unpack_probabilities(test_roll.hit_probabilities(13));
+
+        let test_roll =
+            RollCollection::parse_user_input("fortune:1d20+1d4", Ruleset::DND5e).unwrap();
+        let (obs_crit, obs_hit, obs_miss) = unpack_probabilities(test_roll.hit_probabilities(13));
+
+        assert_close(1.0 - (1.0 - p_crit).powi(2), obs_crit);
+        assert_close(p_miss.powi(2), obs_miss);
+        assert_close(1.0, obs_crit + obs_hit + obs_miss);
+        assert!(obs_hit > p_hit);
+    }
+
+    #[test]
+    fn test_hit_probabilities_misfortune() {
+        // 1d20+10 against AC 15 under PF2e misfortune only crits if both rolls are 15 or higher
+        let test_roll =
+            RollCollection::parse_user_input("misfortune:1d20+10", Ruleset::PF2e).unwrap();
+        let (p_crit, p_hit, p_miss) = unpack_probabilities(test_roll.hit_probabilities(15));
+
+        assert_close(0.09, p_crit);
+        assert_close(1.0 - 0.09 - 0.36, p_hit);
+        assert_close(0.36, p_miss);
+    }
+
+    #[test]
+    fn test_hit_probabilities_pathfinder() {
+        // 1d20+10 against AC 15 misses on a 1-4, hits on a 5-14, and crits on a 15-20.
+        let test_roll = RollCollection::parse_user_input("1d20+10", Ruleset::PF2e).unwrap();
+        let (p_crit, p_hit, p_miss) = unpack_probabilities(test_roll.hit_probabilities(15));
+
+        assert_close(0.30, p_crit);
+        assert_close(0.50, p_hit);
+        assert_close(0.20, p_miss);
+    }
+
+    #[test]
+    fn test_hit_probabilities_bonus_d20() {
+        // Only a natural 20 on the first d20 crits, not one on a d20 added to the roll
+        let test_roll = RollCollection::parse_user_input("1d20+1d20", Ruleset::DND5e).unwrap();
+        let (p_crit, _, p_miss) = unpack_probabilities(test_roll.hit_probabilities(100));
+
+        assert_close(0.05, p_crit);
+        assert_close(0.95, p_miss);
+    }
+
+    #[test]
+    fn test_hit_probabilities_pathfinder_natural() {
+        // Against AC 30, a natural 20 upgrades a miss to a hit. Against AC 1, a natural 1 downgrades
+        //  a crit to a hit.
+        let test_roll = RollCollection::parse_user_input("1d20", Ruleset::PF2e).unwrap();
+
+        let (p_crit, p_hit, p_miss) = unpack_probabilities(test_roll.hit_probabilities(30));
+        assert_close(0.0, p_crit);
+        assert_close(0.05, p_hit);
+        assert_close(0.95, p_miss);
+
+        let (p_crit, p_hit, p_miss) = unpack_probabilities(test_roll.hit_probabilities(-10));
+        assert_close(0.95, p_crit);
+        assert_close(0.05, p_hit);
+        assert_close(0.0, p_miss);
+    }
+
+    // endregion:
+
+    // region: attack_outcomes() tests
+
+    #[test]
+    fn test_attack_outcomes() {
+        // 1d20+1d4+10 against AC 1 only misses on a natural 1, so the bonus die is independent of
+        //  the hit result
+        let test_roll = RollCollection::parse_user_input("1d20+1d4+10", Ruleset::DND5e).unwrap();
+        let obs_outcomes = test_roll.attack_outcomes(1);
+
+        let exp_outcomes: Vec<(HitResult, i32, f64)> = (1..=4)
+            .rev()
+            .map(|x| (HitResult::CriticalHit, x, 0.0125))
+            .chain((1..=4).rev().map(|x| (HitResult::Hit, x, 0.225)))
+            .chain((1..=4).rev().map(|x| (HitResult::Miss, x, 0.0125)))
+            .collect();
+
+        assert_eq!(exp_outcomes.len(), obs_outcomes.len());
+        for (exp, obs) in exp_outcomes.iter().zip(obs_outcomes.iter()) {
+            assert_eq!((&exp.0, exp.1), (&obs.0, obs.1));
+            assert_close(exp.2, obs.2);
+        }
+    }
+
+    #[test]
+    fn test_attack_outcomes_fortune() {
+        // With fortune, a crit needs a natural 20 on either roll, and the bonus die of the kept roll
+        //  is reported
+        let test_roll =
+            RollCollection::parse_user_input("fortune:1d20+1d4", Ruleset::DND5e).unwrap();
+        let obs_outcomes = test_roll.attack_outcomes(100);
+
+        let p_crit = 1.0 - 0.95_f64.powi(2);
+        for (hit_result, _, probability) in obs_outcomes {
+            match hit_result {
+                HitResult::CriticalHit => assert_close(p_crit / 4.0, probability),
+                HitResult::Hit => panic!("Unexpected hit against AC 100"),
+                HitResult::Miss => assert_close((1.0 - p_crit) / 4.0, probability),
+            }
+        }
+    }
+
+    // endregion:
+
+    // region: damage_distribution() tests
+
+    #[test]
+    fn test_damage_distribution_miss() {
+        let test_roll = RollCollection::parse_user_input("1d4+2", Ruleset::DND5e).unwrap();
+
+        let obs_dist = test_roll.damage_distribution(&HitResult::Miss);
+        assert_eq!(Distribution::point(0), obs_dist);
+    }
+
+    #[test]
+    fn test_damage_distribution_hit() {
+        let test_roll = RollCollection::parse_user_input("1d2,1d2+1", Ruleset::DND5e).unwrap();
+
+        let exp_dist = Distribution::new(3, vec![0.25, 0.5, 0.25]);
+        assert_eq!(exp_dist, test_roll.damage_distribution(&HitResult::Hit));
+    }
+
+    #[test]
+    fn test_damage_distribution_crit_dnd() {
+        let test_roll = RollCollection::parse_user_input("1d1+2", Ruleset::DND5e).unwrap();
+
+        let obs_dist = test_roll.damage_distribution(&HitResult::CriticalHit);
+        assert_eq!(Distribution::point(4), obs_dist);
+    }
+
+    #[test]
+    fn test_damage_distribution_crit_pf() {
+        let test_roll = RollCollection::parse_user_input("1d1+2", Ruleset::PF2e).unwrap();
+
+        let obs_dist = test_roll.damage_distribution(&HitResult::CriticalHit);
+        assert_eq!(Distribution::point(6), obs_dist);
+    }
+
+    #[test]
+    fn test_halved_type_damage_distribution() {
+        // The halved damage of each type adds up to the halved total, rounded down once
+        let test_roll =
+            RollCollection::parse_user_input("1d2 fire + 1d2 cold + 1d3 acid", Ruleset::DND5e)
+                .unwrap();
+
+        let obs_mean: f64 = test_roll
+            .damage_types()
+            .iter()
+            .map(|t| test_roll.halved_type_damage_distribution(t).mean())
+            .sum();
+        let exp_mean = test_roll
+            .damage_distribution(&HitResult::Hit)
+            .halve()
+            .mean();
+        assert!((exp_mean - obs_mean).abs() < 1e-9);
+
+        // Only the first of two types dealt an odd amount regains the point lost to rounding
+        let test_roll = RollCollection::parse_user_input("1 fire, 1 cold", Ruleset::DND5e).unwrap();
+        let obs_dist = test_roll.halved_type_damage_distribution(&Some("cold".to_string()));
+        assert_eq!(Distribution::point(1), obs_dist);
+        let obs_dist = test_roll.halved_type_damage_distribution(&Some("fire".to_string()));
+        assert_eq!(Distribution::point(0), obs_dist);
+    }
+
+    #[test]
+    fn test_damage_distribution_on_miss() {
+        let test_roll =
+            RollCollection::parse_user_input("1d4+4,miss:1d2+4", Ruleset::PF2e).unwrap();
+
+        let obs_dist = test_roll.damage_distribution(&HitResult::Miss);
+        assert_eq!(Distribution::uniform(5, 6), obs_dist);
+
+        let obs_dist = test_roll.damage_distribution(&HitResult::Hit);
+        assert_eq!(Distribution::uniform(5, 8), obs_dist);
+    }
+
+    #[test]
+    fn test_type_damage_distribution() {
+        let test_roll =
+            RollCollection::parse_user_input("1d8 slashing + 1d1 fire + 4", Ruleset::DND5e)
+                .unwrap();
+
+        let obs_dist =
+            test_roll.type_damage_distribution(&HitResult::Hit, &Some("fire".to_string()));
+        assert_eq!(Distribution::point(1), obs_dist);
+
+        let obs_dist =
+            test_roll.type_damage_distribution(&HitResult::Hit, &Some("slashing".to_string()));
+        assert_eq!(Distribution::uniform(5, 12), obs_dist);
     }
 
     // endregion:
@@ -0,0 +1,306 @@
+use crate::notation::NotationError;
+use crate::roll_collection::RollCollection;
+use crate::target::Target;
+use crate::{Ruleset, SaveResult};
+
+const EXPECTED_DC: &str = "the DC of the saving throw, then ':' (for example dc17:1d20+5)";
+const EXPECTED_SAVE_ROLL: &str = "a saving throw roll (for example dc17:1d20+5)";
+
+/// A representation of a saving throw made by the target against the attacker's DC
+///
+/// Damage from a saving throw follows the basic save rules, where a success takes half
+/// damage. Under Pathfinder 2e rules, a critical success takes no damage and a critical
+/// failure takes double damage.
+#[derive(Debug, PartialEq)]
+pub struct SavingThrow {
+    dc: i32,
+    save_collection: RollCollection,
+}
+
+impl SavingThrow {
+    /// Creates a new SavingThrow representation of the target's save against a DC.
+    ///
+    /// # Examples
+    /// ```
+    /// // A target with a +5 save against a DC 17 spell
+    /// let save_collection = RollCollection::parse_user_input("1d20+5", Ruleset::DND5e)?;
+    /// let saving_throw = SavingThrow::new(17, save_collection);
+    /// ```
+    pub fn new(dc: i32, save_collection: RollCollection) -> SavingThrow {
+        SavingThrow {
+            dc,
+            save_collection,
+        }
+    }
+
+    /// Take an input string from the user and parse into a SavingThrow.
+    ///
+    /// Accepts the DC of the attacker, prefixed with `dc`, followed by a `:` and the
+    /// notation of the target's saving throw. The saving throw is either written out in
+    /// full, or as the name of one of the saves of the target, which is rolled as a d20
+    /// plus the bonus of that save. Errors in the saving throw notation are reported
+    /// against the full input string.
+    ///
+    /// # Examples
+    /// ```
+    /// // Fireball against a target with a +2 Dexterity save
+    /// let saving_throw = SavingThrow::parse_user_input("dc15:1d20+2", Ruleset::DND5e, &target)?;
+    ///
+    /// // ...or against the Dexterity save of the target
+    /// let saving_throw = SavingThrow::parse_user_input("dc15:dex", Ruleset::DND5e, &target)?;
+    /// ```
+    pub fn parse_user_input(
+        notation: &str,
+        rule_mode: Ruleset,
+        target: &Target,
+    ) -> Result<SavingThrow, NotationError> {
+        let (dc_notation, save_notation) = match notation.split_once(':') {
+            Some(x) => x,
+            None => return Err(NotationError::new(notation, 0, notation, EXPECTED_DC)),
+        };
+
+        let dc = match dc_notation.strip_prefix("dc").map(|x| x.parse::<i32>()) {
+            Some(Ok(x)) => x,
+            _ => return Err(NotationError::new(notation, 0, dc_notation, EXPECTED_DC)),
+        };
+
+        // Replace the name of a save of the target with the roll for that save, keeping any prefixes
+        let (save_prefixes, save_name) = match save_notation.rsplit_once(':') {
+            Some((prefixes, name)) => (format!("{}:", prefixes), name),
+            None => (String::new(), save_notation),
+        };
+        let save_notation = match save_name.chars().all(|c| c.is_ascii_alphabetic()) {
+            true => match target.save_bonus(save_name) {
+                Some(bonus) => format!("{}1d20{:+}", save_prefixes, bonus),
+                None => {
+                    let position = notation.len() - save_name.len();
+                    let expected = match target.saves.is_empty() {
+                        true => EXPECTED_SAVE_ROLL.to_string(),
+                        false => format!(
+                            "a saving throw of the target ({}), or {}",
+                            target.saves.keys().cloned().collect::<Vec<_>>().join(", "),
+                            EXPECTED_SAVE_ROLL
+                        ),
+                    };
+                    return Err(NotationError::new(notation, position, save_name, &expected));
+                }
+            },
+            false => save_notation.to_string(),
+        };
+
+        let prefix_length = dc_notation.len() + 1;
+        let save_collection = RollCollection::parse_user_input(&save_notation, rule_mode)
+            .map_err(|e| e.with_offset(notation, prefix_length))?;
+
+        Ok(SavingThrow::new(dc, save_collection))
+    }
+
+    /// Roll the target's saving throw, returning the target's degree of success.
+    ///
+    /// # Examples
+    /// ```
+    /// let mut saving_throw = SavingThrow::parse_user_input("dc15:1d20+2", Ruleset::DND5e, &target)?;
+    /// let save_result = saving_throw.roll_save();
+    /// ```
+    pub fn roll_save(&mut self) -> SaveResult {
+        self.save_collection.roll_against_difficulty_class(self.dc)
+    }
+
+    /// Return the exact probability of each of the target's degrees of success.
+    ///
+    /// # Examples
+    /// ```
+    /// let saving_throw = SavingThrow::parse_user_input("dc15:1d20+2", Ruleset::DND5e, &target)?;
+    ///
+    /// for (save_result, probability) in saving_throw.save_probabilities() {
+    ///     println!("{:?}: {}", save_result, probability);
+    /// }
+    /// ```
+    pub fn save_probabilities(&self) -> Vec<(SaveResult, f64)> {
+        self.save_collection.save_probabilities(self.dc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_saves() -> Target {
+        Target::from_ac(10)
+    }
+
+    fn spawn_target() -> Target {
+        let mut target = Target::from_ac(10);
+        target.saves.insert("reflex".to_string(), 9);
+        target.saves.insert("will".to_string(), 4);
+        target
+    }
+
+    #[test]
+    fn test_constructor() {
+        let exp_save = SavingThrow {
+            dc: 17,
+            save_collection: RollCollection::parse_user_input("1d20+5", Ruleset::DND5e).unwrap(),
+        };
+
+        let obs_save = SavingThrow::new(
+            17,
+            RollCollection::parse_user_input("1d20+5", Ruleset::DND5e).unwrap(),
+        );
+        assert_eq!(exp_save, obs_save);
+    }
+
+    // region: parse_user_input() tests
+
+    #[test]
+    fn test_parse_user_input() {
+        let exp_save = SavingThrow::new(
+            17,
+            RollCollection::parse_user_input("1d20A+5", Ruleset::DND5e).unwrap(),
+        );
+
+        let obs_save =
+            SavingThrow::parse_user_input("dc17:1d20A+5", Ruleset::DND5e, &no_saves()).unwrap();
+        assert_eq!(exp_save, obs_save);
+    }
+
+    #[test]
+    fn test_parse_user_input_target_save() {
+        let exp_save = SavingThrow::new(
+            21,
+            RollCollection::parse_user_input("fortune:1d20+9", Ruleset::PF2e).unwrap(),
+        );
+
+        let obs_save =
+            SavingThrow::parse_user_input("dc21:fortune:reflex", Ruleset::PF2e, &spawn_target())
+                .unwrap();
+        assert_eq!(exp_save, obs_save);
+    }
+
+    #[test]
+    fn test_parse_user_input_unknown_save() {
+        let obs_result =
+            SavingThrow::parse_user_input("dc21:fortitude", Ruleset::PF2e, &spawn_target());
+        assert_eq!(
+            "Unable to parse notation 'dc21:fortitude': found 'fortitude' at position 5, expected a saving throw of the target (reflex, will), or a saving throw roll (for example dc17:1d20+5)!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_user_input_missing_dc() {
+        let obs_result = SavingThrow::parse_user_input("1d20+5", Ruleset::DND5e, &no_saves());
+        assert_eq!(
+            "Unable to parse notation '1d20+5': found '1d20+5' at position 0, expected the DC of the saving throw, then ':' (for example dc17:1d20+5)!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_user_input_invalid_dc() {
+        let obs_result = SavingThrow::parse_user_input("dcx:1d20+5", Ruleset::DND5e, &no_saves());
+        assert_eq!(
+            "Unable to parse notation 'dcx:1d20+5': found 'dcx' at position 0, expected the DC of the saving throw, then ':' (for example dc17:1d20+5)!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_user_input_invalid_save() {
+        // Test that an error in the save notation is reported against the full input.
+        let obs_result = SavingThrow::parse_user_input("dc17:1d20+5x", Ruleset::DND5e, &no_saves());
+        assert_eq!(11, obs_result.unwrap_err().position);
+    }
+
+    // endregion:
+
+    // region: roll_save() tests
+
+    #[test]
+    fn test_roll_save_dnd() {
+        // Under D&D 5e rules, meeting the DC is a success and there are no critical degrees.
+        let mut saving_throw =
+            SavingThrow::parse_user_input("dc10:1d1+9", Ruleset::DND5e, &no_saves()).unwrap();
+        assert_eq!(SaveResult::Success, saving_throw.roll_save());
+
+        let mut saving_throw =
+            SavingThrow::parse_user_input("dc30:1d1", Ruleset::DND5e, &no_saves()).unwrap();
+        assert_eq!(SaveResult::Failure, saving_throw.roll_save());
+    }
+
+    #[test]
+    fn test_roll_save_pathfinder() {
+        // Under PF2e rules, beating the DC by 10 is a critical success, and missing it by 10 a critical failure.
+        let mut saving_throw =
+            SavingThrow::parse_user_input("dc10:1d1+19", Ruleset::PF2e, &no_saves()).unwrap();
+        assert_eq!(SaveResult::CriticalSuccess, saving_throw.roll_save());
+
+        let mut saving_throw =
+            SavingThrow::parse_user_input("dc10:1d1+9", Ruleset::PF2e, &no_saves()).unwrap();
+        assert_eq!(SaveResult::Success, saving_throw.roll_save());
+
+        let mut saving_throw =
+            SavingThrow::parse_user_input("dc10:1d1+1", Ruleset::PF2e, &no_saves()).unwrap();
+        assert_eq!(SaveResult::Failure, saving_throw.roll_save());
+
+        let mut saving_throw =
+            SavingThrow::parse_user_input("dc10:1d1-1", Ruleset::PF2e, &no_saves()).unwrap();
+        assert_eq!(SaveResult::CriticalFailure, saving_throw.roll_save());
+    }
+
+    // endregion:
+
+    // region: save_probabilities() tests
+
+    fn assert_close(left: f64, right: f64) {
+        assert!((left - right).abs() < 1e-9, "{} != {}", left, right);
+    }
+
+    #[test]
+    fn test_save_probabilities_dnd() {
+        // A +5 save against DC 15 succeeds on a 10 or higher, with natural rolls not special
+        let saving_throw =
+            SavingThrow::parse_user_input("dc15:1d20+5", Ruleset::DND5e, &no_saves()).unwrap();
+        let obs_probabilities = saving_throw.save_probabilities();
+
+        assert_eq!(2, obs_probabilities.len());
+        assert_eq!(SaveResult::Failure, obs_probabilities[0].0);
+        assert_close(0.45, obs_probabilities[0].1);
+        assert_eq!(SaveResult::Success, obs_probabilities[1].0);
+        assert_close(0.55, obs_probabilities[1].1);
+    }
+
+    #[test]
+    fn test_save_probabilities_pathfinder() {
+        // A +10 save against DC 25 critically fails on a 1-5 (including the natural 1 downgrade), fails
+        //  on a 6-14, and succeeds on a 15-19. A natural 20 upgrades the success to a critical success.
+        let saving_throw =
+            SavingThrow::parse_user_input("dc25:1d20+10", Ruleset::PF2e, &no_saves()).unwrap();
+        let obs_probabilities = saving_throw.save_probabilities();
+
+        let exp_probabilities = [
+            (SaveResult::CriticalFailure, 0.25),
+            (SaveResult::Failure, 0.45),
+            (SaveResult::Success, 0.25),
+            (SaveResult::CriticalSuccess, 0.05),
+        ];
+        for (exp, obs) in exp_probabilities.iter().zip(obs_probabilities.iter()) {
+            assert_eq!(exp.0, obs.0);
+            assert_close(exp.1, obs.1);
+        }
+    }
+
+    #[test]
+    fn test_save_probabilities_fortune() {
+        // A target rolling its save with advantage fails only if both rolls fail
+        let saving_throw =
+            SavingThrow::parse_user_input("dc15:fortune:1d20+5", Ruleset::DND5e, &no_saves())
+                .unwrap();
+        let obs_probabilities = saving_throw.save_probabilities();
+
+        assert_close(0.45 * 0.45, obs_probabilities[0].1);
+        assert_close(1.0 - 0.45 * 0.45, obs_probabilities[1].1);
+    }
+
+    // endregion:
+}
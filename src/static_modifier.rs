@@ -5,6 +5,7 @@ use crate::{HitResult, ModifierBehaviour};
 pub struct StaticModifier {
     value: i32,
     mod_behaviour: ModifierBehaviour,
+    pub damage_type: Option<String>,
 }
 
 impl StaticModifier {
@@ -19,9 +20,21 @@ impl StaticModifier {
         StaticModifier {
             value,
             mod_behaviour,
+            damage_type: None,
         }
     }
 
+    /// Set the type of the damage dealt by the modifier, or None for untyped damage.
+    ///
+    /// # Examples
+    /// ```
+    /// let my_modifier = StaticModifier::new(4, ModifierBehaviour::OnHit).set_damage_type(Some(String::from("fire")));
+    /// ```
+    pub fn set_damage_type(mut self, damage_type: Option<String>) -> Self {
+        self.damage_type = damage_type;
+        self
+    }
+
     /// Assess the value of the modifier for an instance of application.
     ///
     /// Accepts an optional hit result for modulating the return result based
@@ -46,7 +59,7 @@ impl StaticModifier {
                 (&ModifierBehaviour::OnHit, &HitResult::Hit | &HitResult::CriticalHit) => {
                     self.value
                 }
-                (&ModifierBehaviour::OnMiss, _) => self.value,
+                (&ModifierBehaviour::OnMiss, &HitResult::Miss) => self.value,
                 (_, _) => 0,
             }
         } else {
@@ -64,6 +77,7 @@ mod tests {
         let exp_value = StaticModifier {
             value: -2,
             mod_behaviour: ModifierBehaviour::OnHit,
+            damage_type: None,
         };
         let obs_value = StaticModifier::new(-2, ModifierBehaviour::OnHit);
 
@@ -86,6 +100,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_set_damage_type() {
+        let my_modifier = StaticModifier::new(4, ModifierBehaviour::OnHit)
+            .set_damage_type(Some("fire".to_string()));
+
+        assert_eq!(Some("fire".to_string()), my_modifier.damage_type);
+    }
+
     // region: `evaluate_result()` ModifierBehaviour::OnHit tests
 
     #[test]
@@ -177,7 +199,7 @@ mod tests {
         let my_modifier = StaticModifier::new(5, ModifierBehaviour::OnMiss);
         let obs_value = my_modifier.evaluate_result(Some(&HitResult::CriticalHit));
 
-        assert_eq!(5, obs_value);
+        assert_eq!(0, obs_value);
     }
 
     #[test]
@@ -185,7 +207,7 @@ mod tests {
         let my_modifier = StaticModifier::new(5, ModifierBehaviour::OnMiss);
         let obs_value = my_modifier.evaluate_result(Some(&HitResult::Hit));
 
-        assert_eq!(5, obs_value);
+        assert_eq!(0, obs_value);
     }
 
     #[test]
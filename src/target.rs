@@ -0,0 +1,216 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::damage::TypedDamage;
+use crate::Ruleset;
+
+/// A representation of the creature being attacked, and its defences
+///
+/// Saving throw bonuses are named freely, such as `reflex` in Pathfinder 2e or `dex` in
+/// D&D 5e, and are referred to by name in the notation of a saving throw. Resistances
+/// and weaknesses are recorded as an amount for each damage type, which only matters
+/// under the Pathfinder 2e rules, and immunities as a list of damage types. Untyped
+/// damage ignores all defences. A target given hit points is fought in encounters
+/// lasting until it drops.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Target {
+    pub name: Option<String>,
+    pub ac: i32,
+    pub hp: Option<i32>,
+    #[serde(default)]
+    pub saves: BTreeMap<String, i32>,
+    #[serde(default)]
+    pub resistances: BTreeMap<String, i32>,
+    #[serde(default)]
+    pub weaknesses: BTreeMap<String, i32>,
+    #[serde(default)]
+    pub immunities: Vec<String>,
+}
+
+impl Target {
+    /// Creates a new unnamed Target with only an Armour Class, and no other defences.
+    ///
+    /// # Examples
+    /// ```
+    /// let target = Target::from_ac(15);
+    /// ```
+    pub fn from_ac(ac: i32) -> Target {
+        Target {
+            name: None,
+            ac,
+            hp: None,
+            saves: BTreeMap::new(),
+            resistances: BTreeMap::new(),
+            weaknesses: BTreeMap::new(),
+            immunities: Vec::new(),
+        }
+    }
+
+    /// Return the bonus of the named saving throw, if the target has one.
+    ///
+    /// # Examples
+    /// ```
+    /// let reflex_bonus = target.save_bonus("reflex");
+    /// ```
+    pub fn save_bonus(&self, save_name: &str) -> Option<i32> {
+        self.saves.get(save_name).copied()
+    }
+
+    /// Return true if the target has any resistances, weaknesses, or immunities.
+    ///
+    /// # Examples
+    /// ```
+    /// assert!(!Target::from_ac(15).has_defences());
+    /// ```
+    pub fn has_defences(&self) -> bool {
+        !(self.resistances.is_empty() && self.weaknesses.is_empty() && self.immunities.is_empty())
+    }
+
+    /// Return the damage taken by the target from a single instance of damage.
+    ///
+    /// Defences are applied to each damage type separately, and immunity to a type removes
+    /// all of its damage. Under the Pathfinder 2e rules any damage of a type is increased by
+    /// the weakness to that type and reduced by the resistance to it, to a minimum of zero.
+    /// Under the D&D 5e rules the amounts are ignored, and damage of a type is halved
+    /// (rounding down) by resistance to it and then doubled by vulnerability to it.
+    ///
+    /// # Examples
+    /// ```
+    /// // A target with weakness 5 to fire takes 8 damage from 3 fire damage in Pathfinder 2e
+    /// let damage_taken = target.apply_defences(&hit_damage, Ruleset::PF2e);
+    /// ```
+    pub fn apply_defences(&self, damage: &TypedDamage, ruleset: Ruleset) -> TypedDamage {
+        let mut damage_taken = TypedDamage::new();
+        for (damage_type, value) in damage.iter() {
+            let value = match damage_type {
+                Some(t) if self.immunities.contains(t) => 0,
+                Some(t) if value > 0 => {
+                    let weakness = self.weaknesses.get(t).copied().unwrap_or(0);
+                    let resistance = self.resistances.get(t).copied().unwrap_or(0);
+                    match ruleset {
+                        Ruleset::PF2e => (value + weakness - resistance).max(0),
+                        Ruleset::DND5e | Ruleset::DND5eNoAutoMiss => {
+                            let resisted = match resistance > 0 {
+                                true => value / 2,
+                                false => value,
+                            };
+                            match weakness > 0 {
+                                true => resisted * 2,
+                                false => resisted,
+                            }
+                        }
+                    }
+                }
+                _ => value,
+            };
+            damage_taken.add(damage_type, value);
+        }
+        damage_taken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_ac() {
+        let exp_target = Target {
+            name: None,
+            ac: 15,
+            hp: None,
+            saves: BTreeMap::new(),
+            resistances: BTreeMap::new(),
+            weaknesses: BTreeMap::new(),
+            immunities: Vec::new(),
+        };
+
+        assert_eq!(exp_target, Target::from_ac(15));
+    }
+
+    #[test]
+    fn test_save_bonus() {
+        let mut target = Target::from_ac(15);
+        target.saves.insert("reflex".to_string(), 9);
+
+        assert_eq!(Some(9), target.save_bonus("reflex"));
+        assert_eq!(None, target.save_bonus("will"));
+    }
+
+    fn typed(damage_type: &str) -> Option<String> {
+        Some(damage_type.to_string())
+    }
+
+    fn spawn_target() -> Target {
+        let mut target = Target::from_ac(15);
+        target.resistances.insert("fire".to_string(), 5);
+        target.weaknesses.insert("cold".to_string(), 3);
+        target.weaknesses.insert("fire".to_string(), 2);
+        target.immunities.push("poison".to_string());
+        target
+    }
+
+    #[test]
+    fn test_has_defences() {
+        assert!(!Target::from_ac(15).has_defences());
+        assert!(spawn_target().has_defences());
+    }
+
+    #[test]
+    fn test_apply_defences() {
+        let mut damage = TypedDamage::new();
+        damage.add(&typed("fire"), 10);
+        damage.add(&typed("cold"), 4);
+        damage.add(&typed("poison"), 6);
+        damage.add(&None, 2);
+
+        let obs_damage = spawn_target().apply_defences(&damage, Ruleset::PF2e);
+        assert_eq!(7, obs_damage.get(&typed("fire")));
+        assert_eq!(7, obs_damage.get(&typed("cold")));
+        assert_eq!(0, obs_damage.get(&typed("poison")));
+        assert_eq!(2, obs_damage.get(&None));
+    }
+
+    #[test]
+    fn test_apply_defences_minimum_zero() {
+        // Resistance cannot reduce damage below zero, and weakness only applies if damage is dealt
+        let mut damage = TypedDamage::new();
+        damage.add(&typed("fire"), 1);
+        damage.add(&typed("cold"), 0);
+
+        let obs_damage = spawn_target().apply_defences(&damage, Ruleset::PF2e);
+        assert_eq!(0, obs_damage.get(&typed("fire")));
+        assert_eq!(0, obs_damage.get(&typed("cold")));
+    }
+
+    #[test]
+    fn test_apply_defences_dnd() {
+        // Fire is resisted and then doubled by vulnerability, with the odd point rounded away
+        let mut damage = TypedDamage::new();
+        damage.add(&typed("fire"), 11);
+        damage.add(&typed("cold"), 4);
+        damage.add(&typed("poison"), 6);
+        damage.add(&None, 2);
+
+        let obs_damage = spawn_target().apply_defences(&damage, Ruleset::DND5e);
+        assert_eq!(10, obs_damage.get(&typed("fire")));
+        assert_eq!(8, obs_damage.get(&typed("cold")));
+        assert_eq!(0, obs_damage.get(&typed("poison")));
+        assert_eq!(2, obs_damage.get(&None));
+    }
+
+    #[test]
+    fn test_apply_defences_dnd_resistance() {
+        // Resistance halves damage rounding down, whatever its amount
+        let mut target = Target::from_ac(15);
+        target.resistances.insert("fire".to_string(), 5);
+        let mut damage = TypedDamage::new();
+        damage.add(&typed("fire"), 7);
+        damage.add(&typed("cold"), 7);
+
+        let obs_damage = target.apply_defences(&damage, Ruleset::DND5eNoAutoMiss);
+        assert_eq!(3, obs_damage.get(&typed("fire")));
+        assert_eq!(7, obs_damage.get(&typed("cold")));
+    }
+}
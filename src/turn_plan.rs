@@ -0,0 +1,255 @@
+use serde::Deserialize;
+use simple_error::bail;
+use std::error::Error;
+
+/// The number of actions available to a Pathfinder 2e character each turn
+const ACTION_BUDGET: i32 = 3;
+
+/// The multiple attack penalty added by each earlier attack in the turn
+const MAP_STEP: i32 = 5;
+
+/// The multiple attack penalty added by each earlier attack in the turn, for an agile weapon
+const AGILE_MAP_STEP: i32 = 4;
+
+/// The number of earlier attacks after which the multiple attack penalty stops increasing
+const MAX_MAP_STEPS: i32 = 2;
+
+/// A single Strike or multi-action activity planned for a Pathfinder 2e turn
+///
+/// Each planned action is rolled as a single attack, and counts as one attack towards the
+/// multiple attack penalty. Activities which deal the damage of several Strikes, such as
+/// Double Slice or Flurry of Blows, combine the damage into a single damage notation.
+#[derive(Debug, PartialEq)]
+pub struct PlannedAction {
+    cost: i32,
+    agile: bool,
+    damage: String,
+}
+
+impl PlannedAction {
+    /// Take an input string from the user and parse into a PlannedAction.
+    ///
+    /// Accepts a damage notation string, optionally prefixed with the number of actions
+    /// taken by the activity (`2a:` or `3a:`, otherwise one action) and whether the
+    /// weapon is agile (`agile:`). The damage notation itself is not parsed until the
+    /// attack is rolled.
+    ///
+    /// # Examples
+    /// ```
+    /// // A Strike with an agile weapon
+    /// let action = PlannedAction::parse_user_input("agile:1d6+4")?;
+    ///
+    /// // Double Slice, combining the damage of both weapons
+    /// let action = PlannedAction::parse_user_input("2a:1d8+4,1d6+4")?;
+    /// ```
+    pub fn parse_user_input(notation: &str) -> Result<PlannedAction, Box<dyn Error>> {
+        let mut cost = 1;
+        let mut agile = false;
+        let mut damage_notation = notation;
+        while let Some((prefix, remainder)) = damage_notation.split_once(':') {
+            match (prefix, prefix.strip_suffix('a').map(str::parse::<i32>)) {
+                ("agile", _) => agile = true,
+                (_, Some(Ok(n))) => cost = n,
+                (_, _) => break,
+            }
+            damage_notation = remainder;
+        }
+
+        if !(1..=ACTION_BUDGET).contains(&cost) {
+            bail!(
+                "Unable to plan action '{}': an action must take between 1 and {} actions!",
+                notation,
+                ACTION_BUDGET
+            );
+        }
+
+        Ok(PlannedAction {
+            cost,
+            agile,
+            damage: damage_notation.to_string(),
+        })
+    }
+
+    /// Return the multiple attack penalty of the action, after a number of earlier attacks.
+    ///
+    /// # Examples
+    /// ```
+    /// let action = PlannedAction::parse_user_input("agile:1d6+4")?;
+    /// assert_eq!(8, action.attack_penalty(2));
+    /// ```
+    fn attack_penalty(&self, previous_attacks: i32) -> i32 {
+        let map_step = match self.agile {
+            true => AGILE_MAP_STEP,
+            false => MAP_STEP,
+        };
+        map_step * previous_attacks.min(MAX_MAP_STEPS)
+    }
+}
+
+/// A representation of the Strikes and activities of a Pathfinder 2e turn
+///
+/// The planner applies the multiple attack penalty to each attack from the base attack
+/// bonus of the character, and ensures that the turn fits within the three-action budget.
+/// The actions are given in the notation of PlannedAction::parse_user_input().
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TurnPlan {
+    pub attack_bonus: i32,
+    pub actions: Vec<String>,
+}
+
+impl TurnPlan {
+    /// Creates a new TurnPlan from a base attack bonus and the actions taken.
+    ///
+    /// # Examples
+    /// ```
+    /// let turn_plan = TurnPlan::new(11, vec![String::from("2a:2d12+4"), String::from("1d12+4")]);
+    /// ```
+    pub fn new(attack_bonus: i32, actions: Vec<String>) -> TurnPlan {
+        TurnPlan {
+            attack_bonus,
+            actions,
+        }
+    }
+
+    /// Produce the to-hit and damage notation of each attack made in the turn.
+    ///
+    /// The first attack of the turn is rolled at the base attack bonus, the second with
+    /// a -5 penalty (-4 for an agile weapon), and any later attacks with a -10 penalty
+    /// (-8 for an agile weapon). Returns an error if an action cannot be parsed, or if
+    /// the actions take more than three actions in total.
+    ///
+    /// # Examples
+    /// ```
+    /// let turn_plan = TurnPlan::new(8, vec![String::from("1d8+4"), String::from("agile:1d6+4")]);
+    /// let (hit_details, weapon_details) = turn_plan.plan_attacks()?;
+    ///
+    /// assert_eq!(vec!["1d20+8", "1d20+4"], hit_details);
+    /// ```
+    pub fn plan_attacks(&self) -> Result<(Vec<String>, Vec<String>), Box<dyn Error>> {
+        let planned_actions = self
+            .actions
+            .iter()
+            .map(|a| PlannedAction::parse_user_input(a))
+            .collect::<Result<Vec<PlannedAction>, Box<dyn Error>>>()?;
+
+        let total_cost: i32 = planned_actions.iter().map(|a| a.cost).sum();
+        if total_cost > ACTION_BUDGET {
+            bail!(
+                "The turn plan takes {} actions, but only {} are available each turn!",
+                total_cost,
+                ACTION_BUDGET
+            );
+        }
+
+        Ok(planned_actions
+            .into_iter()
+            .zip(0..)
+            .map(|(action, previous_attacks)| {
+                let attack_bonus = self.attack_bonus - action.attack_penalty(previous_attacks);
+                (format!("1d20{:+}", attack_bonus), action.damage)
+            })
+            .unzip())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_turn_plan(attack_bonus: i32, actions: Vec<&str>) -> TurnPlan {
+        TurnPlan::new(
+            attack_bonus,
+            actions.into_iter().map(|a| a.to_string()).collect(),
+        )
+    }
+
+    // region: parse_user_input() tests
+
+    #[test]
+    fn test_parse_user_input_default() {
+        let exp_action = PlannedAction {
+            cost: 1,
+            agile: false,
+            damage: "1d8+4".to_string(),
+        };
+
+        let obs_action = PlannedAction::parse_user_input("1d8+4").unwrap();
+        assert_eq!(exp_action, obs_action);
+    }
+
+    #[test]
+    fn test_parse_user_input_prefixes() {
+        let exp_action = PlannedAction {
+            cost: 2,
+            agile: true,
+            damage: "1d6+4,1d6+4".to_string(),
+        };
+
+        let obs_action = PlannedAction::parse_user_input("2a:agile:1d6+4,1d6+4").unwrap();
+        assert_eq!(exp_action, obs_action);
+
+        let obs_action = PlannedAction::parse_user_input("agile:2a:1d6+4,1d6+4").unwrap();
+        assert_eq!(exp_action, obs_action);
+    }
+
+    #[test]
+    fn test_parse_user_input_unknown_prefix() {
+        // An unknown prefix is left in the damage notation, to be reported when parsed
+        let obs_action = PlannedAction::parse_user_input("agle:1d6").unwrap();
+        assert_eq!("agle:1d6", obs_action.damage);
+    }
+
+    #[test]
+    fn test_parse_user_input_invalid_cost() {
+        let obs_result = PlannedAction::parse_user_input("4a:1d8");
+        assert_eq!(
+            "Unable to plan action '4a:1d8': an action must take between 1 and 3 actions!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    // endregion:
+
+    // region: plan_attacks() tests
+
+    #[test]
+    fn test_plan_attacks_map() {
+        let turn_plan = spawn_turn_plan(8, vec!["1d8+4"; 3]);
+
+        let (obs_hit, obs_dmg) = turn_plan.plan_attacks().unwrap();
+        assert_eq!(vec!["1d20+8", "1d20+3", "1d20-2"], obs_hit);
+        assert_eq!(vec!["1d8+4"; 3], obs_dmg);
+    }
+
+    #[test]
+    fn test_plan_attacks_agile() {
+        let turn_plan = spawn_turn_plan(8, vec!["agile:1d6+4"; 3]);
+
+        let (obs_hit, _) = turn_plan.plan_attacks().unwrap();
+        assert_eq!(vec!["1d20+8", "1d20+4", "1d20+0"], obs_hit);
+    }
+
+    #[test]
+    fn test_plan_attacks_activity() {
+        // A two-action activity counts as a single attack towards the penalty
+        let turn_plan = spawn_turn_plan(11, vec!["2a:2d12+4", "1d12+4"]);
+
+        let (obs_hit, obs_dmg) = turn_plan.plan_attacks().unwrap();
+        assert_eq!(vec!["1d20+11", "1d20+6"], obs_hit);
+        assert_eq!(vec!["2d12+4", "1d12+4"], obs_dmg);
+    }
+
+    #[test]
+    fn test_plan_attacks_over_budget() {
+        let turn_plan = spawn_turn_plan(8, vec!["1d8+4"; 4]);
+
+        let obs_result = turn_plan.plan_attacks();
+        assert_eq!(
+            "The turn plan takes 4 actions, but only 3 are available each turn!",
+            obs_result.unwrap_err().to_string()
+        );
+    }
+
+    // endregion:
+}